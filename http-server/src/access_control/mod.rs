@@ -49,14 +49,14 @@ pub struct AccessControl {
 impl AccessControl {
 	/// Validate incoming request by http HOST
 	pub fn deny_host(&self, request: &hyper::Request<hyper::Body>) -> bool {
-		!hosts::is_host_valid(http_helpers::read_header_value(request.headers(), "host"), &self.allowed_hosts)
+		!hosts::is_host_valid(request_host(request), &self.allowed_hosts)
 	}
 
 	/// Validate incoming request by CORS origin
 	pub fn deny_cors_origin(&self, request: &hyper::Request<hyper::Body>) -> bool {
 		let header = cors::get_cors_allow_origin(
 			http_helpers::read_header_value(request.headers(), "origin"),
-			http_helpers::read_header_value(request.headers(), "host"),
+			request_host(request),
 			&self.allowed_origins,
 		)
 		.map(|origin| {
@@ -87,6 +87,12 @@ impl AccessControl {
 	}
 }
 
+/// Host of the request, from the `Host` header or from the `:authority` pseudo-header for HTTP/2, which hyper
+/// puts in the URI.
+fn request_host(request: &hyper::Request<hyper::Body>) -> Option<&str> {
+	http_helpers::read_header_value(request.headers(), "host").or_else(|| request.uri().authority().map(|a| a.as_str()))
+}
+
 impl Default for AccessControl {
 	fn default() -> Self {
 		Self {
@@ -0,0 +1,249 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Content encoding of HTTP request and response bodies.
+
+use std::io::{self, Read, Write};
+
+use crate::types::error::GenericTransportError;
+use hyper::header::{HeaderMap, ACCEPT_ENCODING, CONTENT_ENCODING};
+use jsonrpsee_utils::http_helpers::read_raw_body;
+
+/// Brotli compression quality, between 0 and 11.
+const BROTLI_QUALITY: u32 = 5;
+/// Brotli window size, as a power of two.
+const BROTLI_LG_WINDOW_SIZE: u32 = 22;
+/// Size of the buffers used by the brotli encoder and decoder.
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// Content encodings supported for request and response bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ContentEncoding {
+	/// Brotli.
+	Br,
+	/// Gzip.
+	Gzip,
+	/// Zlib, named `deflate` in HTTP.
+	Deflate,
+}
+
+impl ContentEncoding {
+	/// Encodings in order of preference when the client accepts several with the same quality.
+	const PREFERENCE: [ContentEncoding; 3] = [ContentEncoding::Br, ContentEncoding::Gzip, ContentEncoding::Deflate];
+
+	fn from_name(name: &str) -> Option<Self> {
+		Self::PREFERENCE.iter().copied().find(|encoding| name.eq_ignore_ascii_case(encoding.as_str()))
+	}
+
+	/// Returns the name of the encoding, as used in the `Content-Encoding` header.
+	pub(crate) fn as_str(&self) -> &'static str {
+		match self {
+			ContentEncoding::Br => "br",
+			ContentEncoding::Gzip => "gzip",
+			ContentEncoding::Deflate => "deflate",
+		}
+	}
+
+	/// Compress `data`.
+	pub(crate) fn encode(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+		let buf = Vec::with_capacity(data.len() / 4);
+
+		match self {
+			ContentEncoding::Br => {
+				let mut encoder =
+					brotli::CompressorWriter::new(buf, BROTLI_BUFFER_SIZE, BROTLI_QUALITY, BROTLI_LG_WINDOW_SIZE);
+				encoder.write_all(data)?;
+				Ok(encoder.into_inner())
+			}
+			ContentEncoding::Gzip => {
+				let mut encoder = flate2::write::GzEncoder::new(buf, flate2::Compression::fast());
+				encoder.write_all(data)?;
+				encoder.finish()
+			}
+			ContentEncoding::Deflate => {
+				let mut encoder = flate2::write::ZlibEncoder::new(buf, flate2::Compression::fast());
+				encoder.write_all(data)?;
+				encoder.finish()
+			}
+		}
+	}
+
+	/// Decompress `data`, failing with [`GenericTransportError::TooLarge`] if the decompressed data exceeds
+	/// `max_size` bytes.
+	pub(crate) fn decode(&self, data: &[u8], max_size: u32) -> Result<Vec<u8>, GenericTransportError<io::Error>> {
+		let reader: Box<dyn Read + '_> = match self {
+			ContentEncoding::Br => Box::new(brotli::Decompressor::new(data, BROTLI_BUFFER_SIZE)),
+			ContentEncoding::Gzip => Box::new(flate2::read::GzDecoder::new(data)),
+			ContentEncoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(data)),
+		};
+
+		// Read one byte more than allowed to detect oversized bodies without decompressing them entirely.
+		let mut decoded = Vec::with_capacity(data.len().saturating_mul(4).min(max_size as usize));
+		reader.take(max_size as u64 + 1).read_to_end(&mut decoded).map_err(|_| GenericTransportError::Malformed)?;
+
+		if decoded.len() > max_size as usize {
+			return Err(GenericTransportError::TooLarge);
+		}
+
+		Ok(decoded)
+	}
+}
+
+/// Read and decode a request body compressed with `encoding`. Both the compressed and the decompressed body
+/// must fit within `max_request_body_size`.
+///
+/// Returns the same output as [`read_body`](jsonrpsee_utils::http_helpers::read_body).
+pub(crate) async fn read_encoded_body(
+	headers: &HeaderMap,
+	body: hyper::Body,
+	max_request_body_size: u32,
+	encoding: ContentEncoding,
+) -> Result<(Vec<u8>, bool), GenericTransportError<hyper::Error>> {
+	let encoded = read_raw_body(headers, body, max_request_body_size).await?;
+
+	let decoded = match encoding.decode(&encoded, max_request_body_size) {
+		Ok(decoded) => decoded,
+		Err(GenericTransportError::TooLarge) => return Err(GenericTransportError::TooLarge),
+		Err(_) => return Err(GenericTransportError::Malformed),
+	};
+
+	let single = match decoded.first() {
+		Some(b'{') => true,
+		Some(b'[') => false,
+		_ => return Err(GenericTransportError::Malformed),
+	};
+
+	Ok((decoded, single))
+}
+
+/// Returns the encoding of a request body from its `Content-Encoding` header, `Ok(None)` if the body
+/// is not encoded and `Err(())` if the encoding is not supported.
+pub(crate) fn request_encoding(headers: &HeaderMap) -> Result<Option<ContentEncoding>, ()> {
+	let name = match headers.get(CONTENT_ENCODING).map(|val| val.to_str()) {
+		None => return Ok(None),
+		Some(Ok(name)) => name.trim(),
+		Some(Err(_)) => return Err(()),
+	};
+
+	if name.eq_ignore_ascii_case("identity") {
+		return Ok(None);
+	}
+
+	ContentEncoding::from_name(name).map(Some).ok_or(())
+}
+
+/// Picks the encoding of a response from the request's `Accept-Encoding` header: the supported encoding with
+/// the highest quality value, or `None` if the client accepts none of them.
+pub(crate) fn negotiate(headers: &HeaderMap) -> Option<ContentEncoding> {
+	let mut best: Option<(ContentEncoding, f32)> = None;
+
+	for value in headers.get_all(ACCEPT_ENCODING).iter().filter_map(|val| val.to_str().ok()) {
+		for item in value.split(',') {
+			let mut parts = item.split(';');
+			let name = parts.next().unwrap_or_default().trim();
+			let quality = parts
+				.filter_map(|param| param.trim().strip_prefix("q="))
+				.next()
+				.map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+
+			let quality = match quality {
+				Some(q) if q > 0.0 => q,
+				_ => continue,
+			};
+
+			let encoding = match ContentEncoding::from_name(name) {
+				Some(encoding) => encoding,
+				None if name == "*" => ContentEncoding::PREFERENCE[0],
+				None => continue,
+			};
+
+			let is_better = match best {
+				None => true,
+				Some((current, current_q)) => {
+					quality > current_q || (quality == current_q && preference(encoding) < preference(current))
+				}
+			};
+
+			if is_better {
+				best = Some((encoding, quality));
+			}
+		}
+	}
+
+	best.map(|(encoding, _)| encoding)
+}
+
+fn preference(encoding: ContentEncoding) -> usize {
+	ContentEncoding::PREFERENCE.iter().position(|e| *e == encoding).expect("all encodings are listed; qed")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{negotiate, request_encoding, ContentEncoding};
+	use crate::types::error::GenericTransportError;
+	use hyper::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING};
+
+	fn accept(value: &'static str) -> HeaderMap {
+		let mut headers = HeaderMap::new();
+		headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(value));
+		headers
+	}
+
+	#[test]
+	fn negotiate_works() {
+		assert_eq!(negotiate(&HeaderMap::new()), None);
+		assert_eq!(negotiate(&accept("identity")), None);
+		assert_eq!(negotiate(&accept("gzip")), Some(ContentEncoding::Gzip));
+		assert_eq!(negotiate(&accept("deflate, gzip, br")), Some(ContentEncoding::Br));
+		assert_eq!(negotiate(&accept("br;q=0.5, gzip;q=0.8")), Some(ContentEncoding::Gzip));
+		assert_eq!(negotiate(&accept("gzip;q=0, deflate")), Some(ContentEncoding::Deflate));
+		assert_eq!(negotiate(&accept("*")), Some(ContentEncoding::Br));
+	}
+
+	#[test]
+	fn request_encoding_works() {
+		let mut headers = HeaderMap::new();
+		assert_eq!(request_encoding(&headers), Ok(None));
+		headers.insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+		assert_eq!(request_encoding(&headers), Ok(None));
+		headers.insert(CONTENT_ENCODING, HeaderValue::from_static("GZIP"));
+		assert_eq!(request_encoding(&headers), Ok(Some(ContentEncoding::Gzip)));
+		headers.insert(CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+		assert_eq!(request_encoding(&headers), Err(()));
+	}
+
+	#[test]
+	fn roundtrip_works() {
+		let data = "jsonrpsee".repeat(100);
+
+		for encoding in ContentEncoding::PREFERENCE.iter() {
+			let encoded = encoding.encode(data.as_bytes()).unwrap();
+			assert!(encoded.len() < data.len());
+			assert_eq!(encoding.decode(&encoded, 900).unwrap(), data.as_bytes());
+			assert!(matches!(encoding.decode(&encoded, 899), Err(GenericTransportError::TooLarge)));
+		}
+	}
+}
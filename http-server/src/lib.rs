@@ -31,6 +31,7 @@
 //! `jsonrpsee-http-server` is a [JSON RPC](https://www.jsonrpc.org/specification) HTTPS server library that's is built for `async/await`.
 
 mod access_control;
+mod compression;
 mod server;
 
 /// Common builders for RPC responses.
@@ -42,6 +43,7 @@ pub use access_control::{
 	AccessControl, AccessControlBuilder,
 };
 pub use jsonrpsee_types as types;
+pub use jsonrpsee_utils::server::access_log::{AccessLog, AccessLogWriter};
 pub use jsonrpsee_utils::server::rpc_module::RpcModule;
 pub use server::{Builder as HttpServerBuilder, Server as HttpServer, ServerHandle as HttpServerHandle};
 pub use tracing;
@@ -26,10 +26,15 @@
 
 //! Contains common builders for hyper responses.
 
+use std::io;
+
 use crate::types::v2::{ErrorCode, Id, RpcError};
+use jsonrpsee_utils::server::helpers::StreamingResponse;
 
 const JSON: &str = "application/json; charset=utf-8";
 const TEXT: &str = "text/plain";
+/// Size of the chunks a streaming response body is sent in.
+const STREAMING_CHUNK_SIZE: usize = 64 * 1024;
 
 /// Create a response for json internal error.
 pub fn internal_error() -> hyper::Response<hyper::Body> {
@@ -87,6 +92,15 @@ pub fn malformed() -> hyper::Response<hyper::Body> {
 	from_template(hyper::StatusCode::BAD_REQUEST, error, JSON)
 }
 
+/// Create a text/plain response for request bodies compressed with an unsupported encoding (415)
+pub fn unsupported_content_encoding() -> hyper::Response<hyper::Body> {
+	from_template(
+		hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+		"Unsupported Content-Encoding. Supported encodings are br, gzip and deflate\n".to_owned(),
+		TEXT,
+	)
+}
+
 /// Create a response body.
 fn from_template<S: Into<hyper::Body>>(
 	status: hyper::StatusCode,
@@ -102,7 +116,75 @@ fn from_template<S: Into<hyper::Body>>(
 		.expect("Unable to parse response body for type conversion")
 }
 
+/// Create a JSON response with a custom status code, used to report errors of methods exposed over HTTP GET.
+pub fn error_response(status: hyper::StatusCode, body: String) -> hyper::Response<hyper::Body> {
+	from_template(status, body, JSON)
+}
+
 /// Create a valid JSON response.
 pub fn ok_response(body: String) -> hyper::Response<hyper::Body> {
 	from_template(hyper::StatusCode::OK, body, JSON)
 }
+
+/// Create a valid JSON response with a body compressed with `encoding`.
+pub fn ok_encoded_response(body: Vec<u8>, encoding: &'static str) -> hyper::Response<hyper::Body> {
+	let mut response = from_template(hyper::StatusCode::OK, body, JSON);
+	let headers = response.headers_mut();
+	headers.insert(hyper::header::CONTENT_ENCODING, hyper::header::HeaderValue::from_static(encoding));
+	headers.insert(hyper::header::VARY, hyper::header::HeaderValue::from_static("accept-encoding"));
+	response
+}
+
+/// Create a valid JSON response whose body is serialized on a blocking thread and sent in chunks as it is
+/// produced. The body is aborted if the serialization fails or exceeds the maximum response size.
+pub fn streaming_response(response: StreamingResponse) -> hyper::Response<hyper::Body> {
+	let (sender, body) = hyper::Body::channel();
+	let mut writer = ChunkedBodyWriter {
+		runtime: tokio::runtime::Handle::current(),
+		sender,
+		buf: Vec::with_capacity(STREAMING_CHUNK_SIZE),
+	};
+
+	tokio::task::spawn_blocking(move || {
+		if let Err(err) = response.write_to(&mut writer) {
+			tracing::error!("Aborting streaming response: {:?}", err);
+			writer.sender.abort();
+		}
+	});
+
+	from_template(hyper::StatusCode::OK, body, JSON)
+}
+
+/// Writer that sends the data written to it to a [`hyper::Body`] in chunks of [`STREAMING_CHUNK_SIZE`] bytes.
+struct ChunkedBodyWriter {
+	runtime: tokio::runtime::Handle,
+	sender: hyper::body::Sender,
+	buf: Vec<u8>,
+}
+
+impl ChunkedBodyWriter {
+	fn send_chunk(&mut self) -> io::Result<()> {
+		let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(STREAMING_CHUNK_SIZE));
+		self.runtime
+			.block_on(self.sender.send_data(chunk.into()))
+			.map_err(|err| io::Error::new(io::ErrorKind::BrokenPipe, err))
+	}
+}
+
+impl io::Write for ChunkedBodyWriter {
+	fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+		self.buf.extend_from_slice(data);
+		if self.buf.len() >= STREAMING_CHUNK_SIZE {
+			self.send_chunk()?;
+		}
+		Ok(data.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		if self.buf.is_empty() {
+			Ok(())
+		} else {
+			self.send_chunk()
+		}
+	}
+}
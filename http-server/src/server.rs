@@ -24,23 +24,27 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::{response, AccessControl};
+use crate::{compression, response, AccessControl};
 use futures_channel::mpsc;
 use futures_util::{future::join_all, stream::StreamExt, FutureExt};
 use hyper::{
-	server::{conn::AddrIncoming, Builder as HyperBuilder},
+	server::{
+		conn::{AddrIncoming, AddrStream},
+		Builder as HyperBuilder,
+	},
 	service::{make_service_fn, service_fn},
 	Error as HyperError,
 };
 use jsonrpsee_types::{
 	error::{Error, GenericTransportError},
 	middleware::Middleware,
-	v2::{ErrorCode, Id, Notification, Request},
+	v2::{ErrorCode, Id, Notification, Request, Response, RpcError},
 	TEN_MB_SIZE_BYTES,
 };
 use jsonrpsee_utils::http_helpers::read_body;
 use jsonrpsee_utils::server::{
-	helpers::{collect_batch_response, prepare_error, MethodSink},
+	access_log::{AccessLog, AccessLogEntry},
+	helpers::{collect_batch_response, prepare_error, MethodSink, StreamingResponse},
 	resource_limiting::Resources,
 	rpc_module::{MethodResult, Methods},
 };
@@ -49,10 +53,13 @@ use serde_json::value::RawValue;
 use socket2::{Domain, Socket, Type};
 use std::{
 	cmp,
+	collections::HashMap,
 	future::Future,
 	net::{SocketAddr, TcpListener, ToSocketAddrs},
 	pin::Pin,
+	sync::Arc,
 	task::{Context, Poll},
+	time::Duration,
 };
 
 /// Builder to create JSON-RPC HTTP server.
@@ -64,6 +71,17 @@ pub struct Builder<M = ()> {
 	keep_alive: bool,
 	/// Custom tokio runtime to run the server on.
 	tokio_runtime: Option<tokio::runtime::Handle>,
+	access_log: Option<AccessLog>,
+	/// Methods exposed over HTTP GET, keyed by path.
+	get_endpoints: HashMap<String, String>,
+	/// Minimum size of a response to be compressed, `None` disables compression.
+	compression_threshold: Option<u32>,
+	/// Only accept HTTP/2 connections.
+	http2_only: bool,
+	/// Maximum number of concurrent HTTP/2 streams per connection, hyper's default if `None`.
+	http2_max_concurrent_streams: Option<u32>,
+	/// Interval and timeout of the HTTP/2 keep-alive pings, disabled if `None`.
+	http2_keep_alive: Option<(Duration, Duration)>,
 	middleware: M,
 }
 
@@ -75,6 +93,12 @@ impl Default for Builder {
 			access_control: AccessControl::default(),
 			keep_alive: true,
 			tokio_runtime: None,
+			access_log: None,
+			get_endpoints: HashMap::new(),
+			compression_threshold: None,
+			http2_only: false,
+			http2_max_concurrent_streams: None,
+			http2_keep_alive: None,
 			middleware: (),
 		}
 	}
@@ -119,6 +143,12 @@ impl<M> Builder<M> {
 			access_control: self.access_control,
 			keep_alive: self.keep_alive,
 			tokio_runtime: self.tokio_runtime,
+			access_log: self.access_log,
+			get_endpoints: self.get_endpoints,
+			compression_threshold: self.compression_threshold,
+			http2_only: self.http2_only,
+			http2_max_concurrent_streams: self.http2_max_concurrent_streams,
+			http2_keep_alive: self.http2_keep_alive,
 			middleware,
 		}
 	}
@@ -162,6 +192,80 @@ impl<M> Builder<M> {
 		self
 	}
 
+	/// Enables structured access logging, one record per JSON-RPC call.
+	///
+	/// See [`AccessLog`](../jsonrpsee_utils/server/access_log/struct.AccessLog.html) for the available options.
+	///
+	/// ```
+	/// use jsonrpsee_http_server::{AccessLog, HttpServerBuilder};
+	///
+	/// let builder = HttpServerBuilder::new().set_access_log(AccessLog::new().redact_params(["password"]));
+	/// ```
+	pub fn set_access_log(mut self, access_log: AccessLog) -> Self {
+		self.access_log = Some(access_log);
+		self
+	}
+
+	/// Enables compression of responses of at least `min_size` bytes, with the `br`, `gzip` or `deflate`
+	/// encoding negotiated from the `Accept-Encoding` header of the request.
+	///
+	/// Default is disabled. Compressed request bodies are always accepted, and the
+	/// [`max_request_body_size`](Builder::max_request_body_size) limit applies after decompression.
+	pub fn response_compression(mut self, min_size: u32) -> Self {
+		self.compression_threshold = Some(min_size);
+		self
+	}
+
+	/// Only accept HTTP/2 connections, with prior knowledge since the server does not use TLS.
+	///
+	/// Default is false, in which case both HTTP/1.1 and prior knowledge HTTP/2 (h2c) connections are accepted,
+	/// the latter letting clients multiplex many concurrent calls over a single connection.
+	pub fn http2_only(mut self, http2_only: bool) -> Self {
+		self.http2_only = http2_only;
+		self
+	}
+
+	/// Sets the maximum number of concurrent streams, and so of concurrent calls, of an HTTP/2 connection.
+	///
+	/// Clients queue the calls over the limit once they received it, the ones sent before are refused. Default
+	/// is no limit other than the one of the peer.
+	pub fn http2_max_concurrent_streams(mut self, max: u32) -> Self {
+		self.http2_max_concurrent_streams = Some(max);
+		self
+	}
+
+	/// Sends HTTP/2 keep-alive pings every `interval`, closing the connection if one is not acknowledged within
+	/// `timeout`.
+	///
+	/// Default is disabled.
+	pub fn http2_keep_alive(mut self, interval: Duration, timeout: Duration) -> Self {
+		self.http2_keep_alive = Some((interval, timeout));
+		self
+	}
+
+	/// Expose the RPC method `method` at the HTTP GET endpoint `path`, for example to serve health and
+	/// readiness checks from an [`RpcModule`](crate::RpcModule).
+	///
+	/// The method is called without params. Its result is returned as the JSON body of a `200 OK`
+	/// response, while an error is returned as the JSON-RPC error object with a `5xx` status code.
+	/// Errors if `path` does not start with `/`; [`Server::start`] errors if `method` is not registered.
+	///
+	/// ```
+	/// use jsonrpsee_http_server::HttpServerBuilder;
+	///
+	/// let builder = HttpServerBuilder::new().get_endpoint("/health", "system_health").unwrap();
+	/// ```
+	pub fn get_endpoint(mut self, path: impl Into<String>, method: impl Into<String>) -> Result<Self, Error> {
+		let path = path.into();
+
+		if !path.starts_with('/') {
+			return Err(Error::Custom(format!("Invalid HTTP GET endpoint path: {}, must start with '/'", path)));
+		}
+
+		self.get_endpoints.insert(path, method.into());
+		Ok(self)
+	}
+
 	/// Finalizes the configuration of the server.
 	///
 	/// ```rust
@@ -196,6 +300,9 @@ impl<M> Builder<M> {
 				max_request_body_size: self.max_request_body_size,
 				resources: self.resources,
 				tokio_runtime: self.tokio_runtime,
+				access_log: self.access_log,
+				get_endpoints: Arc::new(self.get_endpoints),
+				compression_threshold: self.compression_threshold,
 				middleware: self.middleware,
 			});
 		}
@@ -220,7 +327,12 @@ impl<M> Builder<M> {
 		socket.listen(128)?;
 		let listener: TcpListener = socket.into();
 		let local_addr = listener.local_addr().ok();
-		let listener = hyper::Server::from_tcp(listener)?;
+		let mut listener = hyper::Server::from_tcp(listener)?
+			.http2_only(self.http2_only)
+			.http2_max_concurrent_streams(self.http2_max_concurrent_streams);
+		if let Some((interval, timeout)) = self.http2_keep_alive {
+			listener = listener.http2_keep_alive_interval(interval).http2_keep_alive_timeout(timeout);
+		}
 		Ok((listener, local_addr))
 	}
 }
@@ -271,6 +383,12 @@ pub struct Server<M = ()> {
 	resources: Resources,
 	/// Custom tokio runtime to run the server on.
 	tokio_runtime: Option<tokio::runtime::Handle>,
+	/// Access log configuration.
+	access_log: Option<AccessLog>,
+	/// Methods exposed over HTTP GET, keyed by path.
+	get_endpoints: Arc<HashMap<String, String>>,
+	/// Minimum size of a response to be compressed, `None` disables compression.
+	compression_threshold: Option<u32>,
 	middleware: M,
 }
 
@@ -288,13 +406,25 @@ impl<M: Middleware> Server<M> {
 		let listener = self.listener;
 		let resources = self.resources;
 		let middleware = self.middleware;
+		let access_log = self.access_log;
+		let get_endpoints = self.get_endpoints;
+		let compression_threshold = self.compression_threshold;
 		let methods = methods.into().initialize_resources(&resources)?;
 
-		let make_service = make_service_fn(move |_| {
+		for method in get_endpoints.values() {
+			if methods.method(method).is_none() {
+				return Err(Error::MethodNotFound(method.clone()));
+			}
+		}
+
+		let make_service = make_service_fn(move |conn: &AddrStream| {
+			let remote_addr = conn.remote_addr();
 			let methods = methods.clone();
 			let access_control = access_control.clone();
 			let resources = resources.clone();
 			let middleware = middleware.clone();
+			let access_log = access_log.clone();
+			let get_endpoints = get_endpoints.clone();
 
 			async move {
 				Ok::<_, HyperError>(service_fn(move |request| {
@@ -302,6 +432,8 @@ impl<M: Middleware> Server<M> {
 					let access_control = access_control.clone();
 					let resources = resources.clone();
 					let middleware = middleware.clone();
+					let access_log = access_log.clone();
+					let get_endpoints = get_endpoints.clone();
 
 					// Run some validation on the http request, then read the body and try to deserialize it into one of
 					// two cases: a single RPC request or a batch of RPC requests.
@@ -310,13 +442,44 @@ impl<M: Middleware> Server<M> {
 							return Ok::<_, HyperError>(e);
 						}
 
+						if request.method() == hyper::Method::GET {
+							if let Some(method) = get_endpoints.get(request.uri().path()) {
+								let entry = access_log.as_ref().and_then(|log| {
+									log.start(
+										"http",
+										Some(remote_addr),
+										&Request::new(method.as_str().into(), None, Id::Number(0)),
+									)
+								});
+								let response = process_get_endpoint(
+									&methods,
+									&resources,
+									&middleware,
+									method,
+									entry,
+									max_request_body_size,
+								)
+								.await;
+								return Ok::<_, HyperError>(response);
+							}
+						}
+
 						if let Err(e) = content_type_is_valid(&request) {
 							return Ok::<_, HyperError>(e);
 						}
 
 						let (parts, body) = request.into_parts();
 
-						let (body, mut is_single) = match read_body(&parts.headers, body, max_request_body_size).await {
+						let body = match compression::request_encoding(&parts.headers) {
+							Ok(None) => read_body(&parts.headers, body, max_request_body_size).await,
+							Ok(Some(encoding)) => {
+								compression::read_encoded_body(&parts.headers, body, max_request_body_size, encoding)
+									.await
+							}
+							Err(()) => return Ok::<_, HyperError>(response::unsupported_content_encoding()),
+						};
+
+						let (body, mut is_single) = match body {
 							Ok(r) => r,
 							Err(GenericTransportError::TooLarge) => return Ok::<_, HyperError>(response::too_large()),
 							Err(GenericTransportError::Malformed) => return Ok::<_, HyperError>(response::malformed()),
@@ -331,16 +494,30 @@ impl<M: Middleware> Server<M> {
 						// NOTE(niklasad1): it's a channel because it's needed for batch requests.
 						let (tx, mut rx) = mpsc::unbounded::<String>();
 						let sink = MethodSink::new_with_limit(tx, max_request_body_size);
+						// Streaming responses are only supported for single requests.
+						let (streams_tx, mut streams_rx) = mpsc::unbounded::<StreamingResponse>();
 
 						type Notif<'a> = Notification<'a, Option<&'a RawValue>>;
 
+						let log_invalid = |body: &[u8]| {
+							access_log.as_ref().and_then(|log| log.start_invalid("http", Some(remote_addr), body))
+						};
+
 						// Single request or notification
 						if is_single {
 							if let Ok(req) = serde_json::from_slice::<Request>(&body) {
 								middleware.on_call(req.method.as_ref());
+								let route = methods.route(&req);
+								if let Some((name, _)) = route.filter(|(_, callback)| callback.is_deprecated()) {
+									middleware.on_deprecated_call(name);
+								}
+
+								let entry =
+									access_log.as_ref().and_then(|log| log.start("http", Some(remote_addr), &req));
+								let sink = sink.with_access_log(entry).with_streaming(streams_tx);
 
 								// NOTE: we don't need to track connection id on HTTP, so using hardcoded 0 here.
-								match methods.execute_with_resources(&sink, req, 0, &resources) {
+								match methods.execute_routed(&sink, req, route, 0, &resources) {
 									Ok((name, MethodResult::Sync(success))) => {
 										middleware.on_result(name, success, request_start);
 									}
@@ -357,16 +534,26 @@ impl<M: Middleware> Server<M> {
 								return Ok::<_, HyperError>(response::ok_response("".into()));
 							} else {
 								let (id, code) = prepare_error(&body);
-								sink.send_error(id, code.into());
+								sink.with_access_log(log_invalid(&body)).send_error(id, code.into());
 							}
 
 						// Batch of requests or notifications
 						} else if let Ok(batch) = serde_json::from_slice::<Vec<Request>>(&body) {
 							if !batch.is_empty() {
 								let middleware = &middleware;
+								let access_log = &access_log;
 
 								join_all(batch.into_iter().filter_map(move |req| {
-									match methods.execute_with_resources(&sink, req, 0, &resources) {
+									let entry =
+										access_log.as_ref().and_then(|log| log.start("http", Some(remote_addr), &req));
+									let sink = sink.with_access_log(entry);
+
+									let route = methods.route(&req);
+									if let Some((name, _)) = route.filter(|(_, callback)| callback.is_deprecated()) {
+										middleware.on_deprecated_call(name);
+									}
+
+									match methods.execute_routed(&sink, req, route, 0, &resources) {
 										Ok((name, MethodResult::Sync(success))) => {
 											middleware.on_result(name, success, request_start);
 											None
@@ -387,7 +574,8 @@ impl<M: Middleware> Server<M> {
 								// Array with at least one value, the response from the Server MUST be a single
 								// Response object." – The Spec.
 								is_single = true;
-								sink.send_error(Id::Null, ErrorCode::InvalidRequest.into());
+								sink.with_access_log(log_invalid(&body))
+									.send_error(Id::Null, ErrorCode::InvalidRequest.into());
 							}
 						} else if let Ok(_batch) = serde_json::from_slice::<Vec<Notif>>(&body) {
 							return Ok::<_, HyperError>(response::ok_response("".into()));
@@ -397,7 +585,13 @@ impl<M: Middleware> Server<M> {
 							// Response object." – The Spec.
 							is_single = true;
 							let (id, code) = prepare_error(&body);
-							sink.send_error(id, code.into());
+							sink.with_access_log(log_invalid(&body)).send_error(id, code.into());
+						}
+
+						streams_rx.close();
+						if let Some(stream) = streams_rx.next().await {
+							middleware.on_response(request_start);
+							return Ok::<_, HyperError>(response::streaming_response(stream));
 						}
 
 						// Closes the receiving half of a channel without dropping it. This prevents any further
@@ -410,7 +604,24 @@ impl<M: Middleware> Server<M> {
 						};
 						tracing::debug!("[service_fn] sending back: {:?}", &response[..cmp::min(response.len(), 1024)]);
 						middleware.on_response(request_start);
-						Ok::<_, HyperError>(response::ok_response(response))
+
+						let encoding = match compression_threshold {
+							Some(threshold) if response.len() >= threshold as usize => {
+								compression::negotiate(&parts.headers)
+							}
+							_ => None,
+						};
+
+						match encoding.map(|encoding| (encoding, encoding.encode(response.as_bytes()))) {
+							Some((encoding, Ok(compressed))) => {
+								Ok::<_, HyperError>(response::ok_encoded_response(compressed, encoding.as_str()))
+							}
+							Some((encoding, Err(err))) => {
+								tracing::error!("Failed to compress response with {}: {:?}", encoding.as_str(), err);
+								Ok::<_, HyperError>(response::ok_response(response))
+							}
+							None => Ok::<_, HyperError>(response::ok_response(response)),
+						}
 					}
 				}))
 			}
@@ -430,6 +641,59 @@ impl<M: Middleware> Server<M> {
 	}
 }
 
+/// Calls the method exposed at an HTTP GET endpoint and converts its JSON-RPC response into an HTTP response.
+async fn process_get_endpoint<M: Middleware>(
+	methods: &Methods,
+	resources: &Resources,
+	middleware: &M,
+	method: &str,
+	access_log: Option<AccessLogEntry>,
+	max_response_size: u32,
+) -> hyper::Response<hyper::Body> {
+	let request_start = middleware.on_request();
+	middleware.on_call(method);
+
+	let (tx, mut rx) = mpsc::unbounded::<String>();
+	let sink = MethodSink::new_with_limit(tx, max_response_size).with_access_log(access_log);
+	let req = Request::new(method.into(), None, Id::Number(0));
+	let route = methods.route(&req);
+	if let Some((name, _)) = route.filter(|(_, callback)| callback.is_deprecated()) {
+		middleware.on_deprecated_call(name);
+	}
+
+	match methods.execute_routed(&sink, req, route, 0, resources) {
+		Ok((name, MethodResult::Sync(success))) => middleware.on_result(name, success, request_start),
+		Ok((name, MethodResult::Async(fut))) => {
+			let success = fut.await;
+			middleware.on_result(name, success, request_start);
+		}
+		Err(name) => middleware.on_result(name.as_ref(), false, request_start),
+	}
+
+	drop(sink);
+	let response = rx.next().await.expect("Sender is still alive managed by us above; qed");
+	middleware.on_response(request_start);
+
+	if let Ok(success) = serde_json::from_str::<Response<&RawValue>>(&response) {
+		return response::ok_response(success.result.get().to_owned());
+	}
+
+	let error = match serde_json::from_str::<RpcError>(&response) {
+		Ok(RpcError { error, .. }) => error,
+		Err(err) => {
+			tracing::error!("Invalid response from GET endpoint method {}: {:?}", method, err);
+			return response::internal_error();
+		}
+	};
+
+	let status = match error.code {
+		ErrorCode::ServerIsBusy => hyper::StatusCode::SERVICE_UNAVAILABLE,
+		_ => hyper::StatusCode::INTERNAL_SERVER_ERROR,
+	};
+	let body = serde_json::to_string(&error).expect("built from known-good data; qed");
+	response::error_response(status, body)
+}
+
 // Checks to that access control of the received request is the same as configured.
 fn access_control_is_valid(
 	access_control: &AccessControl,
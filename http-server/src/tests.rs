@@ -405,3 +405,158 @@ async fn run_forever() {
 	server_handle.handle.take();
 	server_handle.with_timeout(TIMEOUT).await.unwrap();
 }
+
+#[tokio::test]
+async fn access_log_records_each_call() {
+	let records = std::sync::Arc::new(AccessLogRecords::default());
+	let access_log = crate::AccessLog::new().writer(records.clone());
+	let server = HttpServerBuilder::default().set_access_log(access_log).build("127.0.0.1:0").unwrap();
+	let mut module = RpcModule::new(());
+	module.register_method("add", |params, _| Ok(params.parse::<Vec<u64>>()?.into_iter().sum::<u64>())).unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module).unwrap();
+	let uri = to_http_uri(addr);
+
+	let req = r#"{"jsonrpc":"2.0","method":"add","params":[1,2],"id":1}"#;
+	let response = http_request(req.into(), uri.clone()).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.body, ok_response(3.into(), Id::Num(1)));
+
+	let req = r#"[{"jsonrpc":"2.0","method":"add","params":[1],"id":2},{"jsonrpc":"2.0","method":"nope","id":3}]"#;
+	http_request(req.into(), uri.clone()).with_default_timeout().await.unwrap().unwrap();
+
+	let req = r#"{"jsonrpc":"2.0","method":"add","params":[1],"id":4"#;
+	http_request(req.into(), uri).with_default_timeout().await.unwrap().unwrap();
+
+	let records = records.0.lock().unwrap();
+	assert_eq!(records.len(), 4);
+	assert_eq!(records[0]["transport"], "http");
+	assert_eq!(records[0]["method"], "add");
+	assert_eq!(records[0]["params_size"], 5);
+	assert_eq!(records[0]["success"], true);
+	assert_eq!(records[0]["response_size"], response.body.len());
+	assert!(records[0]["remote_addr"].as_str().unwrap().starts_with("127.0.0.1:"));
+	assert!(records.iter().any(|r| r["method"] == "nope" && r["error_code"] == -32601));
+	assert_eq!(records[3]["method"], "");
+	assert_eq!(records[3]["error_code"], -32700);
+}
+
+#[tokio::test]
+async fn get_endpoints_work() {
+	let server = HttpServerBuilder::default()
+		.get_endpoint("/health", "system_health")
+		.unwrap()
+		.get_endpoint("/ready", "system_ready")
+		.unwrap()
+		.build("127.0.0.1:0")
+		.unwrap();
+	let mut module = RpcModule::new(());
+	module.register_method("system_health", |_, _| Ok(serde_json::json!({ "peers": 3 }))).unwrap();
+	module
+		.register_async_method("system_ready", |_, _| async move { Err::<(), _>(Error::Custom("syncing".into())) })
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module).unwrap();
+	let uri = to_http_uri(addr);
+
+	let response = http_get(format!("{}health", uri).parse().unwrap()).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.status, StatusCode::OK);
+	assert_eq!(response.body, r#"{"peers":3}"#);
+
+	let response = http_get(format!("{}ready", uri).parse().unwrap()).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.status, StatusCode::INTERNAL_SERVER_ERROR);
+	assert_eq!(response.body, r#"{"code":-32001,"message":"Custom error: syncing"}"#);
+
+	// Paths without a mapped method are still rejected.
+	let response = http_get(format!("{}other", uri).parse().unwrap()).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn get_endpoint_with_unknown_method_fails_to_start() {
+	assert!(HttpServerBuilder::default().get_endpoint("health", "system_health").is_err());
+
+	let server =
+		HttpServerBuilder::default().get_endpoint("/health", "system_health").unwrap().build("127.0.0.1:0").unwrap();
+	assert!(matches!(server.start(RpcModule::new(())), Err(Error::MethodNotFound(m)) if m == "system_health"));
+}
+
+#[tokio::test]
+async fn compressed_requests_and_responses_work() {
+	use crate::compression::ContentEncoding;
+	use hyper::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE};
+
+	async fn request(uri: &str, body: Vec<u8>, content_encoding: &'static str) -> hyper::Response<hyper::Body> {
+		let req = hyper::Request::post(uri)
+			.header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+			.header(CONTENT_ENCODING, HeaderValue::from_static(content_encoding))
+			.header(ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate;q=0.5"))
+			.body(body.into())
+			.unwrap();
+		hyper::Client::new().request(req).with_default_timeout().await.unwrap().unwrap()
+	}
+
+	let server = HttpServerBuilder::default()
+		.max_request_body_size(1024)
+		.response_compression(100)
+		.build("127.0.0.1:0")
+		.unwrap();
+	let mut module = RpcModule::new(());
+	module.register_method("repeat", |params, _| Ok(params.one::<String>()?.repeat(20))).unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module).unwrap();
+	let uri = to_http_uri(addr).to_string();
+
+	// Large response is compressed with the preferred encoding.
+	let req = call("repeat", vec!["jsonrpsee"], Id::Num(1));
+	let response = request(&uri, ContentEncoding::Gzip.encode(req.as_bytes()).unwrap(), "gzip").await;
+	assert_eq!(response.status(), StatusCode::OK);
+	assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+	let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+	let body = ContentEncoding::Gzip.decode(&body, 1024).unwrap();
+	assert_eq!(body, ok_response("jsonrpsee".repeat(20).into(), Id::Num(1)).as_bytes());
+
+	// Small response is not compressed.
+	let req = call("repeat", vec![""], Id::Num(2));
+	let response = request(&uri, req.into_bytes(), "identity").await;
+	assert!(response.headers().get(CONTENT_ENCODING).is_none());
+	let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+	assert_eq!(body, ok_response("".into(), Id::Num(2)).as_bytes());
+
+	// The size limit applies to the decompressed body.
+	let req = call("repeat", vec!["a".repeat(2000)], Id::Num(3));
+	let response = request(&uri, ContentEncoding::Deflate.encode(req.as_bytes()).unwrap(), "deflate").await;
+	assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+	let response = request(&uri, b"{}".to_vec(), "zstd").await;
+	assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn streaming_responses_work() {
+	let server = HttpServerBuilder::default().max_request_body_size(200 * 1024).build("127.0.0.1:0").unwrap();
+	let mut module = RpcModule::new(());
+	module
+		.register_streaming_method("blob", |params, _| async move {
+			let len: usize = params.one()?;
+			Ok(vec![7_u8; len])
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module).unwrap();
+	let uri = to_http_uri(addr);
+
+	let req = call("blob", vec![50_000], Id::Num(1));
+	let response = http_request(req.into(), uri.clone()).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.status, StatusCode::OK);
+	assert_eq!(response.header[hyper::header::TRANSFER_ENCODING], "chunked");
+	assert_eq!(response.body, ok_response(vec![7_u8; 50_000].into(), Id::Num(1)));
+
+	// The limit is enforced while the body is sent, which aborts the response.
+	let req = hyper::Request::post(uri)
+		.header(hyper::header::CONTENT_TYPE, "application/json")
+		.body(call("blob", vec![200_000], Id::Num(2)).into())
+		.unwrap();
+	let response = hyper::Client::new().request(req).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(response.status(), StatusCode::OK);
+	assert!(hyper::body::to_bytes(response.into_body()).await.is_err());
+}
@@ -235,6 +235,28 @@ where
 	arg.ok().map(transform).transpose()
 }
 
+/// Parses a duration such as `"500ms"`, `"60s"`, `"5m"` or `"1h"` into milliseconds.
+pub(crate) fn parse_duration(arg: Result<Argument, MissingArgument>) -> syn::Result<Option<u64>> {
+	let lit = match optional(arg, Argument::value::<LitStr>)? {
+		Some(lit) => lit,
+		None => return Ok(None),
+	};
+
+	let value = lit.value();
+	let (amount, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+	let unit_millis = match unit {
+		"ms" => 1,
+		"s" => 1_000,
+		"m" => 60_000,
+		"h" => 3_600_000,
+		_ => 0,
+	};
+	match amount.parse::<u64>().ok().and_then(|amount| amount.checked_mul(unit_millis)) {
+		Some(millis) if millis > 0 => Ok(Some(millis)),
+		_ => Err(Error::new(lit.span(), "Invalid duration, expected e.g. `500ms`, `60s`, `5m` or `1h`")),
+	}
+}
+
 pub(crate) fn parse_param_kind(arg: Result<Argument, MissingArgument>) -> syn::Result<ParamKind> {
 	let kind: Option<syn::Ident> = optional(arg, Argument::value)?;
 
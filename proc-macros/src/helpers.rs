@@ -87,6 +87,7 @@ fn find_jsonrpsee_crate(http_name: &str, ws_name: &str) -> Result<proc_macro2::T
 /// This is used as an additional input before traversing the entire trait.
 /// Otherwise, it's not possible to know whether a type parameter is used for subscription result.
 pub(crate) fn generate_where_clause(
+	jsonrpsee: &TokenStream2,
 	item_trait: &syn::ItemTrait,
 	sub_tys: &[syn::Type],
 	is_client: bool,
@@ -103,17 +104,17 @@ pub(crate) fn generate_where_clause(
 
 			if is_client {
 				if visitor.input_params.contains(&ty.ident) {
-					bounds.push(parse_quote!(jsonrpsee::types::Serialize))
+					bounds.push(parse_quote!(#jsonrpsee::types::Serialize))
 				}
 				if visitor.ret_params.contains(&ty.ident) || visitor.sub_params.contains(&ty.ident) {
-					bounds.push(parse_quote!(jsonrpsee::types::DeserializeOwned))
+					bounds.push(parse_quote!(#jsonrpsee::types::DeserializeOwned))
 				}
 			} else {
 				if visitor.input_params.contains(&ty.ident) {
-					bounds.push(parse_quote!(jsonrpsee::types::DeserializeOwned))
+					bounds.push(parse_quote!(#jsonrpsee::types::DeserializeOwned))
 				}
 				if visitor.ret_params.contains(&ty.ident) || visitor.sub_params.contains(&ty.ident) {
-					bounds.push(parse_quote!(jsonrpsee::types::Serialize))
+					bounds.push(parse_quote!(#jsonrpsee::types::Serialize))
 				}
 			}
 
@@ -180,9 +181,59 @@ pub(crate) fn extract_doc_comments(attrs: &[syn::Attribute]) -> TokenStream2 {
 	quote! ( #(#docs)* )
 }
 
+/// Returns the text of the doc comments, without the leading space of each line, or `None` if there are none.
+pub(crate) fn extract_doc_text(attrs: &[syn::Attribute]) -> Option<String> {
+	let lines: Vec<String> = attrs
+		.iter()
+		.filter(|attr| attr.path.is_ident("doc"))
+		.filter_map(|attr| match attr.parse_meta() {
+			Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(lit), .. })) => Some(lit.value()),
+			_ => None,
+		})
+		.map(|line| line.strip_prefix(' ').map(ToOwned::to_owned).unwrap_or(line))
+		.collect();
+
+	let text = lines.join("\n").trim().to_owned();
+
+	if text.is_empty() {
+		None
+	} else {
+		Some(text)
+	}
+}
+
+/// Returns the success type of a `Result<T, E>` or `RpcResult<T>`, i.e. the first generic argument of the leaf
+/// segment of the path.
+pub(crate) fn result_ok_type(ty: &syn::Type) -> Option<&syn::Type> {
+	let segment = match ty {
+		syn::Type::Path(path) => path.path.segments.last()?,
+		_ => return None,
+	};
+
+	match &segment.arguments {
+		syn::PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+			syn::GenericArgument::Type(ty) => Some(ty),
+			_ => None,
+		}),
+		_ => None,
+	}
+}
+
+/// Renders a type the way it's written in source, e.g. `Option<Vec<u8>>` rather than `Option < Vec < u8 > >`.
+pub(crate) fn type_name(ty: &syn::Type) -> String {
+	let mut name = quote!(#ty).to_string();
+	for (from, to) in [(" <", "<"), ("< ", "<"), (" >", ">"), (" ::", "::"), (":: ", "::"), (" ,", ","), ("& ", "&")]
+		.iter()
+		.chain([("( ", "("), (" )", ")"), ("[ ", "["), (" ]", "]"), (" ;", ";")].iter())
+	{
+		name = name.replace(from, to);
+	}
+	name
+}
+
 #[cfg(test)]
 mod tests {
-	use super::is_option;
+	use super::{extract_doc_text, is_option, result_ok_type, type_name};
 	use syn::parse_quote;
 
 	#[test]
@@ -193,4 +244,36 @@ mod tests {
 		assert!(is_option(&parse_quote!(std::option::Option<R>)));
 		assert!(!is_option(&parse_quote!(foo::bar::Option::Booyah)));
 	}
+
+	#[test]
+	fn extract_doc_text_works() {
+		let method: syn::TraitItemMethod = parse_quote! {
+			/// Returns the block.
+			///
+			///  Indented.
+			fn block(&self);
+		};
+		assert_eq!(extract_doc_text(&method.attrs).as_deref(), Some("Returns the block.\n\n Indented."));
+
+		let method: syn::TraitItemMethod = parse_quote!(
+			fn block(&self);
+		);
+		assert_eq!(extract_doc_text(&method.attrs), None);
+	}
+
+	#[test]
+	fn result_ok_type_works() {
+		let expected: syn::Type = parse_quote!(Vec<u8>);
+		assert_eq!(result_ok_type(&parse_quote!(RpcResult<Vec<u8>>)), Some(&expected));
+		assert_eq!(result_ok_type(&parse_quote!(std::result::Result<Vec<u8>, Error>)), Some(&expected));
+		assert_eq!(result_ok_type(&parse_quote!(u8)), None);
+	}
+
+	#[test]
+	fn type_name_works() {
+		assert_eq!(type_name(&parse_quote!(Option<beef::Cow<'_, str>>)), "Option<beef::Cow<'_, str>>");
+		assert_eq!(type_name(&parse_quote!(&str)), "&str");
+		assert_eq!(type_name(&parse_quote!([u8; 32])), "[u8; 32]");
+		assert_eq!(type_name(&parse_quote!((u8, Vec<u8>))), "(u8, Vec<u8>)");
+	}
 }
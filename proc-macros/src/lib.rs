@@ -33,6 +33,7 @@ use rpc_macro::RpcDescription;
 mod attributes;
 mod helpers;
 mod render_client;
+mod render_mock;
 mod render_server;
 mod rpc_macro;
 pub(crate) mod visitor;
@@ -144,17 +145,43 @@ pub(crate) mod visitor;
 ///   implementation's methods conveniently.
 /// - `namespace`: add a prefix to all the methods and subscriptions in this RPC. For example, with namespace `foo` and
 ///   method `spam`, the resulting method name will be `foo_spam`.
+/// - `openrpc`: generate an `openrpc()` function on the server trait that describes the API as an
+///   [OpenRPC](https://spec.open-rpc.org) document, and serve it through the `rpc.discover` method. Doc comments
+///   become method descriptions and all parameter, return and subscription item types must implement
+///   `schemars::JsonSchema`. Requires `server` and the `openrpc` feature of `jsonrpsee`.
+/// - `mock`: generate test doubles of the server and client, see below. Requires the server part of `jsonrpsee`.
 ///
 /// **Trait requirements:**
 ///
 /// A trait wrapped with the `rpc` attribute **must not**:
 ///
-/// - have associated types or constants;
+/// - have associated constants, or associated types with generics or where clauses;
 /// - have Rust methods not marked with either the `method` or `subscription` attribute;
 /// - be empty.
 ///
 /// At least one of the `server` or `client` flags must be provided, otherwise the compilation will err.
 ///
+/// **Generics:**
+///
+/// The trait may be generic. Both generated traits are generic over the same type parameters, and the
+/// bounds needed to (de)serialize them are added per side: `DeserializeOwned` for the server's params and
+/// `Serialize` for its results and subscription items, the other way around for the client. Associated types
+/// are turned into type parameters of the same name, keeping their bounds and defaults, and `Self::Name` into
+/// `Name`: `type Hash;` in trait `Chain` results in `ChainServer<Hash>` and `ChainClient<Hash>`.
+///
+/// **Mocks:**
+///
+/// With `mock`, code depending on the API can be tested without a network:
+///
+/// - with `server`, `<Trait>ServerStub` implements the server trait with canned responses: `on_<method>` sets a
+///   closure answering the calls to a method, or returning the notifications of a subscription. Calls to the other
+///   methods fail. Its `into_rpc()` module can be called in-process with `RpcModule::call` and
+///   `RpcModule::subscribe`.
+/// - with `client`, the `<Trait>ClientMock` trait adds `expect_<method>` to `jsonrpsee::types::mock::MockClient`,
+///   which implements the client traits and hence `<Trait>Client`. Each expectation answers one call, in the order
+///   they were added, with the arguments decoded as on the server, and its errors are returned as the error
+///   responses of a server are. Unexpected calls fail, and the client panics when dropped with expectations left.
+///
 /// ### `method` attribute
 ///
 /// `method` attribute is used to define an RPC method.
@@ -164,6 +191,9 @@ pub(crate) mod visitor;
 /// - `name` (mandatory): name of the RPC method. Does not have to be the same as the Rust method name.
 /// - `aliases`: list of name aliases for the RPC method as a comma separated string.
 /// - `blocking`: when set method execution will always spawn on a dedicated thread. Only usable with non-`async` methods.
+/// - `cache`: how long the server caches the successful responses of the method, keyed by their params, e.g.
+///   `cache = "60s"`. The duration is in `ms`, `s`, `m` or `h`. The cache is returned by `Methods::response_cache`
+///   to invalidate the responses.
 /// - `param_kind`: kind of structure to use for parameter passing. Can be "array" or "map", defaults to "array".
 ///
 /// **Method requirements:**
@@ -174,6 +204,14 @@ pub(crate) mod visitor;
 /// - have input parameters or not;
 /// - have a return value or not (in the latter case, it will be considered a notification method).
 ///
+/// **Typed errors:**
+///
+/// The return value is a `Result<T, E>` where `E` is either `jsonrpsee::types::Error` or a custom error type that
+/// implements `jsonrpsee::types::MethodError`. The server converts a custom error into the JSON-RPC error object
+/// given by `MethodError::into_error_object`, and the generated client decodes the error object of a failed call
+/// back into `E` with `MethodError::from_error_object`. Failures without an error object, such as transport
+/// errors, are converted with `From<jsonrpsee::types::Error>`.
+///
 /// ### `subscription` attribute
 ///
 /// **Arguments:**
@@ -182,18 +220,45 @@ pub(crate) mod visitor;
 /// - `unsub` (mandatory): name of the RPC method to unsubscribe from the subscription. Must not be the same as `name`.
 /// - `item` (mandatory): type of items yielded by the subscription. Note that it must be the type, not string.
 /// - `param_kind`: kind of structure to use for parameter passing. Can be "array" or "map", defaults to "array".
+/// - `with_context`: when set the server method also takes the `ConnectionContext` of the subscription, right after
+///   the `SubscriptionSink`.
 ///
 /// **Method requirements:**
 ///
 /// Rust method marked with the `subscription` attribute **must**:
 ///
-/// - be synchronous;
 /// - not have return value.
 ///
 /// Rust method marked with `subscription` attribute **may**:
 ///
+/// - be either `async` or not; `async` subscriptions are spawned on the tokio runtime and may run for as long as the
+///   subscription is alive;
 /// - have input parameters or not.
 ///
+/// The generated client returns a `Subscription<Item>`, a `Stream` of the items. Dropping it unsubscribes in the
+/// background, while `Subscription::unsubscribe` waits for the server to confirm it and returns its error otherwise.
+///
+/// ### `argument` attribute
+///
+/// `argument` attribute can be applied to the input parameters of methods and subscriptions.
+///
+/// **Arguments:**
+///
+/// - `default`: expression for the value of the parameter when it's omitted or `null`, e.g.
+///   `#[argument(default = 10)] limit: u32`.
+/// - `rename`: name of the parameter when parameters are passed by name, e.g.
+///   `#[argument(rename = "blockHash")] block_hash: Hash`. Defaults to the Rust identifier.
+/// - `flatten`: the parameter is the entire parameters object rather than one of its fields, e.g.
+///   `#[argument(flatten)] params: DidOpenParams`. Must be the only parameter of the method; the client sends it
+///   as an object whatever the `param_kind`.
+///
+/// **Optional parameters:**
+///
+/// Parameters of type `Option<T>` and parameters with a `default` can be omitted by the caller, both at the end of
+/// an array and from an object. The generated client leaves trailing `None` parameters out of arrays and `None`
+/// parameters out of objects, so a new optional parameter can be added to an existing method without breaking
+/// older servers or clients. Parameters with a `default` are always sent by the generated client.
+///
 /// ## Full workflow example
 ///
 /// ```rust
@@ -24,11 +24,11 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 use crate::attributes::ParamKind;
-use crate::helpers::generate_where_clause;
-use crate::rpc_macro::{RpcDescription, RpcMethod, RpcSubscription};
+use crate::helpers::{generate_where_clause, is_option};
+use crate::rpc_macro::{RpcDescription, RpcFnArg, RpcMethod, RpcSubscription};
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{FnArg, Pat, PatIdent, PatType, TypeParam};
+use syn::TypeParam;
 
 impl RpcDescription {
 	pub(super) fn render_client(&self) -> Result<TokenStream2, syn::Error> {
@@ -36,9 +36,17 @@ impl RpcDescription {
 		let sub_tys: Vec<syn::Type> = self.subscriptions.clone().into_iter().map(|s| s.item).collect();
 
 		let trait_name = quote::format_ident!("{}Client", &self.trait_def.ident);
-		let where_clause = generate_where_clause(&self.trait_def, &sub_tys, true);
-		let type_idents = self.trait_def.generics.type_params().collect::<Vec<&TypeParam>>();
-		let (impl_generics, type_generics, _) = self.trait_def.generics.split_for_impl();
+		let where_clause = generate_where_clause(jsonrpsee, &self.trait_def, &sub_tys, true);
+		// Defaults of the type parameters are only allowed in the trait definition, not in the blanket impl.
+		let type_idents = self
+			.trait_def
+			.generics
+			.type_params()
+			.map(|param| TypeParam { eq_token: None, default: None, ..param.clone() })
+			.collect::<Vec<TypeParam>>();
+		// Unlike `impl_generics`, the generics themselves keep the defaults of the type parameters.
+		let generics = &self.trait_def.generics;
+		let (_, type_generics, _) = generics.split_for_impl();
 
 		let super_trait = if self.subscriptions.is_empty() {
 			quote! { #jsonrpsee::types::traits::Client }
@@ -58,7 +66,7 @@ impl RpcDescription {
 		let trait_impl = quote! {
 			#[#async_trait]
 			#[doc = #doc_comment]
-			pub trait #trait_name #impl_generics: #super_trait where #(#where_clause,)* {
+			pub trait #trait_name #generics: #super_trait where #(#where_clause,)* {
 				#(#method_impls)*
 				#(#sub_impls)*
 			}
@@ -95,17 +103,35 @@ impl RpcDescription {
 		};
 
 		// Encoded parameters for the request.
-		let parameters = self.encode_params(&method.params, &method.param_kind, &method.signature);
+		let (encoding, parameters) = self.encode_params(&method.params, &method.param_kind);
 		// Doc-comment to be associated with the method.
 		let docs = &method.docs;
 		// Mark the method as deprecated, if previously declared as so.
 		let deprecated = &method.deprecated;
 
+		let body = if method.returns.is_some() {
+			// Failures are decoded into the error type of the method, which may be a typed `MethodError`.
+			let method_error = self.jrps_client_item(quote! { types::MethodError });
+			quote! {
+				let __res: Result<_, #jrps_error> = async {
+					#encoding
+					self.#called_method(#rpc_method_name, #parameters).await
+				}
+				.await;
+				__res.map_err(#method_error::from_error)
+			}
+		} else {
+			quote! {
+				#encoding
+				self.#called_method(#rpc_method_name, #parameters).await
+			}
+		};
+
 		let method = quote! {
 			#docs
 			#deprecated
 			async fn #rust_method_name(#rust_method_params) -> #returns {
-				self.#called_method(#rpc_method_name, #parameters).await
+				#body
 			}
 		};
 		Ok(method)
@@ -130,70 +156,92 @@ impl RpcDescription {
 		let returns = quote! { Result<#sub_type<#item>, #jrps_error> };
 
 		// Encoded parameters for the request.
-		let parameters = self.encode_params(&sub.params, &sub.param_kind, &sub.signature);
+		let (encoding, parameters) = self.encode_params(&sub.params, &sub.param_kind);
 		// Doc-comment to be associated with the method.
 		let docs = &sub.docs;
 
 		let method = quote! {
 			#docs
 			async fn #rust_method_name(#rust_method_params) -> #returns {
+				#encoding
 				self.subscribe(#rpc_sub_name, #parameters, #rpc_unsub_name).await
 			}
 		};
 		Ok(method)
 	}
 
-	fn encode_params(
-		&self,
-		params: &[(syn::PatIdent, syn::Type)],
-		param_kind: &ParamKind,
-		signature: &syn::TraitItemMethod,
-	) -> TokenStream2 {
-		if !params.is_empty() {
-			let serde_json = self.jrps_client_item(quote! { types::__reexports::serde_json });
-			let params = params.iter().map(|(param, _param_type)| {
-				quote! { #serde_json::to_value(&#param)? }
-			});
-			match param_kind {
-				ParamKind::Map => {
-					// Extract parameter names.
-					let param_names = extract_param_names(&signature.sig);
-					// Combine parameter names and values into tuples.
-					let params = param_names.iter().zip(params).map(|pair| {
-						let param = pair.0;
-						let value = pair.1;
-						quote! { (#param, #value) }
-					});
-					quote! {
-						Some(types::v2::ParamsSer::Map(
-								std::collections::BTreeMap::<&str, #serde_json::Value>::from(
-									[#(#params),*]
-									)
-								)
-							)
+	/// Returns the statements that encode the params, and the expression of the encoded params.
+	fn encode_params(&self, params: &[RpcFnArg], param_kind: &ParamKind) -> (TokenStream2, TokenStream2) {
+		if params.is_empty() {
+			return (TokenStream2::new(), quote! { None });
+		}
+
+		let serde_json = self.jrps_client_item(quote! { types::__reexports::serde_json });
+		let params_ser = self.jrps_client_item(quote! { types::v2::ParamsSer });
+
+		if let [param] = params {
+			if param.is_flattened() {
+				let jrps_error = self.jrps_client_item(quote! { types::Error });
+				let value = &param.arg_pat().ident;
+				let encoding = quote! {
+					let __value = #serde_json::to_value(&#value)?;
+					let __params = match &__value {
+						#serde_json::Value::Object(object) => {
+							object.iter().map(|(key, value)| (key.as_str(), value.clone())).collect()
+						}
+						_ => return Err(#jrps_error::Custom("Flattened params must serialize to a JSON object".into())),
+					};
+				};
+				return (encoding, quote! { Some(#params_ser::Map(__params)) });
+			}
+		}
+
+		let encoded = match param_kind {
+			ParamKind::Map => {
+				// Optional params that are `None` are left out of the object.
+				let inserts = params.iter().map(|param| {
+					let name = param.name();
+					let value = &param.arg_pat().ident;
+					if is_option(param.ty()) {
+						quote! {
+							if #value.is_some() {
+								__params.insert(#name, #serde_json::to_value(&#value)?);
+							}
+						}
+					} else {
+						quote! { __params.insert(#name, #serde_json::to_value(&#value)?); }
 					}
-				}
-				ParamKind::Array => {
+				});
+				quote! {{
+					let mut __params = std::collections::BTreeMap::<&str, #serde_json::Value>::new();
+					#(#inserts)*
+					Some(#params_ser::Map(__params))
+				}}
+			}
+			ParamKind::Array => {
+				let values = params.iter().map(|param| {
+					let value = &param.arg_pat().ident;
+					quote! { #serde_json::to_value(&#value)? }
+				});
+				// Trailing optional params that are `None` are left out of the array, so that servers which
+				// don't know about them yet still accept the call.
+				let required = params.iter().rposition(|param| !is_option(param.ty())).map_or(0, |idx| idx + 1);
+				if required == params.len() {
 					quote! {
-						Some(vec![ #(#params),* ].into())
+						Some(vec![ #(#values),* ].into())
 					}
+				} else {
+					quote! {{
+						let mut __params = vec![ #(#values),* ];
+						while __params.len() > #required && __params.last().map_or(false, #serde_json::Value::is_null) {
+							__params.pop();
+						}
+						Some(__params.into())
+					}}
 				}
 			}
-		} else {
-			quote! { None }
-		}
-	}
-}
+		};
 
-fn extract_param_names(sig: &syn::Signature) -> Vec<String> {
-	sig.inputs
-		.iter()
-		.filter_map(|param| match param {
-			FnArg::Typed(PatType { pat, .. }) => match &**pat {
-				Pat::Ident(PatIdent { ident, .. }) => Some(ident.to_string()),
-				_ => None,
-			},
-			_ => None,
-		})
-		.collect()
+		(TokenStream2::new(), encoded)
+	}
 }
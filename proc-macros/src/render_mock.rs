@@ -0,0 +1,274 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use super::RpcDescription;
+use crate::helpers::generate_where_clause;
+use crate::rpc_macro::RpcFnArg;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+impl RpcDescription {
+	/// Renders the stub server if the server is generated and the expectations of the mock client if the client is
+	/// generated.
+	pub(super) fn render_mock(&self) -> Result<TokenStream2, syn::Error> {
+		let stub = if self.needs_server { self.render_server_stub() } else { TokenStream2::new() };
+		let mock = if self.needs_client { self.render_client_mock() } else { TokenStream2::new() };
+
+		Ok(quote! {
+			#stub
+			#mock
+		})
+	}
+
+	/// Renders `<Trait>ServerStub`, an implementation of the server trait answering with the closures set on it.
+	fn render_server_stub(&self) -> TokenStream2 {
+		let server_trait = quote::format_ident!("{}Server", &self.trait_def.ident);
+		let stub_name = quote::format_ident!("{}ServerStub", &self.trait_def.ident);
+		let async_trait = self.jrps_server_item(quote! { types::__reexports::async_trait });
+		let jrps_error = self.jrps_server_item(quote! { types::Error });
+
+		let generics = &self.trait_def.generics;
+		let (impl_generics, type_generics, _) = generics.split_for_impl();
+		let sub_tys: Vec<syn::Type> = self.subscriptions.iter().map(|sub| sub.item.clone()).collect();
+		let jsonrpsee = self.jsonrpsee_server_path.as_ref().unwrap();
+		let where_clause = generate_where_clause(jsonrpsee, &self.trait_def, &sub_tys, false);
+
+		// The type parameters are only used in the closures, which must not constrain them.
+		let type_params: Vec<_> = generics.type_params().map(|param| &param.ident).collect();
+		let marker = if type_params.is_empty() {
+			TokenStream2::new()
+		} else {
+			quote! { _marker: core::marker::PhantomData<fn() -> (#(#type_params,)*)>, }
+		};
+		let marker_init = if type_params.is_empty() {
+			TokenStream2::new()
+		} else {
+			quote! { _marker: Default::default(), }
+		};
+
+		let mut fields = Vec::new();
+		let mut setters = Vec::new();
+		let mut impls = Vec::new();
+
+		for method in &self.methods {
+			let rust_method_name = &method.signature.sig.ident;
+			let rpc_method_name = self.rpc_identifier(&method.name);
+			let setter = quote::format_ident!("on_{}", rust_method_name);
+			let arg_tys: Vec<_> = method.params.iter().map(RpcFnArg::ty).collect();
+			let args = method.params.iter().map(RpcFnArg::arg_pat);
+			let returns = match &method.returns {
+				Some(returns) => quote! { #returns },
+				None => quote! { () },
+			};
+			let handler = quote! { Box<dyn Fn(#(#arg_tys),*) -> #returns + Send + Sync> };
+			let doc = format!("Answer calls to `{}` with `f`.", rpc_method_name);
+			let missing = format!("No stub for `{}`", rpc_method_name);
+			let missing = if method.returns.is_some() {
+				quote! { Err(#jrps_error::Custom(#missing.into()).into()) }
+			} else {
+				quote! { () }
+			};
+			let sig = &method.signature.sig;
+
+			fields.push(quote! { #rust_method_name: Option<#handler>, });
+			setters.push(quote! {
+				#[doc = #doc]
+				pub fn #setter(mut self, f: impl Fn(#(#arg_tys),*) -> #returns + Send + Sync + 'static) -> Self {
+					self.#rust_method_name = Some(Box::new(f));
+					self
+				}
+			});
+			impls.push(quote! {
+				#sig {
+					match &self.#rust_method_name {
+						Some(f) => f(#(#args),*),
+						None => #missing,
+					}
+				}
+			});
+		}
+
+		for sub in &self.subscriptions {
+			let rust_method_name = &sub.signature.sig.ident;
+			let rpc_sub_name = self.rpc_identifier(&sub.name);
+			let setter = quote::format_ident!("on_{}", rust_method_name);
+			let arg_tys: Vec<_> = sub.params.iter().map(RpcFnArg::ty).collect();
+			let args = sub.params.iter().map(RpcFnArg::arg_pat);
+			let item = &sub.item;
+			let doc = format!("Answer subscriptions to `{}` with the notifications returned by `f`.", rpc_sub_name);
+			let missing = format!("No stub for `{}`", rpc_sub_name);
+			let connection_context = if sub.with_context {
+				quote! { let _ = connection_context; }
+			} else {
+				quote! {}
+			};
+			let sig = &self.subscription_signature(sub).sig;
+
+			fields.push(quote! { #rust_method_name: Option<Box<dyn Fn(#(#arg_tys),*) -> Vec<#item> + Send + Sync>>, });
+			setters.push(quote! {
+				#[doc = #doc]
+				pub fn #setter(mut self, f: impl Fn(#(#arg_tys),*) -> Vec<#item> + Send + Sync + 'static) -> Self {
+					self.#rust_method_name = Some(Box::new(f));
+					self
+				}
+			});
+			impls.push(quote! {
+				#sig {
+					#connection_context
+					let notifs = match &self.#rust_method_name {
+						Some(f) => f(#(#args),*),
+						None => return Err(#jrps_error::Custom(#missing.into()).into()),
+					};
+					let mut sink = subscription_sink;
+					for notif in notifs {
+						sink.send(&notif)?;
+					}
+					Ok(())
+				}
+			});
+		}
+
+		let field_names = self
+			.methods
+			.iter()
+			.map(|method| &method.signature.sig.ident)
+			.chain(self.subscriptions.iter().map(|sub| &sub.signature.sig.ident));
+
+		let doc_comment = format!(
+			"Stub implementation of `{}` answering with canned responses, calls without one fail.",
+			server_trait
+		);
+
+		quote! {
+			#[doc = #doc_comment]
+			pub struct #stub_name #generics {
+				#(#fields)*
+				#marker
+			}
+
+			impl #impl_generics Default for #stub_name #type_generics {
+				fn default() -> Self {
+					Self {
+						#(#field_names: None,)*
+						#marker_init
+					}
+				}
+			}
+
+			impl #impl_generics #stub_name #type_generics {
+				#(#setters)*
+			}
+
+			#[#async_trait]
+			impl #impl_generics #server_trait #type_generics for #stub_name #type_generics where #(#where_clause,)* {
+				#(#impls)*
+			}
+		}
+	}
+
+	/// Renders `<Trait>ClientMock`, the typed expectations of the calls made by the client trait on a `MockClient`.
+	fn render_client_mock(&self) -> TokenStream2 {
+		let trait_name = quote::format_ident!("{}ClientMock", &self.trait_def.ident);
+		let mock_client = self.jrps_server_item(quote! { types::mock::MockClient });
+		let jrps_error = self.jrps_server_item(quote! { types::Error });
+		let method_error = self.jrps_server_item(quote! { types::MethodError });
+		let to_json_value = self.jrps_server_item(quote! { types::to_json_value });
+		let json_value = self.jrps_server_item(quote! { types::JsonValue });
+
+		let generics = &self.trait_def.generics;
+		let (impl_generics, type_generics, _) = generics.split_for_impl();
+		let sub_tys: Vec<syn::Type> = self.subscriptions.iter().map(|sub| sub.item.clone()).collect();
+		let jsonrpsee = self.jsonrpsee_server_path.as_ref().unwrap();
+		// Calls are decoded and answered the same way as by the server.
+		let where_clause = generate_where_clause(jsonrpsee, &self.trait_def, &sub_tys, false);
+
+		let mut sigs = Vec::new();
+		let mut impls = Vec::new();
+
+		let mut push = |rust_method_name: &syn::Ident,
+		                rpc_name: String,
+		                params: &[RpcFnArg],
+		                returns: TokenStream2,
+		                answer: TokenStream2| {
+			let expect = quote::format_ident!("expect_{}", rust_method_name);
+			let arg_tys = params.iter().map(RpcFnArg::ty);
+			let doc = format!("Expect one call to `{}`, answered by `f`.", rpc_name);
+			let (parsing, params_seq) = self.render_params_decoding(params);
+			let params = if params.is_empty() { quote!(_) } else { quote!(params) };
+			let sig = quote! {
+				fn #expect(&self, f: impl FnOnce(#(#arg_tys),*) -> #returns + Send + 'static) -> &Self
+			};
+
+			sigs.push(quote! {
+				#[doc = #doc]
+				#sig;
+			});
+			impls.push(quote! {
+				#sig {
+					self.expect(#rpc_name, move |#params| {
+						#parsing
+						let answer = f(#params_seq);
+						#answer
+					})
+				}
+			});
+		};
+
+		for method in &self.methods {
+			let (returns, answer) = match &method.returns {
+				Some(returns) => (
+					quote! { #returns },
+					quote! { #to_json_value(answer.map_err(#method_error::into_error)?).map_err(#jrps_error::from) },
+				),
+				None => (quote! { () }, quote! { let () = answer; Ok(#json_value::Null) }),
+			};
+			let rpc_name = self.rpc_identifier(&method.name).into_owned();
+			push(&method.signature.sig.ident, rpc_name, &method.params, returns, answer);
+		}
+
+		for sub in &self.subscriptions {
+			let item = &sub.item;
+			let answer = quote! { #to_json_value(answer).map_err(#jrps_error::from) };
+			let rpc_name = self.rpc_identifier(&sub.name).into_owned();
+			push(&sub.signature.sig.ident, rpc_name, &sub.params, quote! { Vec<#item> }, answer);
+		}
+
+		let where_clause_def = &generics.where_clause;
+		let doc_comment =
+			format!("Typed expectations on a `MockClient` for the calls made by `{}Client`.", &self.trait_def.ident);
+
+		quote! {
+			#[doc = #doc_comment]
+			pub trait #trait_name #generics #where_clause_def {
+				#(#sigs)*
+			}
+
+			impl #impl_generics #trait_name #type_generics for #mock_client where #(#where_clause,)* {
+				#(#impls)*
+			}
+		}
+	}
+}
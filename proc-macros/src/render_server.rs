@@ -25,8 +25,9 @@
 // DEALINGS IN THE SOFTWARE.
 
 use super::RpcDescription;
-use crate::attributes::Resource;
-use crate::helpers::{generate_where_clause, is_option};
+use crate::attributes::{ParamKind, Resource};
+use crate::helpers::{generate_where_clause, is_option, result_ok_type, type_name};
+use crate::rpc_macro::{RpcFnArg, RpcSubscription};
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote, quote_spanned};
 use std::collections::HashSet;
@@ -35,10 +36,11 @@ impl RpcDescription {
 	pub(super) fn render_server(&self) -> Result<TokenStream2, syn::Error> {
 		let trait_name = quote::format_ident!("{}Server", &self.trait_def.ident);
 		let generics = self.trait_def.generics.clone();
-		let (impl_generics, _, where_clause) = generics.split_for_impl();
+		let where_clause = &generics.where_clause;
 
 		let method_impls = self.render_methods()?;
 		let into_rpc_impl = self.render_into_rpc()?;
+		let openrpc_impl = if self.openrpc { self.render_openrpc() } else { TokenStream2::new() };
 		let async_trait = self.jrps_server_item(quote! { types::__reexports::async_trait });
 
 		// Doc-comment to be associated with the server.
@@ -47,8 +49,9 @@ impl RpcDescription {
 		let trait_impl = quote! {
 			#[#async_trait]
 			#[doc = #doc_comment]
-			pub trait #trait_name #impl_generics: Sized + Send + Sync + 'static #where_clause {
+			pub trait #trait_name #generics: Sized + Send + Sync + 'static #where_clause {
 				#method_impls
+				#openrpc_impl
 				#into_rpc_impl
 			}
 		};
@@ -68,11 +71,7 @@ impl RpcDescription {
 
 		let subscriptions = self.subscriptions.iter().map(|sub| {
 			let docs = &sub.docs;
-			let subscription_sink_ty = self.jrps_server_item(quote! { SubscriptionSink });
-			// Add `SubscriptionSink` as the second input parameter to the signature.
-			let subscription_sink: syn::FnArg = syn::parse_quote!(subscription_sink: #subscription_sink_ty);
-			let mut sub_sig = sub.signature.clone();
-			sub_sig.sig.inputs.insert(1, subscription_sink);
+			let sub_sig = self.subscription_signature(sub);
 			quote! {
 				#docs
 				#sub_sig
@@ -85,6 +84,23 @@ impl RpcDescription {
 		})
 	}
 
+	/// Signature of the subscription in the server trait, which receives the `SubscriptionSink` (and optionally the
+	/// `ConnectionContext`) ahead of the parameters.
+	pub(crate) fn subscription_signature(&self, sub: &RpcSubscription) -> syn::TraitItemMethod {
+		let subscription_sink_ty = self.jrps_server_item(quote! { SubscriptionSink });
+		// Add `SubscriptionSink` as the second input parameter to the signature.
+		let subscription_sink: syn::FnArg = syn::parse_quote!(subscription_sink: #subscription_sink_ty);
+		let mut sub_sig = sub.signature.clone();
+		sub_sig.sig.inputs.insert(1, subscription_sink);
+		// Add `ConnectionContext` after the sink if requested.
+		if sub.with_context {
+			let connection_context_ty = self.jrps_server_item(quote! { ConnectionContext });
+			let connection_context: syn::FnArg = syn::parse_quote!(connection_context: #connection_context_ty);
+			sub_sig.sig.inputs.insert(2, connection_context);
+		}
+		sub_sig
+	}
+
 	fn render_into_rpc(&self) -> Result<TokenStream2, syn::Error> {
 		let rpc_module = self.jrps_server_item(quote! { RpcModule });
 
@@ -127,6 +143,9 @@ impl RpcDescription {
 
 				check_name(&rpc_method_name, rust_method_name.span());
 
+				let deprecated = !method.deprecated.is_empty();
+				let describe = self.render_describe(&rpc_method_name, deprecated, &method.param_kind, &method.params);
+
 				let resources = method.resources.iter().map(|resource| {
 					let Resource { name, value, .. } = resource;
 
@@ -143,11 +162,19 @@ impl RpcDescription {
 					}
 				};
 
-				if method.signature.sig.asyncness.is_some() {
+				// Typed method errors are converted into the error returned by the callbacks.
+				let into_error = if method.returns.is_some() {
+					let method_error = self.jrps_server_item(quote! { types::MethodError });
+					quote! { .map_err(#method_error::into_error) }
+				} else {
+					TokenStream2::new()
+				};
+
+				let register = if method.signature.sig.asyncness.is_some() {
 					handle_register_result(quote! {
 						rpc.register_async_method(#rpc_method_name, |params, context| async move {
 							#parsing
-							context.as_ref().#rust_method_name(#params_seq).await
+							context.as_ref().#rust_method_name(#params_seq).await#into_error
 						})
 						#resources
 					})
@@ -158,14 +185,43 @@ impl RpcDescription {
 					handle_register_result(quote! {
 						rpc.#register_kind(#rpc_method_name, |params, context| {
 							#parsing
-							context.#rust_method_name(#params_seq)
+							context.#rust_method_name(#params_seq)#into_error
 						})
 						#resources
 					})
+				};
+				let describe = handle_register_result(describe);
+
+				// Cached before the aliases are registered, so that they share the cached responses.
+				let cache = match method.cache {
+					Some(millis) => {
+						let cache_policy = self.jrps_server_item(quote! { types::cache::CachePolicy });
+						handle_register_result(quote! {
+							rpc.cache_method(
+								#rpc_method_name,
+								&response_cache,
+								#cache_policy::new(std::time::Duration::from_millis(#millis)),
+							)
+						})
+					}
+					None => TokenStream2::new(),
+				};
+
+				quote! {
+					#register
+					#describe
+					#cache
 				}
 			})
 			.collect::<Vec<_>>();
 
+		let response_cache = if self.methods.iter().any(|method| method.cache.is_some()) {
+			let response_cache = self.jrps_server_item(quote! { types::cache::ResponseCache });
+			quote! { let response_cache = #response_cache::new(); }
+		} else {
+			TokenStream2::new()
+		};
+
 		let subscriptions = self
 			.subscriptions
 			.iter()
@@ -194,12 +250,37 @@ impl RpcDescription {
 					None => rpc_sub_name.clone(),
 				};
 
-				handle_register_result(quote! {
-					rpc.register_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, sink, context| {
-						#parsing
-						context.as_ref().#rust_method_name(sink, #params_seq)
+				let connection_context = if sub.with_context {
+					quote! { let connection_context = sink.connection_context(); }
+				} else {
+					TokenStream2::new()
+				};
+				let sink_args = if sub.with_context { quote!(sink, connection_context) } else { quote!(sink) };
+
+				let register = if sub.signature.sig.asyncness.is_some() {
+					handle_register_result(quote! {
+						rpc.register_async_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, sink, context| async move {
+							#parsing
+							#connection_context
+							context.as_ref().#rust_method_name(#sink_args, #params_seq).await
+						})
+					})
+				} else {
+					handle_register_result(quote! {
+						rpc.register_subscription(#rpc_sub_name, #rpc_notif_name, #rpc_unsub_name, |params, sink, context| {
+							#parsing
+							#connection_context
+							context.as_ref().#rust_method_name(#sink_args, #params_seq)
+						})
 					})
-				})
+				};
+				let describe =
+					handle_register_result(self.render_describe(&rpc_sub_name, false, &sub.param_kind, &sub.params));
+
+				quote! {
+					#register
+					#describe
+				}
 			})
 			.collect::<Vec<_>>();
 
@@ -262,11 +343,34 @@ impl RpcDescription {
 			})
 			.collect::<Vec<_>>();
 
+		let discover = if self.openrpc {
+			let openrpc = self.jrps_server_item(quote! { types::openrpc });
+			let serde_json = self.jrps_server_item(quote! { types::__reexports::serde_json });
+
+			// The name of the method isn't known when the macro is expanded, a method of the trait with the same
+			// name is only detected when the module is built and makes `into_rpc` panic.
+			quote! {{
+				let document = #serde_json::value::to_raw_value(&Self::openrpc())
+					.expect("OpenRPC document is serializable; qed");
+				if let Err(err) = rpc.register_method(#openrpc::DISCOVER_METHOD, move |_, _| Ok(document.clone())) {
+					panic!(
+						"The OpenRPC document can't be served as `{}`, rename the method or remove the `openrpc` argument: {}",
+						#openrpc::DISCOVER_METHOD,
+						err
+					);
+				}
+			}}
+		} else {
+			TokenStream2::new()
+		};
+
 		let doc_comment = "Collects all the methods and subscriptions defined in the trait \
 								and adds them into a single `RpcModule`.";
 
 		let sub_tys: Vec<syn::Type> = self.subscriptions.clone().into_iter().map(|s| s.item).collect();
-		let where_clause = generate_where_clause(&self.trait_def, &sub_tys, false);
+		let jsonrpsee = self.jsonrpsee_server_path.as_ref().unwrap();
+		let mut where_clause = generate_where_clause(jsonrpsee, &self.trait_def, &sub_tys, false);
+		where_clause.extend(self.openrpc_bounds());
 
 		// NOTE(niklasad1): empty where clause is valid rust syntax.
 		Ok(quote! {
@@ -275,29 +379,232 @@ impl RpcDescription {
 				let mut rpc = #rpc_module::new(self);
 
 				#(#errors)*
+				#response_cache
 				#(#methods)*
 				#(#subscriptions)*
 				#(#method_aliases)*
 				#(#subscription_aliases)*
+				#discover
 
 				rpc
 			}
 		})
 	}
 
-	fn render_params_decoding(&self, params: &[(syn::PatIdent, syn::Type)]) -> (TokenStream2, TokenStream2) {
+	/// Generates the call describing the params of a method to the introspection method.
+	fn render_describe(
+		&self,
+		rpc_name: &str,
+		deprecated: bool,
+		param_kind: &ParamKind,
+		params: &[RpcFnArg],
+	) -> TokenStream2 {
+		let introspection = self.jrps_server_item(quote! { types::introspection });
+		let param_kind = match param_kind {
+			ParamKind::Array => quote! { #introspection::ParamKind::Array },
+			ParamKind::Map => quote! { #introspection::ParamKind::Map },
+		};
+		let params = params.iter().map(|param| {
+			let name = param.name();
+			let ty_name = type_name(param.ty());
+			let optional = param.is_optional();
+			if param.is_flattened() {
+				quote! { #introspection::ParamDescription::new(#name, #ty_name, #optional).flattened() }
+			} else {
+				quote! { #introspection::ParamDescription::new(#name, #ty_name, #optional) }
+			}
+		});
+
+		quote! {
+			rpc.describe(#rpc_name, #deprecated, #param_kind, vec![#(#params),*])
+		}
+	}
+
+	/// Generates the `openrpc` function, which describes the API as an OpenRPC document.
+	fn render_openrpc(&self) -> TokenStream2 {
+		let openrpc = self.jrps_server_item(quote! { types::openrpc });
+		let serde_json = self.jrps_server_item(quote! { types::__reexports::serde_json });
+		let title = self.trait_def.ident.to_string();
+
+		// Returns the params and their structure.
+		let render_params = |params: &[RpcFnArg]| {
+			if let [param] = params {
+				if param.is_flattened() {
+					let ty = param.ty();
+					return (
+						quote! { builder.flattened_content::<#ty>() },
+						quote! { #openrpc::ParamStructure::ByName },
+					);
+				}
+			}
+
+			let params = params.iter().map(|param| {
+				let name = param.name();
+				let ty = param.ty();
+				let required = !param.is_optional();
+				quote! { builder.content::<#ty>(#name, #required) }
+			});
+			(quote! { vec![#(#params),*] }, quote! { #openrpc::ParamStructure::Either })
+		};
+		let render_description = |description: &Option<String>| match description {
+			Some(description) => quote! { Some(#description.into()) },
+			None => quote! { None },
+		};
+
+		let methods = self.methods.iter().map(|method| {
+			let name = self.rpc_identifier(&method.name);
+			let description = render_description(&method.description);
+			let (params, param_structure) = render_params(&method.params);
+			let result = match &method.returns {
+				Some(returns) => {
+					let ty = result_ok_type(returns).unwrap_or(returns);
+					quote! { Some(builder.content::<#ty>("result", true)) }
+				}
+				None => quote! { None },
+			};
+			let deprecated = !method.deprecated.is_empty();
+			let aliases = &method.aliases;
+
+			quote! {{
+				let params = #params;
+				let result = #result;
+				builder.method(#openrpc::Method {
+					name: #name.into(),
+					description: #description,
+					params,
+					result,
+					deprecated: #deprecated,
+					param_structure: #param_structure,
+					aliases: vec![#(#aliases.into()),*],
+					subscription: None,
+				});
+			}}
+		});
+
+		let subscriptions = self.subscriptions.iter().map(|sub| {
+			let sub_name = self.rpc_identifier(&sub.name);
+			let unsub_name = self.rpc_identifier(&sub.unsubscribe);
+			let notif_name = match &sub.notif_name_override {
+				Some(notif) => self.rpc_identifier(notif),
+				None => sub_name.clone(),
+			};
+			let unsub_description = format!("Terminates a `{}` subscription.", sub_name);
+			let description = render_description(&sub.description);
+			let (params, param_structure) = render_params(&sub.params);
+			let item = &sub.item;
+			let aliases = &sub.aliases;
+			let unsub_aliases = &sub.unsubscribe_aliases;
+
+			quote! {{
+				let params = #params;
+				let item = builder.content::<#item>(#notif_name, true);
+				builder.method(#openrpc::Method {
+					name: #sub_name.into(),
+					description: #description,
+					params,
+					result: None,
+					deprecated: false,
+					param_structure: #param_structure,
+					aliases: vec![#(#aliases.into()),*],
+					subscription: Some(#openrpc::Subscription {
+						notification: #notif_name.into(),
+						unsubscribe: #unsub_name.into(),
+						item,
+					}),
+				});
+
+				let params = vec![builder.content::<#serde_json::Value>("subscription", true)];
+				let result = Some(builder.content::<bool>("result", true));
+				builder.method(#openrpc::Method {
+					name: #unsub_name.into(),
+					description: Some(#unsub_description.into()),
+					params,
+					result,
+					deprecated: false,
+					param_structure: #openrpc::ParamStructure::ByPosition,
+					aliases: vec![#(#unsub_aliases.into()),*],
+					subscription: None,
+				});
+			}}
+		});
+
+		let where_clause = self.openrpc_bounds();
+
+		quote! {
+			/// Describes the API as an [OpenRPC](https://spec.open-rpc.org) document, served by `rpc.discover`.
+			fn openrpc() -> #openrpc::OpenRpc where #(#where_clause,)* {
+				let mut builder = #openrpc::OpenRpcBuilder::new(#title, env!("CARGO_PKG_VERSION"));
+
+				#(#methods)*
+				#(#subscriptions)*
+
+				builder.build()
+			}
+		}
+	}
+
+	/// `JsonSchema` bounds of the type parameters, required to describe them in the OpenRPC document.
+	fn openrpc_bounds(&self) -> Vec<syn::WherePredicate> {
+		if !self.openrpc {
+			return Vec::new();
+		}
+
+		let schemars = self.jrps_server_item(quote! { types::__reexports::schemars });
+		self.trait_def
+			.generics
+			.type_params()
+			.map(|ty| {
+				let ident = &ty.ident;
+				syn::parse_quote! { #ident: #schemars::JsonSchema }
+			})
+			.collect()
+	}
+
+	pub(crate) fn render_params_decoding(&self, params: &[RpcFnArg]) -> (TokenStream2, TokenStream2) {
 		if params.is_empty() {
 			return (TokenStream2::default(), TokenStream2::default());
 		}
 
-		let params_fields_seq = params.iter().map(|(name, _)| name);
+		let params_fields_seq = params.iter().map(RpcFnArg::arg_pat);
 		let params_fields = quote! { #(#params_fields_seq),* };
 		let tracing = self.jrps_server_item(quote! { tracing });
 
+		// A flattened argument is decoded from the params as a whole.
+		if let [param] = params {
+			if param.is_flattened() {
+				let name = param.arg_pat();
+				let ty = param.ty();
+				let parsing = quote! {
+					let #name: #ty = match params.parse() {
+						Ok(v) => v,
+						Err(e) => {
+							#tracing::error!(concat!("Error parsing params as \"", stringify!(#ty), "\": {:?}"), e);
+							return Err(e.into())
+						}
+					};
+				};
+				return (parsing, params_fields);
+			}
+		}
+
 		// Code to decode sequence of parameters from a JSON array.
 		let decode_array = {
-			let decode_fields = params.iter().map(|(name, ty)| {
-				if is_option(ty) {
+			let decode_fields = params.iter().map(|param| {
+				let name = param.arg_pat();
+				let ty = param.ty();
+
+				if let Some(default) = param.default() {
+					quote! {
+						let #name: #ty = match seq.optional_next() {
+							Ok(Some(v)) => v,
+							Ok(None) => #default,
+							Err(e) => {
+								#tracing::error!(concat!("Error parsing optional \"", stringify!(#name), "\" as \"", stringify!(#ty), "\": {:?}"), e);
+								return Err(e.into())
+							}
+						};
+					}
+				} else if is_option(ty) {
 					quote! {
 						let #name: #ty = match seq.optional_next() {
 							Ok(v) => v,
@@ -333,11 +640,24 @@ impl RpcDescription {
 
 			let serde = self.jrps_server_item(quote! { types::__reexports::serde });
 			let serde_crate = serde.to_string();
-			let fields = params.iter().zip(generics.clone()).map(|((name, _), ty)| {
-				quote! { #name: #ty, }
+			let fields = params.iter().zip(generics.clone()).map(|(param, ty)| {
+				let name = &param.arg_pat().ident;
+				let rename = param.name();
+				// Missing or `null` params with a default value are decoded as `None`.
+				if param.default().is_some() {
+					quote! { #[serde(rename = #rename)] #name: Option<#ty>, }
+				} else {
+					quote! { #[serde(rename = #rename)] #name: #ty, }
+				}
+			});
+			let destruct = params.iter().map(|param| {
+				let name = &param.arg_pat().ident;
+				match param.default() {
+					Some(default) => quote! { parsed.#name.unwrap_or_else(|| #default) },
+					None => quote! { parsed.#name },
+				}
 			});
-			let destruct = params.iter().map(|(name, _)| quote! { parsed.#name });
-			let types = params.iter().map(|(_, ty)| ty);
+			let types = params.iter().map(RpcFnArg::ty);
 
 			quote! {
 				#[derive(#serde::Deserialize)]
@@ -28,44 +28,161 @@
 
 use crate::{
 	attributes::{
-		optional, parse_param_kind, Aliases, Argument, AttributeMeta, MissingArgument, NameMapping, ParamKind, Resource,
+		optional, parse_duration, parse_param_kind, Aliases, Argument, AttributeMeta, MissingArgument, NameMapping,
+		ParamKind, Resource,
 	},
-	helpers::extract_doc_comments,
+	helpers::{extract_doc_comments, extract_doc_text, is_option},
+	visitor::ReplaceAssociatedTypes,
 };
 
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use std::borrow::Cow;
+use std::collections::HashSet;
 use syn::spanned::Spanned;
+use syn::visit_mut::VisitMut;
 use syn::{punctuated::Punctuated, Attribute, Token};
 
+/// Argument of an RPC method or subscription.
+#[derive(Debug, Clone)]
+pub struct RpcFnArg {
+	arg_pat: syn::PatIdent,
+	ty: syn::Type,
+	/// Value used when the argument is omitted, set with `#[argument(default = <expr>)]`.
+	default: Option<syn::Expr>,
+	/// Name of the argument in params passed by name, set with `#[argument(rename = "<name>")]`.
+	rename: Option<String>,
+	/// Whether the argument is the entire params object, set with `#[argument(flatten)]`.
+	flatten: bool,
+}
+
+impl RpcFnArg {
+	/// Parses the argument and strips its `argument` attribute.
+	fn from_arg(arg: &mut syn::PatType) -> syn::Result<Self> {
+		let arg_pat = match &*arg.pat {
+			syn::Pat::Ident(name) => name.clone(),
+			syn::Pat::Wild(wild) => {
+				return Err(syn::Error::new(
+					wild.underscore_token.span(),
+					"Method argument names must be valid Rust identifiers; got `_` instead",
+				))
+			}
+			_ => {
+				return Err(syn::Error::new(
+					arg.span(),
+					format!("Unexpected method signature input; got {:?} ", *arg.pat),
+				))
+			}
+		};
+
+		let (default, rename, flatten) = match find_attr(&arg.attrs, "argument") {
+			Some(attr) => {
+				let [default, flatten, rename] =
+					AttributeMeta::parse(attr.clone())?.retain(["default", "flatten", "rename"])?;
+				let default = optional(default, Argument::value::<syn::Expr>)?;
+				let flatten = optional(flatten, Argument::flag)?.is_some();
+				let rename = optional(rename, Argument::string)?;
+
+				if flatten && (default.is_some() || rename.is_some()) {
+					return Err(syn::Error::new_spanned(
+						attr,
+						"A flattened argument can't have a default value or be renamed",
+					));
+				}
+
+				(default, rename, flatten)
+			}
+			None => (None, None, false),
+		};
+		arg.attrs.retain(|attr| !attr.path.is_ident("argument"));
+
+		Ok(Self { arg_pat, ty: (*arg.ty).clone(), default, rename, flatten })
+	}
+
+	/// Rust pattern of the argument.
+	pub fn arg_pat(&self) -> &syn::PatIdent {
+		&self.arg_pat
+	}
+
+	/// Rust type of the argument.
+	pub fn ty(&self) -> &syn::Type {
+		&self.ty
+	}
+
+	/// Value used when the argument is omitted.
+	pub fn default(&self) -> Option<&syn::Expr> {
+		self.default.as_ref()
+	}
+
+	/// Name of the argument in params passed by name.
+	pub fn name(&self) -> String {
+		self.rename.clone().unwrap_or_else(|| self.arg_pat.ident.to_string())
+	}
+
+	/// Whether the argument is the entire params object.
+	pub fn is_flattened(&self) -> bool {
+		self.flatten
+	}
+
+	/// Whether the argument may be omitted, i.e. it's an `Option` or has a default value.
+	pub fn is_optional(&self) -> bool {
+		self.default.is_some() || is_option(&self.ty)
+	}
+}
+
+/// Parses the arguments of an RPC method or subscription and strips their attributes from the signature.
+fn parse_args(sig: &mut syn::Signature) -> syn::Result<Vec<RpcFnArg>> {
+	let args: Vec<RpcFnArg> = sig
+		.inputs
+		.iter_mut()
+		.filter_map(|arg| match arg {
+			syn::FnArg::Receiver(_) => None,
+			syn::FnArg::Typed(arg) => Some(RpcFnArg::from_arg(arg)),
+		})
+		.collect::<syn::Result<_>>()?;
+
+	if args.len() > 1 {
+		if let Some(arg) = args.iter().find(|arg| arg.flatten) {
+			return Err(syn::Error::new_spanned(&arg.arg_pat, "A flattened argument must be the only argument"));
+		}
+	}
+
+	Ok(args)
+}
+
 #[derive(Debug, Clone)]
 pub struct RpcMethod {
 	pub name: String,
 	pub blocking: bool,
 	pub docs: TokenStream2,
+	/// Text of the doc comments, used as description in the OpenRPC document.
+	pub description: Option<String>,
 	pub deprecated: TokenStream2,
-	pub params: Vec<(syn::PatIdent, syn::Type)>,
+	pub params: Vec<RpcFnArg>,
 	pub param_kind: ParamKind,
 	pub returns: Option<syn::Type>,
 	pub signature: syn::TraitItemMethod,
 	pub aliases: Vec<String>,
 	pub resources: Punctuated<Resource, Token![,]>,
+	/// How long the responses are cached by the server in milliseconds, if they are.
+	pub cache: Option<u64>,
 }
 
 impl RpcMethod {
 	pub fn from_item(attr: Attribute, mut method: syn::TraitItemMethod) -> syn::Result<Self> {
-		let [aliases, blocking, name, param_kind, resources] =
-			AttributeMeta::parse(attr)?.retain(["aliases", "blocking", "name", "param_kind", "resources"])?;
+		let [aliases, blocking, cache, name, param_kind, resources] =
+			AttributeMeta::parse(attr)?.retain(["aliases", "blocking", "cache", "name", "param_kind", "resources"])?;
 
 		let aliases = parse_aliases(aliases)?;
 		let blocking = optional(blocking, Argument::flag)?.is_some();
+		let cache = parse_duration(cache)?;
 		let name = name?.string()?;
 		let param_kind = parse_param_kind(param_kind)?;
 		let resources = optional(resources, Argument::group)?.unwrap_or_default();
 
 		let sig = method.sig.clone();
 		let docs = extract_doc_comments(&method.attrs);
+		let description = extract_doc_text(&method.attrs);
 		let deprecated = match find_attr(&method.attrs, "deprecated") {
 			Some(attr) => quote!(#attr),
 			None => quote!(),
@@ -75,24 +192,7 @@ impl RpcMethod {
 			return Err(syn::Error::new(sig.span(), "Blocking method must be synchronous"));
 		}
 
-		let params: Vec<_> = sig
-			.inputs
-			.into_iter()
-			.filter_map(|arg| match arg {
-				syn::FnArg::Receiver(_) => None,
-				syn::FnArg::Typed(arg) => match *arg.pat {
-					syn::Pat::Ident(name) => Some(Ok((name, *arg.ty))),
-					syn::Pat::Wild(wild) => Some(Err(syn::Error::new(
-						wild.underscore_token.span(),
-						"Method argument names must be valid Rust identifiers; got `_` instead",
-					))),
-					_ => Some(Err(syn::Error::new(
-						arg.span(),
-						format!("Unexpected method signature input; got {:?} ", *arg.pat),
-					))),
-				},
-			})
-			.collect::<Result<_, _>>()?;
+		let params = parse_args(&mut method.sig)?;
 
 		let returns = match sig.output {
 			syn::ReturnType::Default => None,
@@ -105,12 +205,14 @@ impl RpcMethod {
 		Ok(Self {
 			aliases,
 			blocking,
+			cache,
 			name,
 			params,
 			param_kind,
 			returns,
 			signature: method,
 			docs,
+			description,
 			resources,
 			deprecated,
 		})
@@ -128,19 +230,23 @@ pub struct RpcSubscription {
 	/// If no override is provided, the subscription method name is used.
 	pub notif_name_override: Option<String>,
 	pub docs: TokenStream2,
+	/// Text of the doc comments, used as description in the OpenRPC document.
+	pub description: Option<String>,
 	pub unsubscribe: String,
-	pub params: Vec<(syn::PatIdent, syn::Type)>,
+	pub params: Vec<RpcFnArg>,
 	pub param_kind: ParamKind,
 	pub item: syn::Type,
 	pub signature: syn::TraitItemMethod,
 	pub aliases: Vec<String>,
 	pub unsubscribe_aliases: Vec<String>,
+	/// Whether the server method takes the `ConnectionContext` of the subscription after the sink.
+	pub with_context: bool,
 }
 
 impl RpcSubscription {
 	pub fn from_item(attr: syn::Attribute, mut sub: syn::TraitItemMethod) -> syn::Result<Self> {
-		let [aliases, item, name, param_kind, unsubscribe_aliases] =
-			AttributeMeta::parse(attr)?.retain(["aliases", "item", "name", "param_kind", "unsubscribe_aliases"])?;
+		let [aliases, item, name, param_kind, unsubscribe_aliases, with_context] = AttributeMeta::parse(attr)?
+			.retain(["aliases", "item", "name", "param_kind", "unsubscribe_aliases", "with_context"])?;
 
 		let aliases = parse_aliases(aliases)?;
 		let map = name?.value::<NameMapping>()?;
@@ -149,22 +255,13 @@ impl RpcSubscription {
 		let item = item?.value()?;
 		let param_kind = parse_param_kind(param_kind)?;
 		let unsubscribe_aliases = parse_aliases(unsubscribe_aliases)?;
+		let with_context = optional(with_context, Argument::flag)?.is_some();
 
-		let sig = sub.sig.clone();
 		let docs = extract_doc_comments(&sub.attrs);
+		let description = extract_doc_text(&sub.attrs);
 		let unsubscribe = build_unsubscribe_method(&name);
 
-		let params: Vec<_> = sig
-			.inputs
-			.into_iter()
-			.filter_map(|arg| match arg {
-				syn::FnArg::Receiver(_) => None,
-				syn::FnArg::Typed(arg) => match *arg.pat {
-					syn::Pat::Ident(name) => Some((name, *arg.ty)),
-					_ => panic!("Identifier in signature must be an ident"),
-				},
-			})
-			.collect();
+		let params = parse_args(&mut sub.sig)?;
 
 		// We've analyzed attributes and don't need them anymore.
 		sub.attrs.clear();
@@ -180,6 +277,8 @@ impl RpcSubscription {
 			signature: sub,
 			aliases,
 			docs,
+			description,
+			with_context,
 		})
 	}
 }
@@ -200,6 +299,10 @@ pub struct RpcDescription {
 	pub(crate) needs_client: bool,
 	/// Optional prefix for RPC namespace.
 	pub(crate) namespace: Option<String>,
+	/// Switch denoting that an OpenRPC document must be generated and served by `rpc.discover`.
+	pub(crate) openrpc: bool,
+	/// Switch denoting that the stub server and the expectations of the mock client must be generated.
+	pub(crate) mock: bool,
 	/// Trait definition in which all the attributes were stripped.
 	pub(crate) trait_def: syn::ItemTrait,
 	/// List of RPC methods defined in the trait.
@@ -210,15 +313,21 @@ pub struct RpcDescription {
 
 impl RpcDescription {
 	pub fn from_item(attr: Attribute, mut item: syn::ItemTrait) -> syn::Result<Self> {
-		let [client, server, namespace] = AttributeMeta::parse(attr)?.retain(["client", "server", "namespace"])?;
+		let [client, server, mock, namespace, openrpc] =
+			AttributeMeta::parse(attr)?.retain(["client", "server", "mock", "namespace", "openrpc"])?;
 
 		let needs_server = optional(server, Argument::flag)?.is_some();
 		let needs_client = optional(client, Argument::flag)?.is_some();
 		let namespace = optional(namespace, Argument::string)?;
+		let openrpc = optional(openrpc, Argument::flag)?.is_some();
+		let mock = optional(mock, Argument::flag)?.is_some();
 
 		if !needs_server && !needs_client {
 			return Err(syn::Error::new_spanned(&item.ident, "Either 'server' or 'client' attribute must be applied"));
 		}
+		if openrpc && !needs_server {
+			return Err(syn::Error::new_spanned(&item.ident, "The 'openrpc' attribute requires 'server'"));
+		}
 
 		let jsonrpsee_client_path = crate::helpers::find_jsonrpsee_client_crate().ok();
 		let jsonrpsee_server_path = crate::helpers::find_jsonrpsee_server_crate().ok();
@@ -229,9 +338,18 @@ impl RpcDescription {
 		if needs_server && jsonrpsee_server_path.is_none() {
 			return Err(syn::Error::new_spanned(&item.ident, "Unable to locate 'jsonrpsee' server dependency"));
 		}
+		// The mocks decode the calls the same way as the server does.
+		if mock && jsonrpsee_server_path.is_none() {
+			return Err(syn::Error::new_spanned(
+				&item.ident,
+				"The 'mock' attribute requires the 'jsonrpsee' server dependency",
+			));
+		}
 
 		item.attrs.clear(); // Remove RPC attributes.
 
+		let mut associated_types = lift_associated_types(&mut item)?;
+
 		let mut methods = Vec::new();
 		let mut subscriptions = Vec::new();
 
@@ -259,11 +377,9 @@ impl RpcDescription {
 							"Element cannot be both subscription and method at the same time",
 						));
 					}
-					if method.sig.asyncness.is_some() {
-						return Err(syn::Error::new_spanned(&method, "Subscription methods must not be `async`"));
-					}
-
-					let sub_data = RpcSubscription::from_item(attr.clone(), method.clone())?;
+					let mut sub_data = RpcSubscription::from_item(attr.clone(), method.clone())?;
+					// The item is parsed from the attribute, which isn't traversed when lifting associated types.
+					associated_types.visit_type_mut(&mut sub_data.item);
 					subscriptions.push(sub_data);
 				}
 
@@ -288,6 +404,8 @@ impl RpcDescription {
 			needs_server,
 			needs_client,
 			namespace,
+			openrpc,
+			mock,
 			trait_def: item,
 			methods,
 			subscriptions,
@@ -297,10 +415,12 @@ impl RpcDescription {
 	pub fn render(self) -> Result<TokenStream2, syn::Error> {
 		let server_impl = if self.needs_server { self.render_server()? } else { TokenStream2::new() };
 		let client_impl = if self.needs_client { self.render_client()? } else { TokenStream2::new() };
+		let mock_impl = if self.mock { self.render_mock()? } else { TokenStream2::new() };
 
 		Ok(quote! {
 			#server_impl
 			#client_impl
+			#mock_impl
 		})
 	}
 
@@ -331,6 +451,47 @@ impl RpcDescription {
 	}
 }
 
+/// Turns the associated types of the trait into type parameters of the same name, with the same bounds and default,
+/// and rewrites `Self::<Name>` paths accordingly. The generated client extension trait is implemented for every
+/// client, so it can't choose associated types and needs type parameters instead.
+fn lift_associated_types(item: &mut syn::ItemTrait) -> syn::Result<ReplaceAssociatedTypes> {
+	let mut names = HashSet::new();
+	let mut params = Vec::new();
+
+	for entry in item.items.iter() {
+		if let syn::TraitItem::Type(ty) = entry {
+			if !ty.generics.params.is_empty() || ty.generics.where_clause.is_some() {
+				return Err(syn::Error::new_spanned(
+					&ty.generics,
+					"Associated types with generics or where clauses are not supported",
+				));
+			}
+
+			names.insert(ty.ident.clone());
+			params.push(syn::GenericParam::Type(syn::TypeParam {
+				attrs: Vec::new(),
+				ident: ty.ident.clone(),
+				colon_token: ty.colon_token,
+				bounds: ty.bounds.clone(),
+				eq_token: ty.default.as_ref().map(|(eq, _)| *eq),
+				default: ty.default.as_ref().map(|(_, default)| default.clone()),
+			}));
+		}
+	}
+
+	let mut visitor = ReplaceAssociatedTypes { names };
+
+	if !params.is_empty() {
+		item.items.retain(|entry| !matches!(entry, syn::TraitItem::Type(_)));
+		item.generics.lt_token.get_or_insert_with(Default::default);
+		item.generics.gt_token.get_or_insert_with(Default::default);
+		item.generics.params.extend(params);
+		visitor.visit_item_trait_mut(item);
+	}
+
+	Ok(visitor)
+}
+
 fn parse_aliases(arg: Result<Argument, MissingArgument>) -> syn::Result<Vec<String>> {
 	let aliases = optional(arg, Argument::value::<Aliases>)?;
 
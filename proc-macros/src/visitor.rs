@@ -27,6 +27,7 @@
 use std::collections::HashSet;
 use syn::{
 	visit::{self, Visit},
+	visit_mut::{self, VisitMut},
 	Ident,
 };
 
@@ -169,9 +170,6 @@ impl FindSubscriptionParams {
 
 			syn::Type::Infer(_) | syn::Type::Never(_) | syn::Type::Verbatim(_) => {}
 
-			#[cfg(test)]
-			syn::Type::__TestExhaustive(_) => unimplemented!(),
-			#[cfg(not(test))]
 			_ => {}
 		}
 	}
@@ -230,6 +228,28 @@ impl FindSubscriptionParams {
 	fn visit_macro(&mut self, _mac: &syn::Macro) {}
 }
 
+/// Visitor that rewrites the paths to the given associated types of the trait, i.e. `Self::Hash`, into paths to
+/// type parameters of the same name, i.e. `Hash`.
+pub(crate) struct ReplaceAssociatedTypes {
+	pub(crate) names: HashSet<Ident>,
+}
+
+impl VisitMut for ReplaceAssociatedTypes {
+	fn visit_path_mut(&mut self, path: &mut syn::Path) {
+		let is_associated_type = path.leading_colon.is_none()
+			&& path.segments.len() >= 2
+			&& path.segments[0].ident == "Self"
+			&& path.segments[0].arguments.is_empty()
+			&& self.names.contains(&path.segments[1].ident);
+
+		if is_associated_type {
+			path.segments = path.segments.clone().into_iter().skip(1).collect();
+		}
+
+		visit_mut::visit_path_mut(self, path);
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -283,4 +303,14 @@ mod tests {
 
 		assert_eq!(exp, FindSubscriptionParams::new(generics).visit(&[t]));
 	}
+
+	#[test]
+	fn replace_associated_types() {
+		let mut visitor = ReplaceAssociatedTypes { names: [parse_quote!(Hash)].iter().cloned().collect() };
+		let mut ty: Type = parse_quote!(Vec<(Self::Hash, Self::Number, Self::Hash::Output)>);
+		visitor.visit_type_mut(&mut ty);
+
+		let expected: Type = parse_quote!(Vec<(Hash, Self::Number, Hash::Output)>);
+		assert_eq!(ty, expected);
+	}
 }
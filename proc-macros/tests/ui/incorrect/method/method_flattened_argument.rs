@@ -0,0 +1,9 @@
+use jsonrpsee::proc_macros::rpc;
+
+#[rpc(server)]
+pub trait FlattenedArgument {
+	#[method(name = "a")]
+	async fn a(&self, #[argument(flatten)] params: Vec<u8>, b: u8);
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use jsonrpsee::proc_macros::rpc;
+
+// Cache duration without a unit.
+#[rpc(server)]
+pub trait InvalidCache {
+	#[method(name = "foo", cache = "60")]
+	async fn async_method(&self) -> jsonrpsee::types::RpcResult<u8>;
+}
+
+fn main() {}
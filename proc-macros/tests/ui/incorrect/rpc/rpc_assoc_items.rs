@@ -1,6 +1,6 @@
 use jsonrpsee::proc_macros::rpc;
 
-// Associated items are forbidden.
+// Associated constants and generic associated types are forbidden.
 #[rpc(client, server)]
 pub trait AssociatedConst {
 	const WOO: usize;
@@ -11,7 +11,7 @@ pub trait AssociatedConst {
 
 #[rpc(client, server)]
 pub trait AssociatedType {
-	type Woo;
+	type Woo<T>;
 
 	#[method(name = "foo")]
 	async fn async_method(&self) -> jsonrpsee::types::RpcResult<u8>;
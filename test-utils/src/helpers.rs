@@ -27,10 +27,12 @@
 use crate::mocks::{Body, HttpResponse, Id, Uri};
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Request, Response, Server};
+use jsonrpsee_utils::server::access_log::AccessLogWriter;
 use serde::Serialize;
 use serde_json::Value;
 use std::convert::Infallible;
 use std::net::SocketAddr;
+use std::sync::Mutex;
 
 pub const PARSE_ERROR: &str = "Parse error";
 pub const INTERNAL_ERROR: &str = "Internal error";
@@ -164,6 +166,17 @@ pub async fn http_request(body: Body, uri: Uri) -> Result<HttpResponse, String>
 	Ok(HttpResponse { status: parts.status, header: parts.headers, body: String::from_utf8(bytes.to_vec()).unwrap() })
 }
 
+/// Send an HTTP GET request to `uri`.
+pub async fn http_get(uri: Uri) -> Result<HttpResponse, String> {
+	let client = hyper::Client::new();
+	let res = client.get(uri).await.map_err(|e| format!("{:?}", e))?;
+
+	let (parts, body) = res.into_parts();
+	let bytes = hyper::body::to_bytes(body).await.unwrap();
+
+	Ok(HttpResponse { status: parts.status, header: parts.headers, body: String::from_utf8(bytes.to_vec()).unwrap() })
+}
+
 /// Spawn HTTP server that responds with a hardcoded response.
 //
 // NOTE: This must be spawned on tokio because hyper only works with tokio.
@@ -193,3 +206,13 @@ pub async fn http_server_with_hardcoded_response(response: String) -> SocketAddr
 
 	rx.await.unwrap()
 }
+
+/// Access log writer collecting the records written by a server.
+#[derive(Debug, Default)]
+pub struct AccessLogRecords(pub Mutex<Vec<Value>>);
+
+impl AccessLogWriter for AccessLogRecords {
+	fn write_record(&self, line: &str) {
+		self.0.lock().unwrap().push(serde_json::from_str(line).unwrap());
+	}
+}
@@ -101,11 +101,24 @@ impl From<io::Error> for WebSocketTestError {
 
 impl WebSocketTestClient {
 	pub async fn new(url: SocketAddr) -> Result<Self, WebSocketTestError> {
+		Self::connect(url, None).await
+	}
+
+	/// Connect to the server with a client rejecting the frames larger than `max_frame_size` bytes.
+	pub async fn with_max_frame_size(url: SocketAddr, max_frame_size: usize) -> Result<Self, WebSocketTestError> {
+		Self::connect(url, Some(max_frame_size)).await
+	}
+
+	async fn connect(url: SocketAddr, max_frame_size: Option<usize>) -> Result<Self, WebSocketTestError> {
 		let socket = TcpStream::connect(url).await?;
 		let mut client = handshake::Client::new(BufReader::new(BufWriter::new(socket.compat())), "test-client", "/");
 		match client.handshake().await {
 			Ok(handshake::ServerResponse::Accepted { .. }) => {
-				let (tx, rx) = client.into_builder().finish();
+				let mut builder = client.into_builder();
+				if let Some(max_frame_size) = max_frame_size {
+					builder.set_max_frame_size(max_frame_size);
+				}
+				let (tx, rx) = builder.finish();
 				Ok(Self { tx, rx })
 			}
 			Ok(handshake::ServerResponse::Redirect { .. }) => Err(WebSocketTestError::Redirect),
@@ -188,7 +201,7 @@ impl WebSocketTestServer {
 	// Spawns a dummy `JSONRPC v2` WebSocket server that sends out a pre-configured subscription ID and subscription
 	// response.
 	//
-	// NOTE: ignores the actual subscription and unsubscription method.
+	// NOTE: ignores the actual subscription and unsubscription method, only the first request is answered.
 	pub async fn with_hardcoded_subscription(
 		sockaddr: SocketAddr,
 		subscription_id: String,
@@ -266,6 +279,8 @@ async fn connection_task(socket: tokio::net::TcpStream, mode: ServerMode, mut ex
 	});
 	pin_mut!(ws_stream);
 
+	let mut subscribed = false;
+
 	loop {
 		let next_ws = ws_stream.next().fuse();
 		let next_exit = exit.next().fuse();
@@ -299,7 +314,8 @@ async fn connection_task(socket: tokio::net::TcpStream, mode: ServerMode, mut ex
 								tracing::warn!("send response to request error: {:?}", e);
 							}
 						},
-						ServerMode::Subscription { subscription_id, .. } => {
+						ServerMode::Subscription { subscription_id, .. } if !subscribed => {
+							subscribed = true;
 							if let Err(e) = sender.send_text(&subscription_id).await {
 								tracing::warn!("send subscription id error: {:?}", e);
 							}
@@ -31,7 +31,19 @@ use jsonrpsee::{
 	RpcModule,
 };
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls;
+
+/// PEM encoded certificate of the CA signing the test certificates.
+pub const CA_PEM: &[u8] = include_bytes!("certs/ca.pem");
+/// PEM encoded client certificate signed by [`CA_PEM`].
+pub const CLIENT_PEM: &[u8] = include_bytes!("certs/client.pem");
+/// PEM encoded private key of [`CLIENT_PEM`].
+pub const CLIENT_KEY: &[u8] = include_bytes!("certs/client.key");
 
 pub async fn websocket_server_with_subscription() -> (SocketAddr, WsServerHandle) {
 	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
@@ -102,6 +114,12 @@ pub async fn websocket_server() -> SocketAddr {
 	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
 	let mut module = RpcModule::new(());
 	module.register_method("say_hello", |_, _| Ok("hello")).unwrap();
+	module
+		.register_method("add", |params, _| {
+			let nums: Vec<u64> = params.parse()?;
+			Ok(nums.iter().sum::<u64>())
+		})
+		.unwrap();
 
 	module
 		.register_async_method("slow_hello", |_, _| async {
@@ -123,7 +141,169 @@ pub async fn http_server() -> (SocketAddr, HttpServerHandle) {
 	let addr = server.local_addr().unwrap();
 	module.register_method("say_hello", |_, _| Ok("hello")).unwrap();
 	module.register_method("notif", |_, _| Ok("")).unwrap();
+	module
+		.register_method("add", |params, _| {
+			let nums: Vec<u64> = params.parse()?;
+			Ok(nums.iter().sum::<u64>())
+		})
+		.unwrap();
+
+	let handle = server.start(module).unwrap();
+	(addr, handle)
+}
+
+/// HTTP proxy tunneling connections with `CONNECT` or forwarding plain requests with an absolute URI, which records
+/// the requests sent to it and everything the clients send through the tunnels.
+pub async fn connect_proxy() -> (SocketAddr, Arc<Mutex<String>>) {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	let traffic = Arc::new(Mutex::new(String::new()));
+
+	let log = traffic.clone();
+	tokio::spawn(async move {
+		while let Ok((mut client, _)) = listener.accept().await {
+			let log = log.clone();
+			tokio::spawn(async move {
+				let mut head = Vec::new();
+				while !head.ends_with(b"\r\n\r\n") {
+					head.push(client.read_u8().await.unwrap());
+				}
+				let head = String::from_utf8(head).unwrap();
+				log.lock().unwrap().push_str(&head);
+				let (method, uri) =
+					head.split_once(' ').and_then(|(m, rest)| Some((m, rest.split(' ').next()?))).unwrap();
+				let target = match method {
+					"CONNECT" => uri.to_owned(),
+					_ => uri.strip_prefix("http://").and_then(|u| u.split('/').next()).unwrap().to_owned(),
+				};
+
+				let mut upstream = match TcpStream::connect(target).await {
+					Ok(upstream) => upstream,
+					Err(_) => {
+						let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n").await;
+						return;
+					}
+				};
+				if method == "CONNECT" {
+					client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n").await.unwrap();
+				} else {
+					// The origin server accepts the absolute URI as is.
+					upstream.write_all(head.as_bytes()).await.unwrap();
+				}
+
+				let (mut client_rx, mut client_tx) = client.into_split();
+				let (mut upstream_rx, mut upstream_tx) = upstream.into_split();
+				tokio::spawn(async move { tokio::io::copy(&mut upstream_rx, &mut client_tx).await });
+				let mut buf = vec![0; 4096];
+				loop {
+					let len = match client_rx.read(&mut buf).await {
+						Ok(0) | Err(_) => break,
+						Ok(len) => len,
+					};
+					log.lock().unwrap().push_str(&String::from_utf8_lossy(&buf[..len]));
+					if upstream_tx.write_all(&buf[..len]).await.is_err() {
+						break;
+					}
+				}
+			});
+		}
+	});
+
+	(addr, traffic)
+}
+
+/// TLS server for `localhost` forwarding the connections to `target`, which requires a client certificate signed
+/// by [`CA_PEM`] if `mtls`. It offers HTTP/2 with ALPN, which `target` must then support.
+pub async fn tls_proxy(target: SocketAddr, mtls: bool) -> SocketAddr {
+	let certs = |pem: &[u8]| rustls_pemfile::certs(&mut &*pem).unwrap().into_iter().map(rustls::Certificate);
+	let mut key = rustls_pemfile::pkcs8_private_keys(&mut &include_bytes!("certs/server.key")[..]).unwrap();
+
+	let builder = rustls::ServerConfig::builder().with_safe_defaults();
+	let builder = if mtls {
+		let mut roots = rustls::RootCertStore::empty();
+		for cert in certs(CA_PEM) {
+			roots.add(&cert).unwrap();
+		}
+		builder.with_client_cert_verifier(rustls::server::AllowAnyAuthenticatedClient::new(roots))
+	} else {
+		builder.with_no_client_auth()
+	};
+	let mut config = builder
+		.with_single_cert(certs(include_bytes!("certs/server.pem")).collect(), rustls::PrivateKey(key.remove(0)))
+		.unwrap();
+	config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+	let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(config));
+
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	tokio::spawn(async move {
+		while let Ok((socket, _)) = listener.accept().await {
+			let acceptor = acceptor.clone();
+			tokio::spawn(async move {
+				if let Ok(mut tls) = acceptor.accept(socket).await {
+					let mut upstream = TcpStream::connect(target).await.unwrap();
+					let _ = tokio::io::copy_bidirectional(&mut tls, &mut upstream).await;
+				}
+			});
+		}
+	});
+
+	addr
+}
+
+pub async fn http2_only_server() -> (SocketAddr, HttpServerHandle) {
+	let server =
+		HttpServerBuilder::default().http2_only(true).http2_max_concurrent_streams(8).build("127.0.0.1:0").unwrap();
+	let mut module = RpcModule::new(());
+	let addr = server.local_addr().unwrap();
+	module.register_method("say_hello", |_, _| Ok("hello")).unwrap();
 
 	let handle = server.start(module).unwrap();
 	(addr, handle)
 }
+
+/// HTTP server answering `503 Service Unavailable` to its first `failures` requests and `"hello"` to the next ones,
+/// which counts the requests it received.
+pub async fn flaky_http_server(failures: usize) -> (SocketAddr, Arc<AtomicUsize>) {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let addr = listener.local_addr().unwrap();
+	let requests = Arc::new(AtomicUsize::new(0));
+
+	let count = requests.clone();
+	tokio::spawn(async move {
+		while let Ok((mut stream, _)) = listener.accept().await {
+			let count = count.clone();
+			tokio::spawn(async move {
+				loop {
+					let mut head = Vec::new();
+					while !head.ends_with(b"\r\n\r\n") {
+						match stream.read_u8().await {
+							Ok(byte) => head.push(byte),
+							Err(_) => return,
+						}
+					}
+					let head = String::from_utf8(head).unwrap().to_lowercase();
+					let len = head
+						.lines()
+						.find_map(|line| line.strip_prefix("content-length:"))
+						.map_or(0, |len| len.trim().parse().unwrap());
+					let mut body = vec![0; len];
+					stream.read_exact(&mut body).await.unwrap();
+
+					let response = if count.fetch_add(1, Ordering::SeqCst) < failures {
+						"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n".to_owned()
+					} else {
+						let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+						let body = format!(r#"{{"jsonrpc":"2.0","result":"hello","id":{}}}"#, request["id"]);
+						format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}", body.len(), body)
+					};
+					if stream.write_all(response.as_bytes()).await.is_err() {
+						return;
+					}
+				}
+			});
+		}
+	});
+
+	(addr, requests)
+}
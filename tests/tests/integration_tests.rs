@@ -29,19 +29,26 @@
 
 mod helpers;
 
-use helpers::{http_server, websocket_server, websocket_server_with_subscription};
+use helpers::{
+	connect_proxy, flaky_http_server, http2_only_server, http_server, tls_proxy, websocket_server,
+	websocket_server_with_subscription, CA_PEM, CLIENT_KEY, CLIENT_PEM,
+};
 use jsonrpsee::{
 	http_client::HttpClientBuilder,
 	rpc_params,
 	types::{
+		batch::BatchRequestBuilder,
 		error::SubscriptionClosedReason,
+		pool::ClientPoolBuilder,
+		retry::{RetryOn, RetryPolicy},
 		traits::{Client, SubscriptionClient},
-		Error, JsonValue, Subscription,
+		CertificateStore, CustomCertStore, Error, JsonValue, Subscription,
 	},
-	ws_client::WsClientBuilder,
+	ws_client::{WsClientBuilder, WsEncoding},
 };
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[tokio::test]
 async fn ws_subscription_works() {
@@ -74,6 +81,27 @@ async fn ws_subscription_with_input_works() {
 	}
 }
 
+#[tokio::test]
+async fn ws_unsubscribe_waits_for_the_server() {
+	let (server_addr, _) = websocket_server_with_subscription().await;
+	let server_url = format!("ws://{}", server_addr);
+	let client = WsClientBuilder::default().build(&server_url).await.unwrap();
+
+	let mut hello_sub: Subscription<String> =
+		client.subscribe("subscribe_hello", None, "unsubscribe_hello").await.unwrap();
+	assert_eq!(hello_sub.next().await.unwrap().unwrap(), "hello from subscription");
+	hello_sub.unsubscribe().await.unwrap();
+
+	// The server closes the subscription before the client unsubscribes.
+	let mut noop_sub: Subscription<()> = client.subscribe("subscribe_noop", None, "unsubscribe_noop").await.unwrap();
+	assert!(matches!(noop_sub.next().await, Some(Err(Error::SubscriptionClosed(_)))));
+	assert!(matches!(noop_sub.unsubscribe().await, Err(Error::Request(_))));
+
+	// The client is still usable.
+	let hello_sub: Subscription<String> = client.subscribe("subscribe_hello", None, "unsubscribe_hello").await.unwrap();
+	hello_sub.unsubscribe().await.unwrap();
+}
+
 #[tokio::test]
 async fn ws_method_call_works() {
 	let server_addr = websocket_server().await;
@@ -376,3 +404,601 @@ async fn ws_batch_works() {
 	let responses: Vec<String> = client.batch_request(batch).await.unwrap();
 	assert_eq!(responses, vec!["hello".to_string(), "hello".to_string()]);
 }
+
+async fn assert_typed_batch(client: impl Client + Sync) {
+	let mut batch = BatchRequestBuilder::new();
+	let hello = batch.insert::<String>("say_hello", rpc_params![]);
+	let missing = batch.insert::<String>("missing", rpc_params![]);
+	let sum = batch.insert::<u64>("add", rpc_params![1, 2]);
+
+	let mut responses = client.send_batch(batch).await.unwrap();
+	assert_eq!(responses.num_failed(), 1);
+	assert_eq!(responses.take(hello).unwrap(), "hello");
+	assert_eq!(responses.take(sum).unwrap(), 3);
+	let err = responses.take(missing).unwrap_err();
+	assert!(matches!(&err, Error::Request(e) if e.contains("Method not found")));
+
+	// `batch_request` fails the whole batch instead, the client is still usable after.
+	let batch = vec![("say_hello", rpc_params![]), ("missing", rpc_params![])];
+	assert!(matches!(client.batch_request::<String>(batch).await, Err(Error::Request(_))));
+	assert_eq!(client.request::<String>("say_hello", None).await.unwrap(), "hello");
+}
+
+#[tokio::test]
+async fn ws_typed_batch_works() {
+	let server_addr = websocket_server().await;
+	let client = WsClientBuilder::default().build(&format!("ws://{}", server_addr)).await.unwrap();
+	assert_typed_batch(client).await;
+}
+
+#[tokio::test]
+async fn http_typed_batch_works() {
+	let (server_addr, _handle) = http_server().await;
+	let client = HttpClientBuilder::default().no_proxy().build(format!("http://{}", server_addr)).unwrap();
+	assert_typed_batch(client).await;
+}
+
+async fn assert_raw_request(client: &(impl Client + Sync)) {
+	let params = serde_json::value::RawValue::from_string("[1, 2]".into()).unwrap();
+	let sum = client.request_raw("add", Some(params)).await.unwrap();
+	assert_eq!(sum.get(), "3");
+	assert!(matches!(client.request_raw("missing", None).await, Err(Error::Request(_))));
+}
+
+fn assert_raw_json(response: Option<Box<serde_json::value::RawValue>>, expected: &str) {
+	let response: JsonValue = serde_json::from_str(response.unwrap().get()).unwrap();
+	assert_eq!(response, serde_json::from_str::<JsonValue>(expected).unwrap());
+}
+
+const RAW_BATCH: &str = r#"[
+	{"jsonrpc":"2.0","method":"add","params":[1,2],"id":"first"},
+	{"jsonrpc":"2.0","method":"missing","id":7}
+]"#;
+
+const RAW_BATCH_RESPONSE: &str = r#"[
+	{"jsonrpc":"2.0","result":3,"id":"first"},
+	{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":7}
+]"#;
+
+#[tokio::test]
+async fn ws_raw_messages_work() {
+	let server_addr = websocket_server().await;
+	let client = WsClientBuilder::default().build(&format!("ws://{}", server_addr)).await.unwrap();
+	assert_raw_request(&client).await;
+
+	let response = client.send_raw(r#"{"jsonrpc":"2.0","method":"say_hello","id":"abc"}"#).await.unwrap();
+	assert_raw_json(response, r#"{"jsonrpc":"2.0","result":"hello","id":"abc"}"#);
+	assert_raw_json(client.send_raw(RAW_BATCH).await.unwrap(), RAW_BATCH_RESPONSE);
+	assert!(client.send_raw(r#"{"jsonrpc":"2.0","method":"say_hello"}"#).await.unwrap().is_none());
+	assert!(client.send_raw("not json").await.is_err());
+}
+
+#[tokio::test]
+async fn http_raw_messages_work() {
+	let (server_addr, _handle) = http_server().await;
+	let client = HttpClientBuilder::default().no_proxy().build(format!("http://{}", server_addr)).unwrap();
+	assert_raw_request(&client).await;
+
+	let response = client.send_raw(r#"{"jsonrpc":"2.0","method":"say_hello","id":"abc"}"#).await.unwrap();
+	assert_raw_json(response, r#"{"jsonrpc":"2.0","result":"hello","id":"abc"}"#);
+	assert_raw_json(client.send_raw(RAW_BATCH).await.unwrap(), RAW_BATCH_RESPONSE);
+	assert!(client.send_raw(r#"{"jsonrpc":"2.0","method":"say_hello"}"#).await.unwrap().is_none());
+	assert!(client.send_raw("not json").await.is_err());
+}
+
+#[tokio::test]
+async fn ws_permessage_deflate_works() {
+	use jsonrpsee::ws_server::{RpcModule, WsServerBuilder};
+
+	let server = WsServerBuilder::default()
+		.permessage_deflate(true)
+		.max_request_body_size(1024)
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let mut module = RpcModule::new(());
+	module.register_method("echo", |params, _| params.one::<String>().map_err(Into::into)).unwrap();
+	module.register_method("repeat", |params, _| Ok("a".repeat(params.one()?))).unwrap();
+	let server_url = format!("ws://{}", server.local_addr().unwrap());
+	let _handle = server.start(module).unwrap();
+
+	let client = WsClientBuilder::default().permessage_deflate(true).build(&server_url).await.unwrap();
+
+	let msg = "jsonrpsee".repeat(50);
+	let response: String = client.request("echo", rpc_params![msg.clone()]).await.unwrap();
+	assert_eq!(response, msg);
+
+	// Compresses well below the limit, but the limit applies to the decompressed message. The server replies
+	// with an oversized request error without an ID, which terminates the client.
+	let err = client.request::<String>("echo", rpc_params!["a".repeat(2000)]).await.unwrap_err();
+	assert!(matches!(err, Error::RestartNeeded(_)));
+
+	// The limit of the client applies to the decompressed responses.
+	let client = WsClientBuilder::default()
+		.permessage_deflate(true)
+		.max_request_body_size(512)
+		.build(&server_url)
+		.await
+		.unwrap();
+	assert_eq!(client.request::<String>("repeat", rpc_params![100]).await.unwrap().len(), 100);
+	let err = client.request::<String>("repeat", rpc_params![700]).await.unwrap_err();
+	assert!(matches!(err, Error::RestartNeeded(_)));
+}
+
+#[tokio::test]
+async fn ws_binary_encodings_work() {
+	use jsonrpsee::{
+		types::CallError,
+		ws_server::{RpcModule, WsEncoding, WsServerBuilder},
+	};
+
+	let server = WsServerBuilder::default()
+		.enable_encoding(WsEncoding::Cbor)
+		.enable_encoding(WsEncoding::MessagePack)
+		.build("127.0.0.1:0")
+		.await
+		.unwrap();
+	let mut module = RpcModule::new(());
+	module.register_method("echo", |params, _| params.one::<JsonValue>().map_err(Into::into)).unwrap();
+	// The data of errors is kept as raw JSON, which is transcoded.
+	module
+		.register_method::<(), _>("fail", |_, _| {
+			let data = serde_json::value::to_raw_value(&serde_json::json!({ "reason": [1, 2] })).ok();
+			Err(CallError::Custom { code: 1234, message: "Failed".into(), data }.into())
+		})
+		.unwrap();
+	module
+		.register_subscription("subscribe_count", "count", "unsubscribe_count", |_, mut sink, _| {
+			std::thread::spawn(move || {
+				for i in 0..3_u64 {
+					sink.send(&i).unwrap();
+				}
+			});
+			Ok(())
+		})
+		.unwrap();
+	let server_url = format!("ws://{}", server.local_addr().unwrap());
+	let _handle = server.start(module).unwrap();
+
+	let value = serde_json::json!({ "list": [1, -2, 3.5, "four", null], "nested": { "flag": true } });
+
+	for encoding in [WsEncoding::Json, WsEncoding::Cbor, WsEncoding::MessagePack] {
+		let client = WsClientBuilder::default().encoding(encoding).build(&server_url).await.unwrap();
+
+		let response: JsonValue = client.request("echo", rpc_params![value.clone()]).await.unwrap();
+		assert_eq!(response, value);
+
+		let batch = vec![("echo", rpc_params![1]), ("echo", rpc_params![2])];
+		let responses: Vec<u64> = client.batch_request(batch).await.unwrap();
+		assert_eq!(responses, vec![1, 2]);
+
+		let mut sub: Subscription<u64> = client.subscribe("subscribe_count", None, "unsubscribe_count").await.unwrap();
+		for i in 0..3 {
+			assert_eq!(sub.next().await.unwrap().unwrap(), i);
+		}
+
+		let err = client.request::<JsonValue>("fail", None).await.unwrap_err();
+		assert!(matches!(&err, Error::Request(e) if e.contains(r#""data":{"reason":[1,2]}"#)), "{:?}", err);
+	}
+}
+
+#[tokio::test]
+async fn ws_binary_encoding_falls_back_to_json() {
+	let server_addr = websocket_server().await;
+	let server_url = format!("ws://{}", server_addr);
+	let client = WsClientBuilder::default().encoding(WsEncoding::Cbor).build(&server_url).await.unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+}
+
+#[tokio::test]
+async fn http_client_sends_headers_and_basic_auth() {
+	let (server_addr, _handle) = http_server().await;
+	let (proxy_addr, traffic) = connect_proxy().await;
+	let counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+	let count = counter.clone();
+
+	let client = HttpClientBuilder::default()
+		.add_header("X-Static", "static")
+		.add_dynamic_header("X-Dynamic", move || {
+			format!("call-{}", count.fetch_add(1, std::sync::atomic::Ordering::SeqCst))
+		})
+		.proxy(format!("http://proxy:secret@{}", proxy_addr))
+		.build(format!("http://user:pass@{}", server_addr))
+		.unwrap();
+
+	for _ in 0..2 {
+		let response: String = client.request("say_hello", None).await.unwrap();
+		assert_eq!(response, "hello");
+	}
+
+	let traffic = traffic.lock().unwrap().to_lowercase();
+	// Plain HTTP requests are forwarded by the proxy rather than tunneled.
+	assert!(traffic.contains(&format!("post http://{}/ http/1.1", server_addr)));
+	assert!(!traffic.contains("connect "));
+	assert!(traffic.contains("proxy-authorization: basic chjvehk6c2vjcmv0"));
+	assert!(traffic.contains("\nauthorization: basic dxnlcjpwyxnz"));
+	assert!(traffic.contains("x-static: static"));
+	assert!(traffic.contains("x-dynamic: call-0"));
+	assert!(traffic.contains("x-dynamic: call-1"));
+	assert!(!traffic.contains(&format!("host: user:pass@{}", server_addr)));
+}
+
+#[tokio::test]
+async fn http_client_rejects_invalid_header() {
+	let err = HttpClientBuilder::default().add_header("Invalid Name", "value").build("http://127.0.0.1:9933");
+	assert!(matches!(err, Err(Error::Transport(_))));
+}
+
+#[tokio::test]
+async fn ws_client_connects_through_proxy() {
+	let server_addr = websocket_server().await;
+	let (proxy_addr, traffic) = connect_proxy().await;
+
+	let client = WsClientBuilder::default()
+		.proxy(format!("http://user:pass@{}", proxy_addr))
+		.build(&format!("ws://{}", server_addr))
+		.await
+		.unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+
+	let traffic = traffic.lock().unwrap().to_lowercase();
+	assert!(traffic.contains(&format!("connect {} http/1.1", server_addr)));
+	assert!(traffic.contains("proxy-authorization: basic dxnlcjpwyxnz"));
+	assert!(traffic.contains("upgrade: websocket"));
+}
+
+#[tokio::test]
+async fn ws_client_proxy_does_not_resolve_target() {
+	let (proxy_addr, traffic) = connect_proxy().await;
+
+	// The proxy can't reach the target either, but the client must not fail to resolve it.
+	let err = WsClientBuilder::default()
+		.proxy(format!("http://{}", proxy_addr))
+		.connection_timeout(Duration::from_millis(500))
+		.build("ws://unresolvable.invalid:9944")
+		.await
+		.unwrap_err();
+	assert!(matches!(err, Error::Transport(_)));
+	assert!(traffic.lock().unwrap().contains("CONNECT unresolvable.invalid:9944 HTTP/1.1"));
+}
+
+#[tokio::test]
+async fn ws_client_custom_certificate_store_works() {
+	let server_addr = websocket_server().await;
+	let tls_addr = tls_proxy(server_addr, false).await;
+	let server_url = format!("wss://localhost:{}", tls_addr.port());
+
+	let custom = CertificateStore::Custom(CustomCertStore::from_pem(CA_PEM));
+	let client = WsClientBuilder::default().certificate_store(custom).no_proxy().build(&server_url).await.unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+
+	// The test CA is not trusted by default.
+	let err = WsClientBuilder::default().certificate_store(CertificateStore::WebPki).build(&server_url).await;
+	assert!(matches!(err, Err(Error::Transport(_))));
+}
+
+#[tokio::test]
+async fn ws_client_mutual_tls_works() {
+	let server_addr = websocket_server().await;
+	let tls_addr = tls_proxy(server_addr, true).await;
+	let server_url = format!("wss://localhost:{}", tls_addr.port());
+
+	let custom = CustomCertStore::from_pem(CA_PEM).with_client_auth(CLIENT_PEM, CLIENT_KEY);
+	let client = WsClientBuilder::default()
+		.certificate_store(CertificateStore::Custom(custom))
+		.no_proxy()
+		.build(&server_url)
+		.await
+		.unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+
+	let without_client_auth = CertificateStore::Custom(CustomCertStore::from_pem(CA_PEM));
+	let err = WsClientBuilder::default().certificate_store(without_client_auth).no_proxy().build(&server_url).await;
+	assert!(matches!(err, Err(Error::Transport(_))));
+}
+
+#[tokio::test]
+async fn http_client_mutual_tls_works() {
+	let (server_addr, _handle) = http_server().await;
+	let tls_addr = tls_proxy(server_addr, true).await;
+	let server_url = format!("https://localhost:{}", tls_addr.port());
+
+	let custom = CustomCertStore::from_pem(CA_PEM).with_client_auth(CLIENT_PEM, CLIENT_KEY);
+	let client = HttpClientBuilder::default()
+		.certificate_store(CertificateStore::Custom(custom))
+		.no_proxy()
+		.build(&server_url)
+		.unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+
+	let without_client_auth = CertificateStore::Custom(CustomCertStore::from_pem(CA_PEM));
+	let client =
+		HttpClientBuilder::default().certificate_store(without_client_auth).no_proxy().build(&server_url).unwrap();
+	assert!(matches!(client.request::<String>("say_hello", None).await, Err(Error::Transport(_))));
+}
+
+#[tokio::test]
+async fn invalid_custom_certificate_store_is_rejected() {
+	let custom = CertificateStore::Custom(CustomCertStore::from_pem("not a certificate"));
+	let err = HttpClientBuilder::default().certificate_store(custom.clone()).build("https://localhost:9933");
+	assert!(matches!(err, Err(Error::Transport(_))));
+	let err = WsClientBuilder::default().certificate_store(custom).build("wss://localhost:9933").await;
+	assert!(matches!(err, Err(Error::Transport(_))));
+
+	let custom = CustomCertStore::from_pem(CA_PEM).with_client_auth(CLIENT_PEM, "no key");
+	let err = HttpClientBuilder::default()
+		.certificate_store(CertificateStore::Custom(custom))
+		.build("https://localhost:9933");
+	assert!(matches!(err, Err(Error::Transport(_))));
+}
+
+#[tokio::test]
+async fn http2_prior_knowledge_works() {
+	let (server_addr, _handle) = http2_only_server().await;
+	let server_url = format!("http://{}", server_addr);
+
+	let client = HttpClientBuilder::default()
+		.http2_prior_knowledge()
+		.http2_keep_alive(Duration::from_secs(10), Duration::from_secs(5))
+		.build(&server_url)
+		.unwrap();
+	// Streams opened before the settings of the server are received may be refused, so the first call is alone.
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+
+	// More calls than the server allows concurrent streams, they are multiplexed and queued on one connection.
+	let calls = (0..64).map(|_| client.request::<String>("say_hello", None));
+	for response in futures::future::join_all(calls).await {
+		assert_eq!(response.unwrap(), "hello");
+	}
+
+	let http1_client = HttpClientBuilder::default().build(&server_url).unwrap();
+	assert!(http1_client.request::<String>("say_hello", None).await.is_err());
+}
+
+#[tokio::test]
+async fn http2_alpn_over_tls_works() {
+	let (server_addr, _handle) = http2_only_server().await;
+	let tls_addr = tls_proxy(server_addr, false).await;
+	let server_url = format!("https://localhost:{}", tls_addr.port());
+	let custom = CertificateStore::Custom(CustomCertStore::from_pem(CA_PEM));
+
+	let client = HttpClientBuilder::default()
+		.certificate_store(custom.clone())
+		.enable_http2()
+		.no_proxy()
+		.build(&server_url)
+		.unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+
+	// Without ALPN the TLS server falls back to HTTP/1.1, which the server does not accept.
+	let http1_client = HttpClientBuilder::default().certificate_store(custom).no_proxy().build(&server_url).unwrap();
+	assert!(http1_client.request::<String>("say_hello", None).await.is_err());
+}
+
+#[tokio::test]
+async fn http2_is_accepted_by_default() {
+	let (server_addr, _handle) = http_server().await;
+	let client = HttpClientBuilder::default().http2_prior_knowledge().build(format!("http://{}", server_addr)).unwrap();
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+}
+
+#[tokio::test]
+async fn http_client_pool_fails_over() {
+	let (server_addr, _handle) = http_server().await;
+	let (stopped_addr, stopped_handle) = http_server().await;
+	stopped_handle.stop().unwrap().await.unwrap();
+
+	let pool = ClientPoolBuilder::default().max_failures(2);
+	let client = HttpClientBuilder::default()
+		.no_proxy()
+		.build_pool(pool, [format!("http://{}", stopped_addr), format!("http://{}", server_addr)])
+		.unwrap();
+
+	for _ in 0..6 {
+		let response: String = client.request("say_hello", None).await.unwrap();
+		assert_eq!(response, "hello");
+	}
+
+	let stats = client.stats();
+	assert_eq!((stats[0].failures, stats[0].ejected), (2, true));
+	assert_eq!((stats[1].successes, stats[1].ejected), (6, false));
+}
+
+#[tokio::test]
+async fn ws_client_pool_works() {
+	let (server_addr, _handle) = websocket_server_with_subscription().await;
+	// Nothing listens on the address of a dropped listener.
+	let closed_addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+	let client = WsClientBuilder::default()
+		.no_proxy()
+		.build_pool(ClientPoolBuilder::default(), [format!("ws://{}", closed_addr), format!("ws://{}", server_addr)]);
+
+	for _ in 0..4 {
+		let response: String = client.request("say_hello", None).await.unwrap();
+		assert_eq!(response, "hello");
+	}
+	let mut sub: Subscription<String> = client.subscribe("subscribe_hello", None, "unsubscribe_hello").await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), "hello from subscription");
+
+	let stats = client.stats();
+	assert_eq!(stats[0].successes, 0);
+	assert!(stats[0].failures > 0);
+	assert_eq!(stats[1].successes, 5);
+}
+
+#[tokio::test]
+async fn http_client_retries_transient_failures() {
+	let (server_addr, requests) = flaky_http_server(2).await;
+	let policy =
+		RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_millis(50)).retry_method("say_hello");
+	let client =
+		HttpClientBuilder::default().no_proxy().retry_policy(policy).build(format!("http://{}", server_addr)).unwrap();
+
+	let response: String = client.request("say_hello", None).await.unwrap();
+	assert_eq!(response, "hello");
+	assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn http_client_only_retries_allowed_methods() {
+	let (server_addr, requests) = flaky_http_server(2).await;
+	let policy =
+		RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_millis(50)).retry_method("say_hi");
+	let client =
+		HttpClientBuilder::default().no_proxy().retry_policy(policy).build(format!("http://{}", server_addr)).unwrap();
+
+	assert!(matches!(client.request::<String>("say_hello", None).await, Err(Error::Transport(_))));
+	assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn http_client_retries_within_request_timeout() {
+	let (server_addr, requests) = flaky_http_server(usize::MAX).await;
+	let policy =
+		RetryPolicy::new(100).backoff(Duration::from_millis(50), Duration::from_millis(50)).retry_all_methods();
+	let client = HttpClientBuilder::default()
+		.no_proxy()
+		.request_timeout(Duration::from_millis(300))
+		.retry_policy(policy)
+		.build(format!("http://{}", server_addr))
+		.unwrap();
+
+	let started_at = Instant::now();
+	// The last attempt fails, or times out if it started right before the deadline.
+	let res = client.request::<String>("say_hello", None).await;
+	assert!(matches!(res, Err(Error::Transport(_)) | Err(Error::RequestTimeout)));
+	assert!(started_at.elapsed() < Duration::from_secs(1));
+	assert!((2..10).contains(&requests.load(Ordering::SeqCst)));
+}
+
+#[tokio::test]
+async fn ws_client_retries_on_error_codes() {
+	use jsonrpsee::{types::CallError, ws_server::WsServerBuilder, RpcModule};
+
+	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let server_url = format!("ws://{}", server.local_addr().unwrap());
+	let mut module = RpcModule::new(AtomicUsize::new(0));
+	module
+		.register_method("busy", |_, attempts| match attempts.fetch_add(1, Ordering::SeqCst) {
+			0 | 1 => Err(Error::Call(CallError::Custom { code: -32005, message: "Busy".into(), data: None })),
+			_ => Ok("done"),
+		})
+		.unwrap();
+	module
+		.register_method("failing", |_, _| -> Result<(), Error> {
+			Err(Error::Call(CallError::Custom { code: -32000, message: "Failed".into(), data: None }))
+		})
+		.unwrap();
+	let _handle = server.start(module).unwrap();
+
+	let policy = RetryPolicy::new(3)
+		.backoff(Duration::from_millis(10), Duration::from_millis(50))
+		.retry_on([RetryOn::Code(-32005)])
+		.retry_all_methods();
+	let client = WsClientBuilder::default().no_proxy().retry_policy(policy).build(&server_url).await.unwrap();
+
+	let response: String = client.request("busy", None).await.unwrap();
+	assert_eq!(response, "done");
+	assert!(matches!(client.request::<()>("failing", None).await, Err(Error::Request(_))));
+}
+
+#[tokio::test]
+async fn ws_gateway_forwards_methods_and_subscriptions() {
+	use jsonrpsee::{types::CallError, utils::server::gateway::Gateway, ws_server::WsServerBuilder, RpcModule};
+
+	// Upstream server counting its active subscriptions.
+	let upstream = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let upstream_url = format!("ws://{}", upstream.local_addr().unwrap());
+	let active = Arc::new(AtomicUsize::new(0));
+	let mut module = RpcModule::new(active.clone());
+	module.register_method("say_hello", |_, _| Ok("hello")).unwrap();
+	module.register_method("secret", |_, _| Ok("secret")).unwrap();
+	module
+		.register_method("failing", |_, _| -> Result<(), Error> {
+			let data = serde_json::value::to_raw_value("details").ok();
+			Err(Error::Call(CallError::Custom { code: -32099, message: "Upstream failure".into(), data }))
+		})
+		.unwrap();
+	module
+		.register_subscription("subscribe_count", "count", "unsubscribe_count", |params, mut sink, active| {
+			let mut count: usize = params.one()?;
+			active.fetch_add(1, Ordering::SeqCst);
+			std::thread::spawn(move || {
+				loop {
+					count += 1;
+					if sink.send(&count).is_err() {
+						break;
+					}
+					std::thread::sleep(Duration::from_millis(20));
+				}
+				active.fetch_sub(1, Ordering::SeqCst);
+			});
+			Ok(())
+		})
+		.unwrap();
+	let _upstream_handle = upstream.start(module).unwrap();
+
+	let mut gateway = Gateway::new(WsClientBuilder::default().build(&upstream_url).await.unwrap());
+	gateway.forward_method("say_hello").unwrap();
+	gateway.forward_method("failing").unwrap();
+	gateway.forward_subscription("subscribe_count", "count", "unsubscribe_count").unwrap();
+
+	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let server_url = format!("ws://{}", server.local_addr().unwrap());
+	let mut module = RpcModule::new(());
+	module.register_method("local_hello", |_, _| Ok("local hello")).unwrap();
+	module.merge(gateway).unwrap();
+	let _handle = server.start(module).unwrap();
+
+	let client = WsClientBuilder::default().build(&server_url).await.unwrap();
+	assert_eq!(client.request::<String>("say_hello", None).await.unwrap(), "hello");
+	assert_eq!(client.request::<String>("local_hello", None).await.unwrap(), "local hello");
+	let err = client.request::<String>("secret", None).await.unwrap_err();
+	assert!(matches!(&err, Error::Request(e) if e.contains("Method not found")));
+	let err = client.request::<()>("failing", None).await.unwrap_err();
+	assert!(
+		matches!(&err, Error::Request(e) if e.contains("-32099") && e.contains("Upstream failure") && e.contains("details"))
+	);
+
+	let mut sub: Subscription<usize> =
+		client.subscribe("subscribe_count", rpc_params![10], "unsubscribe_count").await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), 11);
+	assert_eq!(sub.next().await.unwrap().unwrap(), 12);
+	assert_eq!(active.load(Ordering::SeqCst), 1);
+
+	// Unsubscribing from the gateway unsubscribes upstream.
+	drop(sub);
+	let started_at = Instant::now();
+	while active.load(Ordering::SeqCst) != 0 {
+		assert!(started_at.elapsed() < Duration::from_secs(5), "upstream subscription is still active");
+		tokio::time::sleep(Duration::from_millis(20)).await;
+	}
+}
+
+#[tokio::test]
+async fn http_gateway_forwards_methods() {
+	use jsonrpsee::{http_server::HttpServerBuilder, utils::server::gateway::Gateway};
+
+	let (upstream_addr, _upstream_handle) = http_server().await;
+	let upstream = HttpClientBuilder::default().no_proxy().build(format!("http://{}", upstream_addr)).unwrap();
+	let mut gateway = Gateway::new(upstream);
+	gateway.forward_method("add").unwrap();
+
+	let server = HttpServerBuilder::default().build("127.0.0.1:0").unwrap();
+	let server_url = format!("http://{}", server.local_addr().unwrap());
+	let _handle = server.start(gateway).unwrap();
+
+	let client = HttpClientBuilder::default().no_proxy().build(server_url).unwrap();
+	assert_eq!(client.request::<u64>("add", rpc_params![1, 2, 3]).await.unwrap(), 6);
+	let err = client.request::<String>("say_hello", None).await.unwrap_err();
+	assert!(matches!(&err, Error::Request(e) if e.contains("Method not found")));
+}
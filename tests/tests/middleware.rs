@@ -28,7 +28,12 @@ use jsonrpsee::{
 	http_client::HttpClientBuilder,
 	http_server::{HttpServerBuilder, HttpServerHandle},
 	proc_macros::rpc,
-	types::{middleware::Middleware, traits::Client, Error},
+	types::{
+		middleware::{ClientMiddleware, ClientRequest, Middleware},
+		traits::Client,
+		v2::ParamsSer,
+		CallError, Error, JsonValue,
+	},
 	ws_client::WsClientBuilder,
 	ws_server::{WsServerBuilder, WsServerHandle},
 	RpcModule,
@@ -37,6 +42,7 @@ use tokio::time::sleep;
 
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -53,6 +59,8 @@ struct CounterInner {
 	requests: (u32, u32),
 	/// Mapping method names to (number of calls, ids of successfully completed calls)
 	calls: HashMap<String, (u32, Vec<u32>)>,
+	/// Names of the deprecated methods called
+	deprecated_calls: Vec<String>,
 }
 
 impl Middleware for Counter {
@@ -79,6 +87,10 @@ impl Middleware for Counter {
 		entry.0 += 1;
 	}
 
+	fn on_deprecated_call(&self, name: &str) {
+		self.inner.lock().unwrap().deprecated_calls.push(name.into());
+	}
+
 	fn on_result(&self, name: &str, success: bool, n: u32) {
 		if success {
 			self.inner.lock().unwrap().calls.get_mut(name).unwrap().1.push(n);
@@ -195,3 +207,124 @@ async fn http_server_middleware() {
 	// HTTP server doesn't track connections
 	assert_eq!(inner.connections, (0, 0));
 }
+
+#[tokio::test]
+async fn ws_server_middleware_reports_deprecated_calls() {
+	let mut module = RpcModule::new(());
+	module.register_method("v1_greet", |_, _| Ok("hello")).unwrap();
+	module.register_method("v2_greet", |_, _| Ok("hello, world")).unwrap();
+	module.register_version("v1_greet").unwrap();
+	module.register_version("v2_greet").unwrap();
+	module.deprecate("v1_greet").unwrap();
+
+	let counter = Counter::default();
+	let (server_addr, server_handle) = websocket_server(module, counter.clone()).await.unwrap();
+
+	let server_url = format!("ws://{}", server_addr);
+	let client = WsClientBuilder::default().build(&server_url).await.unwrap();
+
+	assert_eq!(client.request::<String>("greet", None).await.unwrap(), "hello, world");
+	assert_eq!(client.request::<String>("v1_greet", None).await.unwrap(), "hello");
+	let params = std::iter::once(("version", JsonValue::from(1))).collect();
+	assert_eq!(client.request::<String>("greet", Some(ParamsSer::Map(params))).await.unwrap(), "hello");
+
+	assert_eq!(counter.inner.lock().unwrap().deprecated_calls, vec!["v1_greet", "v1_greet"]);
+
+	server_handle.stop().unwrap().await;
+}
+
+/// Client middleware that authenticates the calls, retries `flaky` and shouts the responses.
+#[derive(Clone, Default)]
+struct ClientHooks {
+	/// Names of the called methods and whether the calls succeeded.
+	results: Arc<Mutex<Vec<(String, bool)>>>,
+}
+
+impl ClientMiddleware for ClientHooks {
+	type Instant = ();
+
+	fn on_request(&self, request: &mut ClientRequest<'_>) {
+		request.headers.push(("Authorization".into(), "Bearer secret".into()));
+		if let Some(ParamsSer::Map(params)) = &mut request.params {
+			params.insert("token", "secret".into());
+		}
+	}
+
+	fn on_response(&self, _method: &str, response: &mut JsonValue) {
+		if let JsonValue::String(s) = response {
+			*s = s.to_uppercase();
+		}
+	}
+
+	fn on_result(&self, method: &str, success: bool, _: ()) {
+		self.results.lock().unwrap().push((method.into(), success));
+	}
+
+	fn retry_after(&self, requests: &[ClientRequest<'_>], _error: &Error, attempt: u32) -> Option<Duration> {
+		if requests.iter().all(|request| request.method == "flaky") && attempt < 3 {
+			Some(Duration::from_secs(0))
+		} else {
+			None
+		}
+	}
+}
+
+fn client_test_module() -> RpcModule<AtomicUsize> {
+	let mut module = RpcModule::new(AtomicUsize::new(0));
+	module
+		.register_method("whoami", |params, _| {
+			#[derive(serde::Deserialize)]
+			struct Params {
+				name: String,
+				token: String,
+			}
+			let params: Params = params.parse()?;
+			Ok(format!("{} with {}", params.name, params.token))
+		})
+		.unwrap();
+	module
+		.register_method("flaky", |_, attempts| match attempts.fetch_add(1, Ordering::SeqCst) {
+			0 | 1 => Err(Error::Call(CallError::Custom { code: -32000, message: "Try again".into(), data: None })),
+			_ => Ok("recovered"),
+		})
+		.unwrap();
+	module
+}
+
+async fn assert_client_middleware(client: impl Client + Sync, hooks: ClientHooks) {
+	let params = std::iter::once(("name", JsonValue::from("alice"))).collect();
+	assert_eq!(client.request::<String>("whoami", Some(ParamsSer::Map(params))).await.unwrap(), "ALICE WITH SECRET");
+	assert_eq!(client.request::<String>("flaky", None).await.unwrap(), "RECOVERED");
+	// Raw calls go through the middleware too, but their responses aren't decoded for `on_response`.
+	assert_eq!(client.request_raw("flaky", None).await.unwrap().get(), r#""recovered""#);
+
+	let results = hooks.results.lock().unwrap().clone();
+	let expected = [("whoami", true), ("flaky", false), ("flaky", false), ("flaky", true), ("flaky", true)];
+	assert_eq!(results, expected.iter().map(|(m, s)| (m.to_string(), *s)).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn ws_client_middleware() {
+	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let server_url = format!("ws://{}", server.local_addr().unwrap());
+	let handle = server.start(client_test_module()).unwrap();
+
+	let hooks = ClientHooks::default();
+	let client = WsClientBuilder::default().set_middleware(hooks.clone()).build(&server_url).await.unwrap();
+	assert_client_middleware(client, hooks).await;
+
+	handle.stop().unwrap().await;
+}
+
+#[tokio::test]
+async fn http_client_middleware() {
+	let server = HttpServerBuilder::default().build("127.0.0.1:0").unwrap();
+	let server_url = format!("http://{}", server.local_addr().unwrap());
+	let handle = server.start(client_test_module()).unwrap();
+
+	let hooks = ClientHooks::default();
+	let client = HttpClientBuilder::default().set_middleware(hooks.clone()).build(&server_url).unwrap();
+	assert_client_middleware(client, hooks).await;
+
+	handle.stop().unwrap().await.unwrap();
+}
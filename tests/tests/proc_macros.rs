@@ -38,10 +38,22 @@ use serde_json::json;
 mod rpc_impl {
 	use jsonrpsee::{
 		proc_macros::rpc,
-		types::{async_trait, RpcResult},
-		ws_server::SubscriptionSink,
+		types::{
+			async_trait,
+			v2::{ErrorCode, OwnedErrorObject},
+			Error, MethodError, RpcResult,
+		},
+		ws_server::{ConnectionContext, SubscriptionSink},
 	};
 
+	#[derive(Debug, serde::Serialize, serde::Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	pub struct FlatParams {
+		pub text_document: String,
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		pub version: Option<u32>,
+	}
+
 	#[rpc(client, server, namespace = "foo")]
 	pub trait Rpc {
 		#[method(name = "foo")]
@@ -66,6 +78,35 @@ mod rpc_impl {
 			Ok(format!("Called with: {}, {:?}, {:?}", a, b, c))
 		}
 
+		#[method(name = "default_params")]
+		fn default_params(&self, a: u32, #[argument(default = 10)] b: u32, c: Option<u32>) -> RpcResult<String> {
+			Ok(format!("Called with: {}, {}, {:?}", a, b, c))
+		}
+
+		#[method(name = "default_map_params", param_kind = map)]
+		fn default_map_params(
+			&self,
+			a: u32,
+			#[argument(default = Some(7))] b: Option<u32>,
+			c: Option<String>,
+		) -> RpcResult<String> {
+			Ok(format!("Called with: {}, {:?}, {:?}", a, b, c))
+		}
+
+		#[method(name = "renamed_params", param_kind = map)]
+		fn renamed_params(
+			&self,
+			#[argument(rename = "blockHash")] block_hash: String,
+			#[argument(rename = "fullTransactions")] full: Option<bool>,
+		) -> RpcResult<String> {
+			Ok(format!("Called with: {}, {:?}", block_hash, full))
+		}
+
+		#[method(name = "flattened_params")]
+		fn flattened_params(&self, #[argument(flatten)] params: FlatParams) -> RpcResult<String> {
+			Ok(format!("Called with: {}, {:?}", params.text_document, params.version))
+		}
+
 		#[method(name = "lifetimes")]
 		fn lifetimes(
 			&self,
@@ -151,8 +192,151 @@ mod rpc_impl {
 		fn call(&self, input: I) -> RpcResult<R>;
 	}
 
+	#[derive(serde::Serialize, schemars::JsonSchema)]
+	pub struct Block<Hash> {
+		pub number: u64,
+		pub hash: Hash,
+	}
+
+	/// Trait to ensure that the OpenRPC document is generated.
+	#[rpc(server, namespace = "documented", openrpc)]
+	pub trait DocumentedApi<Hash> {
+		/// Get a block.
+		///
+		/// Returns the best block when no hash is given.
+		#[method(name = "getBlock", aliases = ["documented_fetchBlock"])]
+		fn block(&self, number: u64, hash: Option<Hash>) -> RpcResult<Block<Hash>>;
+
+		#[deprecated(note = "use getBlock")]
+		#[method(name = "getBestBlock")]
+		fn best_block(&self) -> RpcResult<Block<Hash>>;
+
+		/// New blocks.
+		#[subscription(name = "subscribeBlocks" => "block", item = Block<Hash>)]
+		fn subscribe_blocks(&self) -> RpcResult<()>;
+	}
+
+	/// Trait with a method that collides with the OpenRPC discovery method.
+	#[rpc(server, openrpc)]
+	pub trait DiscoverCollision {
+		#[method(name = "rpc.discover")]
+		fn discover(&self) -> RpcResult<String>;
+	}
+
+	/// Trait to ensure that associated types are turned into type parameters.
+	#[rpc(client, server, namespace = "associated")]
+	pub trait AssociatedTypes {
+		type Hash: Clone;
+		type Number = u64;
+
+		#[method(name = "hash")]
+		fn hash(&self, number: Self::Number) -> RpcResult<Self::Hash>;
+
+		#[subscription(name = "subscribeHashes", item = Vec<Self::Hash>)]
+		fn subscribe_hashes(&self) -> RpcResult<()>;
+	}
+
+	pub const LOOKUP_NOT_FOUND_CODE: i32 = -32100;
+
+	/// Typed error of the `Fallible` API.
+	#[derive(Debug, PartialEq)]
+	pub enum LookupError {
+		NotFound(u64),
+		Other(String),
+	}
+
+	impl From<Error> for LookupError {
+		fn from(err: Error) -> Self {
+			LookupError::Other(err.to_string())
+		}
+	}
+
+	impl MethodError for LookupError {
+		fn into_error_object(self) -> OwnedErrorObject {
+			match self {
+				LookupError::NotFound(number) => OwnedErrorObject::new(LOOKUP_NOT_FOUND_CODE, "Not found")
+					.with_data(&number)
+					.expect("u64 serializes; qed"),
+				LookupError::Other(message) => OwnedErrorObject::new(ErrorCode::InternalError, message),
+			}
+		}
+
+		fn from_error_object(object: OwnedErrorObject) -> Self {
+			match (object.code.code(), object.data()) {
+				(LOOKUP_NOT_FOUND_CODE, Some(number)) => LookupError::NotFound(number),
+				_ => LookupError::Other(object.message),
+			}
+		}
+	}
+
+	/// Trait to ensure that methods can return typed errors.
+	#[rpc(client, server, namespace = "fallible")]
+	pub trait Fallible {
+		#[method(name = "lookup")]
+		fn lookup(&self, number: u64) -> Result<String, LookupError>;
+
+		#[method(name = "asyncLookup")]
+		async fn async_lookup(&self, number: u64) -> Result<String, LookupError>;
+	}
+
+	/// Trait to ensure that subscriptions can be `async` and take the connection context.
+	#[rpc(client, server, namespace = "async_sub")]
+	pub trait AsyncSubscription {
+		/// Counts up from `start`, along with the ID of the connection.
+		#[subscription(name = "count", item = (usize, u64), with_context)]
+		async fn count(&self, start: u64) -> RpcResult<()>;
+	}
+
+	/// Trait to ensure that a mock client and a stub server are generated.
+	#[rpc(client, server, mock, namespace = "mocked")]
+	pub trait Mocked {
+		#[method(name = "add")]
+		async fn add(&self, a: u8, b: String) -> RpcResult<u16>;
+
+		#[method(name = "unanswered")]
+		fn unanswered(&self) -> RpcResult<u16>;
+
+		#[method(name = "lifetimes")]
+		fn lifetimes(&self, a: &str, b: Option<beef::Cow<'_, str>>) -> RpcResult<String>;
+
+		#[method(name = "defaults")]
+		fn defaults(&self, a: u32, #[argument(default = 10)] b: u32, c: Option<u32>) -> RpcResult<String>;
+
+		#[method(name = "lookup")]
+		fn lookup(&self, number: u64) -> Result<String, LookupError>;
+
+		#[subscription(name = "echo", aliases = ["alias_echo"], item = u32)]
+		fn echo(&self, val: u32) -> RpcResult<()>;
+	}
+
+	/// Trait to ensure that the responses of methods can be cached.
+	#[rpc(server, namespace = "cached")]
+	pub trait Cached {
+		/// Number of lookups made before this one.
+		#[method(name = "lookup", aliases = ["cached_find"], cache = "60s")]
+		fn lookup(&self, number: u64) -> RpcResult<usize>;
+
+		#[method(name = "asyncLookup", cache = "100ms")]
+		async fn async_lookup(&self, number: u64) -> RpcResult<usize>;
+	}
+
 	pub struct RpcServerImpl;
 
+	/// Counts the lookups.
+	#[derive(Default)]
+	pub struct CachedImpl(pub std::sync::atomic::AtomicUsize);
+
+	#[async_trait]
+	impl CachedServer for CachedImpl {
+		fn lookup(&self, _number: u64) -> RpcResult<usize> {
+			Ok(self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst))
+		}
+
+		async fn async_lookup(&self, number: u64) -> RpcResult<usize> {
+			self.lookup(number)
+		}
+	}
+
 	#[async_trait]
 	impl RpcServer for RpcServerImpl {
 		async fn async_method(&self, _param_a: u8, _param_b: String) -> RpcResult<u16> {
@@ -189,6 +373,62 @@ mod rpc_impl {
 			sink.send(&"hello")
 		}
 	}
+
+	impl AssociatedTypesServer<String> for RpcServerImpl {
+		fn hash(&self, number: u64) -> RpcResult<String> {
+			Ok(format!("0x{:x}", number))
+		}
+
+		fn subscribe_hashes(&self, mut sink: SubscriptionSink) -> RpcResult<()> {
+			sink.send(&["0x0", "0x1"])
+		}
+	}
+
+	#[async_trait]
+	impl FallibleServer for RpcServerImpl {
+		fn lookup(&self, number: u64) -> Result<String, LookupError> {
+			match number {
+				0 => Ok("genesis".into()),
+				1 => Err(LookupError::Other("Database closed".into())),
+				n => Err(LookupError::NotFound(n)),
+			}
+		}
+
+		async fn async_lookup(&self, number: u64) -> Result<String, LookupError> {
+			self.lookup(number)
+		}
+	}
+
+	#[async_trait]
+	impl AsyncSubscriptionServer for RpcServerImpl {
+		async fn count(&self, mut sink: SubscriptionSink, context: ConnectionContext, start: u64) -> RpcResult<()> {
+			for n in start.. {
+				sink.send(&(context.connection_id(), n))?;
+				tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+			}
+			Ok(())
+		}
+	}
+
+	impl DiscoverCollisionServer for RpcServerImpl {
+		fn discover(&self) -> RpcResult<String> {
+			Ok("not a document".into())
+		}
+	}
+
+	impl DocumentedApiServer<String> for RpcServerImpl {
+		fn block(&self, number: u64, hash: Option<String>) -> RpcResult<Block<String>> {
+			Ok(Block { number, hash: hash.unwrap_or_default() })
+		}
+
+		fn best_block(&self) -> RpcResult<Block<String>> {
+			Ok(Block { number: 0, hash: String::new() })
+		}
+
+		fn subscribe_blocks(&self, mut sink: SubscriptionSink) -> RpcResult<()> {
+			sink.send(&Block { number: 0, hash: String::new() })
+		}
+	}
 }
 
 // Use generated implementations of server and client.
@@ -322,3 +562,287 @@ async fn subscriptions_do_not_work_for_http_servers() {
 	assert!(matches!(htclient.sub().await, Err(Error::HttpNotImplemented)));
 	assert_eq!(htclient.sync_method().await.unwrap(), 10);
 }
+
+#[test]
+#[should_panic(expected = "The OpenRPC document can't be served as `rpc.discover`")]
+fn macro_openrpc_discover_collision_panics() {
+	use rpc_impl::DiscoverCollisionServer;
+
+	let _ = DiscoverCollisionServer::into_rpc(RpcServerImpl);
+}
+
+#[tokio::test]
+async fn macro_openrpc_document() {
+	use rpc_impl::DocumentedApiServer;
+
+	let module = DocumentedApiServer::<String>::into_rpc(RpcServerImpl);
+	let doc: serde_json::Value = module.call("rpc.discover", jsonrpsee::types::EmptyParams::new()).await.unwrap();
+
+	assert_eq!(doc["openrpc"], "1.2.6");
+	assert_eq!(doc["info"]["title"], "DocumentedApi");
+
+	let methods = doc["methods"].as_array().unwrap();
+	let names: Vec<_> = methods.iter().map(|m| m["name"].as_str().unwrap()).collect();
+	assert_eq!(
+		names,
+		[
+			"documented_getBlock",
+			"documented_getBestBlock",
+			"documented_subscribeBlocks",
+			"documented_unsubscribeBlocks"
+		]
+	);
+
+	let block = &methods[0];
+	assert_eq!(block["description"], "Get a block.\n\nReturns the best block when no hash is given.");
+	assert_eq!(block["x-aliases"], json!(["documented_fetchBlock"]));
+	assert_eq!(
+		block["params"][0],
+		json!({ "name": "number", "required": true, "schema": { "type": "integer", "format": "uint64", "minimum": 0.0 } })
+	);
+	assert_eq!(block["params"][1]["name"], "hash");
+	assert!(block["params"][1].get("required").is_none());
+	assert_eq!(block["result"]["schema"], json!({ "$ref": "#/components/schemas/Block_for_String" }));
+	assert_eq!(doc["components"]["schemas"]["Block_for_String"]["required"], json!(["hash", "number"]));
+
+	assert_eq!(methods[1]["deprecated"], true);
+
+	let sub = &methods[2];
+	assert!(sub.get("result").is_none());
+	assert_eq!(sub["x-subscription"]["notification"], "documented_block");
+	assert_eq!(sub["x-subscription"]["unsubscribe"], "documented_unsubscribeBlocks");
+	assert_eq!(methods[3]["result"]["schema"]["type"], "boolean");
+}
+
+#[tokio::test]
+async fn macro_methods_are_described_by_introspection() {
+	use jsonrpsee::types::introspection::{MethodType, ParamDescription, ParamKind, RpcMethods, RPC_METHODS};
+	use rpc_impl::DocumentedApiServer;
+
+	let mut module = RpcServer::into_rpc(RpcServerImpl);
+	module.merge(DocumentedApiServer::<String>::into_rpc(RpcServerImpl)).unwrap();
+	module.register_introspection().unwrap();
+
+	let res: RpcMethods = module.call(RPC_METHODS, jsonrpsee::types::EmptyParams::new()).await.unwrap();
+
+	let optional_params = res.get("foo_optional_params").unwrap();
+	assert_eq!(optional_params.ty, MethodType::Method);
+	assert_eq!(optional_params.param_kind, Some(ParamKind::Array));
+	assert_eq!(
+		optional_params.params,
+		Some(vec![
+			ParamDescription::new("a", "u32", false),
+			ParamDescription::new("b", "Option<u32>", true),
+			ParamDescription::new("c", "Option<u32>", true),
+		])
+	);
+	assert!(!optional_params.deprecated);
+
+	assert!(res.get("documented_getBestBlock").unwrap().deprecated);
+	assert_eq!(res.get("documented_fetchBlock").unwrap().alias_of.as_deref(), Some("documented_getBlock"));
+
+	let echo = res.get("foo_echo").unwrap();
+	assert_eq!(echo.ty, MethodType::Subscription);
+	assert_eq!(echo.params, Some(vec![ParamDescription::new("val", "u32", false)]));
+	assert_eq!(res.get("alias_echo").unwrap().params, echo.params);
+	assert_eq!(res.get(echo.unsubscribe.as_deref().unwrap()).unwrap().ty, MethodType::Unsubscribe);
+}
+
+#[tokio::test]
+async fn macro_associated_types_become_type_params() {
+	use rpc_impl::{AssociatedTypesClient, AssociatedTypesServer};
+
+	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	server.start(AssociatedTypesServer::<String>::into_rpc(RpcServerImpl)).unwrap();
+
+	let client = WsClientBuilder::default().build(&format!("ws://{}", addr)).await.unwrap();
+
+	let hash = AssociatedTypesClient::<String>::hash(&client, 255).await.unwrap();
+	assert_eq!(hash, "0xff");
+
+	let mut sub = AssociatedTypesClient::<String>::subscribe_hashes(&client).await.unwrap();
+	assert_eq!(sub.next().await.unwrap().unwrap(), ["0x0", "0x1"]);
+}
+
+#[tokio::test]
+async fn macro_default_param_parsing() {
+	let module = RpcServer::into_rpc(RpcServerImpl);
+
+	// Params with a default value can be omitted or `null`.
+	let res: String = module.call("foo_default_params", [42_u64]).await.unwrap();
+	assert_eq!(&res, "Called with: 42, 10, None");
+	let res: String = module.call("foo_default_params", [json!(42_u64), json!(null), json!(70_u64)]).await.unwrap();
+	assert_eq!(&res, "Called with: 42, 10, Some(70)");
+	let res: String = module.call("foo_default_params", [42_u64, 3]).await.unwrap();
+	assert_eq!(&res, "Called with: 42, 3, None");
+
+	// Named params using a map
+	let (resp, _) = module
+		.raw_json_request(r#"{"jsonrpc":"2.0","method":"foo_default_params","params":{"a":22,"c":50},"id":0}"#)
+		.await
+		.unwrap();
+	assert_eq!(resp, r#"{"jsonrpc":"2.0","result":"Called with: 22, 10, Some(50)","id":0}"#);
+
+	let (resp, _) = module
+		.raw_json_request(r#"{"jsonrpc":"2.0","method":"foo_default_map_params","params":{"a":22,"b":null},"id":0}"#)
+		.await
+		.unwrap();
+	assert_eq!(resp, r#"{"jsonrpc":"2.0","result":"Called with: 22, Some(7), None","id":0}"#);
+}
+
+#[tokio::test]
+async fn macro_client_omits_optional_params() {
+	let server_addr = websocket_server().await;
+	let server_url = format!("ws://{}", server_addr);
+	let client = WsClientBuilder::default().build(&server_url).await.unwrap();
+
+	assert_eq!(client.default_params(1, 2, None).await.unwrap(), "Called with: 1, 2, None");
+	assert_eq!(client.default_params(1, 2, Some(3)).await.unwrap(), "Called with: 1, 2, Some(3)");
+	assert_eq!(client.optional_params(1, None, Some(3)).await.unwrap(), "Called with: 1, None, Some(3)");
+	assert_eq!(client.default_map_params(1, None, None).await.unwrap(), "Called with: 1, Some(7), None");
+	assert_eq!(
+		client.default_map_params(1, Some(2), Some("c".into())).await.unwrap(),
+		r#"Called with: 1, Some(2), Some("c")"#
+	);
+}
+
+#[tokio::test]
+async fn macro_renamed_and_flattened_params() {
+	let module = RpcServer::into_rpc(RpcServerImpl);
+
+	let (resp, _) = module
+		.raw_json_request(
+			r#"{"jsonrpc":"2.0","method":"foo_renamed_params","params":{"blockHash":"0x1","fullTransactions":true},"id":0}"#,
+		)
+		.await
+		.unwrap();
+	assert_eq!(resp, r#"{"jsonrpc":"2.0","result":"Called with: 0x1, Some(true)","id":0}"#);
+
+	let (resp, _) = module
+		.raw_json_request(
+			r#"{"jsonrpc":"2.0","method":"foo_flattened_params","params":{"textDocument":"a.rs"},"id":0}"#,
+		)
+		.await
+		.unwrap();
+	assert_eq!(resp, r#"{"jsonrpc":"2.0","result":"Called with: a.rs, None","id":0}"#);
+
+	let server_addr = websocket_server().await;
+	let client = WsClientBuilder::default().build(&format!("ws://{}", server_addr)).await.unwrap();
+
+	assert_eq!(client.renamed_params("0x2".into(), None).await.unwrap(), "Called with: 0x2, None");
+	let params = rpc_impl::FlatParams { text_document: "b.rs".into(), version: Some(3) };
+	assert_eq!(client.flattened_params(params).await.unwrap(), "Called with: b.rs, Some(3)");
+}
+
+#[tokio::test]
+async fn macro_typed_method_errors() {
+	use rpc_impl::{FallibleClient, FallibleServer, LookupError};
+
+	let module = FallibleServer::into_rpc(RpcServerImpl);
+
+	let (resp, _) =
+		module.raw_json_request(r#"{"jsonrpc":"2.0","method":"fallible_lookup","params":[7],"id":0}"#).await.unwrap();
+	assert_eq!(resp, r#"{"jsonrpc":"2.0","error":{"code":-32100,"message":"Not found","data":7},"id":0}"#);
+
+	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	server.start(module).unwrap();
+	let client = WsClientBuilder::default().build(&format!("ws://{}", addr)).await.unwrap();
+
+	assert_eq!(client.lookup(0).await, Ok("genesis".to_string()));
+	assert_eq!(client.lookup(7).await, Err(LookupError::NotFound(7)));
+	assert_eq!(client.async_lookup(1).await, Err(LookupError::Other("Database closed".into())));
+}
+
+#[tokio::test]
+async fn macro_async_subscription_with_context() {
+	use futures::StreamExt;
+	use rpc_impl::{AsyncSubscriptionClient, AsyncSubscriptionServer};
+
+	let server = WsServerBuilder::default().build("127.0.0.1:0").await.unwrap();
+	let addr = server.local_addr().unwrap();
+	server.start(AsyncSubscriptionServer::into_rpc(RpcServerImpl)).unwrap();
+	let client = WsClientBuilder::default().build(&format!("ws://{}", addr)).await.unwrap();
+
+	let mut sub = client.count(10).await.unwrap();
+	let (conn_id, first) = sub.next().await.unwrap().unwrap();
+	assert_eq!(first, 10);
+	assert_eq!(sub.next().await.unwrap().unwrap(), (conn_id, 11));
+	sub.unsubscribe().await.unwrap();
+
+	// The subscription is a typed stream.
+	let sub = client.count(0).await.unwrap();
+	let items: Vec<_> = sub.take(3).map(|item| item.unwrap().1).collect().await;
+	assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[tokio::test]
+async fn macro_mock_client_and_stub_server() {
+	use futures::StreamExt;
+	use jsonrpsee::types::mock::MockClient;
+	use jsonrpsee_test_utils::TimeoutFutureExt;
+	use rpc_impl::{LookupError, MockedClient, MockedClientMock, MockedServer, MockedServerStub};
+
+	// The stub server answers in-process with canned responses.
+	let module = MockedServerStub::default()
+		.on_add(|a, b| Ok(a as u16 + b.len() as u16))
+		.on_lifetimes(|a, b| Ok(format!("{} {:?}", a, b)))
+		.on_echo(|val| vec![val, val + 1])
+		.into_rpc();
+	let res: u16 = module.call("mocked_add", (1, "ab")).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(res, 3);
+	let res: String = module.call("mocked_lifetimes", ("a", "b")).with_default_timeout().await.unwrap().unwrap();
+	assert_eq!(res, r#"a Some("b")"#);
+	let res = module.call::<_, u16>("mocked_unanswered", Vec::<()>::new()).with_default_timeout().await.unwrap();
+	assert!(res.is_err());
+	let mut sub = module.subscribe("mocked_echo", (7,)).await.unwrap();
+	let (first, _) = sub.next::<u32>().with_default_timeout().await.unwrap().unwrap().unwrap();
+	let (second, _) = sub.next::<u32>().with_default_timeout().await.unwrap().unwrap().unwrap();
+	assert_eq!((first, second), (7, 8));
+
+	// The mock client answers the calls of the client trait with its expectations, in order.
+	let client = MockClient::new();
+	client
+		.expect_add(|a, b| {
+			assert_eq!((a, b.as_str()), (1, "ab"));
+			Ok(42)
+		})
+		.expect_add(|_, _| Err(Error::Custom("Unavailable".into())))
+		.expect_defaults(|a, b, c| Ok(format!("{} {} {:?}", a, b, c)))
+		.expect_echo(|val| vec![val; 2])
+		.expect_lookup(|number| Err(LookupError::NotFound(number)));
+
+	assert_eq!(client.add(1, "ab".into()).with_default_timeout().await.unwrap().unwrap(), 42);
+	// The errors of the handlers reach the caller as the error responses of a server would.
+	let err = client.add(1, "ab".into()).await.unwrap_err();
+	assert!(matches!(err, Error::Request(e) if e.contains("Unavailable")));
+	assert_eq!(client.defaults(1, 10, None).await.unwrap(), "1 10 None");
+	let items: Vec<u32> = client.echo(3).await.unwrap().map(Result::unwrap).collect().await;
+	assert_eq!(items, vec![3, 3]);
+	assert_eq!(client.lookup(9).await, Err(LookupError::NotFound(9)));
+
+	// Calls without expectations fail.
+	assert!(client.unanswered().await.is_err());
+	client.checkpoint();
+}
+
+#[tokio::test]
+async fn macro_cached_methods() {
+	use jsonrpsee::rpc_params;
+	use rpc_impl::{CachedImpl, CachedServer};
+
+	let module = CachedImpl::default().into_rpc();
+	assert_eq!(module.call::<_, usize>("cached_lookup", [1]).await.unwrap(), 0);
+	assert_eq!(module.call::<_, usize>("cached_lookup", [1]).await.unwrap(), 0);
+	assert_eq!(module.call::<_, usize>("cached_find", [1]).await.unwrap(), 0);
+	assert_eq!(module.call::<_, usize>("cached_lookup", [2]).await.unwrap(), 1);
+
+	module.response_cache("cached_lookup").unwrap().invalidate("cached_lookup", rpc_params![1]);
+	assert_eq!(module.call::<_, usize>("cached_lookup", [1]).await.unwrap(), 2);
+
+	assert_eq!(module.call::<_, usize>("cached_asyncLookup", [1]).await.unwrap(), 3);
+	assert_eq!(module.call::<_, usize>("cached_asyncLookup", [1]).await.unwrap(), 3);
+	tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+	assert_eq!(module.call::<_, usize>("cached_asyncLookup", [1]).await.unwrap(), 4);
+}
@@ -0,0 +1,208 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::v2::ParamsSer;
+use crate::Error;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Identifier of the next batch created, so that the entries of a batch can't be taken from the responses of another.
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Builder of a [batch request](https://www.jsonrpc.org/specification#batch) whose calls may have different result
+/// types, sent with [`Client::send_batch`](crate::traits::Client::send_batch).
+///
+/// Each call inserted in the batch returns a handle used to read its own result from the [`BatchResponse`], so that
+/// a call which failed doesn't fail the others.
+///
+/// ```no_run
+/// use jsonrpsee_types::{batch::BatchRequestBuilder, traits::Client, Error};
+///
+/// async fn call(client: impl Client + Sync) -> Result<(), Error> {
+///     let mut batch = BatchRequestBuilder::new();
+///     let name = batch.insert::<String>("system_name", None);
+///     let height = batch.insert::<u64>("chain_getHeight", None);
+///
+///     let mut responses = client.send_batch(batch).await?;
+///     println!("{:?} at {:?}", responses.take(name), responses.take(height));
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchRequestBuilder<'a> {
+	id: u64,
+	calls: Vec<(&'a str, Option<ParamsSer<'a>>)>,
+}
+
+impl<'a> Default for BatchRequestBuilder<'a> {
+	fn default() -> Self {
+		Self { id: NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed), calls: Vec::new() }
+	}
+}
+
+impl<'a> BatchRequestBuilder<'a> {
+	/// Create an empty batch.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Insert a call to `method` with `params` in the batch, returns the handle of its result of type `T`.
+	pub fn insert<T: DeserializeOwned>(&mut self, method: &'a str, params: Option<ParamsSer<'a>>) -> BatchEntry<T> {
+		self.calls.push((method, params));
+		BatchEntry { batch_id: self.id, index: self.calls.len() - 1, _marker: PhantomData }
+	}
+
+	/// Identifier of the batch, shared by its clones, to create its [`BatchResponse`].
+	pub fn id(&self) -> u64 {
+		self.id
+	}
+
+	/// Number of calls in the batch.
+	pub fn len(&self) -> usize {
+		self.calls.len()
+	}
+
+	/// Whether the batch has no call.
+	pub fn is_empty(&self) -> bool {
+		self.calls.is_empty()
+	}
+
+	/// Calls of the batch, in the order they were inserted.
+	pub fn into_calls(self) -> Vec<(&'a str, Option<ParamsSer<'a>>)> {
+		self.calls
+	}
+}
+
+/// Handle of the result of type `T` of a call inserted in a [`BatchRequestBuilder`].
+pub struct BatchEntry<T> {
+	batch_id: u64,
+	index: usize,
+	_marker: PhantomData<fn() -> T>,
+}
+
+impl<T> BatchEntry<T> {
+	/// Position of the call in the batch.
+	pub fn index(&self) -> usize {
+		self.index
+	}
+}
+
+impl<T> fmt::Debug for BatchEntry<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("BatchEntry").field("batch_id", &self.batch_id).field("index", &self.index).finish()
+	}
+}
+
+/// Responses to the calls of a batch sent with [`Client::send_batch`](crate::traits::Client::send_batch), in the
+/// order they were inserted.
+#[derive(Debug)]
+pub struct BatchResponse {
+	batch_id: u64,
+	results: Vec<Option<Result<JsonValue, Error>>>,
+}
+
+impl BatchResponse {
+	/// Create the responses from the results of the calls of the batch identified by `batch_id`, see
+	/// [`BatchRequestBuilder::id`], in the order they were inserted.
+	pub fn new(batch_id: u64, results: Vec<Result<JsonValue, Error>>) -> Self {
+		Self { batch_id, results: results.into_iter().map(Some).collect() }
+	}
+
+	/// Take the result of the call of `entry`, decoded as `T`.
+	///
+	/// Fails with [`Error::InvalidRequestId`] if the entry doesn't belong to the batch, and with the error the call
+	/// failed with, usually an [`Error::Request`] with its JSON-RPC error object, otherwise.
+	pub fn take<T: DeserializeOwned>(&mut self, entry: BatchEntry<T>) -> Result<T, Error> {
+		if entry.batch_id != self.batch_id {
+			return Err(Error::InvalidRequestId);
+		}
+		let result = self.results.get_mut(entry.index).and_then(Option::take).ok_or(Error::InvalidRequestId)?;
+		serde_json::from_value(result?).map_err(Error::ParseError)
+	}
+
+	/// Number of calls in the batch.
+	pub fn len(&self) -> usize {
+		self.results.len()
+	}
+
+	/// Whether the batch has no call.
+	pub fn is_empty(&self) -> bool {
+		self.results.is_empty()
+	}
+
+	/// Number of calls of the batch which failed, whose result wasn't taken yet.
+	pub fn num_failed(&self) -> usize {
+		self.results.iter().filter(|result| matches!(result, Some(Err(_)))).count()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn take_typed_results() {
+		let mut batch = BatchRequestBuilder::new();
+		let hello = batch.insert::<String>("say_hello", None);
+		let missing = batch.insert::<u64>("missing", None);
+		let sum = batch.insert::<u64>("add", None);
+		assert_eq!(batch.len(), 3);
+
+		let mut responses = BatchResponse::new(
+			batch.id(),
+			vec![
+				Ok("hello".into()),
+				Err(Error::Request(
+					r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#.into(),
+				)),
+				Ok(3.into()),
+			],
+		);
+		assert_eq!(responses.num_failed(), 1);
+		assert_eq!(responses.take(sum).unwrap(), 3);
+		assert_eq!(responses.take(hello).unwrap(), "hello");
+		assert!(matches!(responses.take(missing), Err(Error::Request(_))));
+		assert_eq!(responses.num_failed(), 0);
+	}
+
+	#[test]
+	fn take_entry_of_other_batch_fails() {
+		let mut batch = BatchRequestBuilder::new();
+		let hello = batch.insert::<String>("say_hello", None);
+		let out_of_range = batch.insert::<String>("say_hello", None);
+
+		let mut other = BatchRequestBuilder::new();
+		let in_range = other.insert::<String>("say_hello", None);
+
+		let mut responses = BatchResponse::new(batch.id(), vec![Ok("hello".into())]);
+		assert!(matches!(responses.take(in_range), Err(Error::InvalidRequestId)));
+		assert!(matches!(responses.take(out_of_range), Err(Error::InvalidRequestId)));
+		assert_eq!(responses.take(hello).unwrap(), "hello");
+	}
+}
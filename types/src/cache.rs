@@ -0,0 +1,514 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::batch::{BatchRequestBuilder, BatchResponse};
+use crate::traits::{Client, SubscriptionClient};
+use crate::v2::ParamsSer;
+use crate::{Error, Subscription};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long and how many responses of a method are cached by a [`ResponseCache`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+	ttl: Duration,
+	max_entries: usize,
+	max_bytes: usize,
+}
+
+impl CachePolicy {
+	/// Create a policy caching the responses for `ttl`, up to 1024 responses and 10 megabytes.
+	pub fn new(ttl: Duration) -> Self {
+		Self { ttl, max_entries: 1024, max_bytes: 10 * 1024 * 1024 }
+	}
+
+	/// Set the maximum number of cached responses of the method, the oldest ones are evicted first.
+	pub fn max_entries(mut self, max_entries: usize) -> Self {
+		self.max_entries = max_entries;
+		self
+	}
+
+	/// Set the maximum size of the cached responses of the method in bytes, counting their params and results.
+	/// A response larger than it isn't cached.
+	pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+		self.max_bytes = max_bytes;
+		self
+	}
+
+	/// How long a response is cached.
+	pub fn ttl(&self) -> Duration {
+		self.ttl
+	}
+}
+
+/// Cache of the successful responses of the methods which opted in with [`ResponseCache::cache_method`], keyed by
+/// the method name and the canonical form of its params, which doesn't depend on whitespace nor on the order of
+/// the fields of objects.
+///
+/// The cache is shared by its clones. It is used on the server with
+/// `Methods::cache_method`, and on the client with [`CachedClient`]. It only suits methods whose result depends on
+/// their params only, such as lookups by hash, or which are invalidated explicitly when it changes.
+///
+/// ```
+/// use jsonrpsee_types::{cache::{CachePolicy, ResponseCache}, v2::ParamsSer};
+/// use std::time::Duration;
+///
+/// let cache = ResponseCache::new();
+/// cache.cache_method("chain_getBlock", CachePolicy::new(Duration::from_secs(60)).max_entries(100));
+///
+/// // Drop the cached response to one call after the block was reverted, or all of them.
+/// let params = ParamsSer::Array(vec!["0x1234".into()]);
+/// cache.invalidate("chain_getBlock", Some(params));
+/// cache.invalidate_method("chain_getBlock");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ResponseCache {
+	methods: Arc<Mutex<HashMap<String, MethodCache>>>,
+}
+
+impl ResponseCache {
+	/// Create a cache with no method cached.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Cache the responses of `method` following `policy`, replacing its previous policy and responses if any.
+	pub fn cache_method(&self, method: impl Into<String>, policy: CachePolicy) {
+		self.lock().insert(method.into(), MethodCache::new(policy));
+	}
+
+	/// Whether the responses of `method` are cached.
+	pub fn is_cached(&self, method: &str) -> bool {
+		self.lock().contains_key(method)
+	}
+
+	/// Get the cached result of the call to `method` with `params`, if it didn't expire.
+	pub fn get(&self, method: &str, params: Option<ParamsSer>) -> Option<Arc<RawValue>> {
+		let mut methods = self.lock();
+		let cache = methods.get_mut(method)?;
+		let key = canonical_params(params)?;
+		cache.get(&key)
+	}
+
+	/// Cache the `result` of the call to `method` with `params`, if its responses are cached.
+	pub fn insert(&self, method: &str, params: Option<ParamsSer>, result: &RawValue) {
+		let mut methods = self.lock();
+		if let Some(cache) = methods.get_mut(method) {
+			if let Some(key) = canonical_params(params) {
+				cache.insert(key, result.to_owned().into());
+			}
+		}
+	}
+
+	/// Drop the cached result of the call to `method` with `params`.
+	pub fn invalidate(&self, method: &str, params: Option<ParamsSer>) {
+		let mut methods = self.lock();
+		if let (Some(cache), Some(key)) = (methods.get_mut(method), canonical_params(params)) {
+			cache.remove(&key);
+		}
+	}
+
+	/// Drop the cached results of all calls to `method`.
+	pub fn invalidate_method(&self, method: &str) {
+		if let Some(cache) = self.lock().get_mut(method) {
+			cache.clear();
+		}
+	}
+
+	/// Drop all cached results.
+	pub fn clear(&self) {
+		self.lock().values_mut().for_each(MethodCache::clear);
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, MethodCache>> {
+		self.methods.lock().expect("cache lock is never poisoned; qed")
+	}
+}
+
+/// Canonical form of the `params` keying the responses of the [`ResponseCache`], which doesn't depend on whitespace
+/// nor on the order of the fields of objects. `None` if they can't be serialized.
+pub fn canonical_params(params: Option<ParamsSer>) -> Option<String> {
+	let params = match params {
+		Some(params) => serde_json::to_value(params).ok()?,
+		None => JsonValue::Null,
+	};
+	let mut canonical = String::new();
+	write_canonical(&params, &mut canonical);
+	Some(canonical)
+}
+
+/// Write the compact form of `value` with the fields of its objects sorted, which `JsonValue` doesn't do when the
+/// `preserve_order` feature of `serde_json` is enabled.
+fn write_canonical(value: &JsonValue, out: &mut String) {
+	match value {
+		JsonValue::Object(fields) => {
+			let mut fields: Vec<_> = fields.iter().collect();
+			fields.sort_unstable_by_key(|(name, _)| *name);
+			out.push('{');
+			for (i, (name, value)) in fields.into_iter().enumerate() {
+				if i > 0 {
+					out.push(',');
+				}
+				let _ = write!(out, "{}:", JsonValue::from(name.as_str()));
+				write_canonical(value, out);
+			}
+			out.push('}');
+		}
+		JsonValue::Array(values) => {
+			out.push('[');
+			for (i, value) in values.iter().enumerate() {
+				if i > 0 {
+					out.push(',');
+				}
+				write_canonical(value, out);
+			}
+			out.push(']');
+		}
+		value => {
+			let _ = write!(out, "{}", value);
+		}
+	}
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+	result: Arc<RawValue>,
+	inserted_at: Instant,
+	/// Insertion sequence number, to tell whether the entry is the one of the key at the front of the queue.
+	seq: u64,
+}
+
+#[derive(Debug)]
+struct MethodCache {
+	policy: CachePolicy,
+	entries: HashMap<String, CacheEntry>,
+	/// Keys in insertion order, with their sequence number, the entries replaced since are skipped.
+	order: VecDeque<(u64, String)>,
+	next_seq: u64,
+	bytes: usize,
+}
+
+impl MethodCache {
+	fn new(policy: CachePolicy) -> Self {
+		Self { policy, entries: HashMap::new(), order: VecDeque::new(), next_seq: 0, bytes: 0 }
+	}
+
+	fn get(&mut self, key: &str) -> Option<Arc<RawValue>> {
+		let entry = self.entries.get(key)?;
+		if entry.inserted_at.elapsed() < self.policy.ttl {
+			Some(entry.result.clone())
+		} else {
+			self.remove(key);
+			None
+		}
+	}
+
+	fn insert(&mut self, key: String, result: Arc<RawValue>) {
+		let size = key.len() + result.get().len();
+		if size > self.policy.max_bytes || self.policy.max_entries == 0 {
+			return;
+		}
+		self.remove(&key);
+		while self.entries.len() >= self.policy.max_entries || self.bytes + size > self.policy.max_bytes {
+			if !self.evict_oldest() {
+				break;
+			}
+		}
+
+		let seq = self.next_seq;
+		self.next_seq += 1;
+		self.bytes += size;
+		self.order.push_back((seq, key.clone()));
+		self.entries.insert(key, CacheEntry { result, inserted_at: Instant::now(), seq });
+
+		// Drop the keys of the replaced entries once they outnumber the live ones.
+		if self.order.len() > 2 * self.entries.len() + 16 {
+			let entries = &self.entries;
+			self.order.retain(|(seq, key)| matches!(entries.get(key), Some(entry) if entry.seq == *seq));
+		}
+	}
+
+	fn evict_oldest(&mut self) -> bool {
+		while let Some((seq, key)) = self.order.pop_front() {
+			if matches!(self.entries.get(&key), Some(entry) if entry.seq == seq) {
+				self.remove(&key);
+				return true;
+			}
+		}
+		false
+	}
+
+	fn remove(&mut self, key: &str) {
+		if let Some(entry) = self.entries.remove(key) {
+			self.bytes -= key.len() + entry.result.get().len();
+		}
+		if self.entries.is_empty() {
+			self.order.clear();
+		}
+	}
+
+	fn clear(&mut self) {
+		self.entries.clear();
+		self.order.clear();
+		self.bytes = 0;
+	}
+}
+
+/// Client caching the responses to the requests of the methods cached by its [`ResponseCache`], which are only
+/// sent to the wrapped client when no cached response is found. Notifications, batches and subscriptions are
+/// always sent.
+///
+/// ```no_run
+/// use jsonrpsee_types::{cache::{CachePolicy, CachedClient, ResponseCache}, traits::Client, Error};
+/// use std::time::Duration;
+///
+/// async fn block(client: impl Client + Send + Sync) -> Result<String, Error> {
+///     let cache = ResponseCache::new();
+///     cache.cache_method("chain_getBlockHash", CachePolicy::new(Duration::from_secs(60)));
+///
+///     let client = CachedClient::new(client, cache);
+///     client.request("chain_getBlockHash", None).await
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachedClient<C> {
+	client: C,
+	cache: ResponseCache,
+}
+
+impl<C> CachedClient<C> {
+	/// Wrap the `client`, caching the responses to the methods cached by `cache`.
+	pub fn new(client: C, cache: ResponseCache) -> Self {
+		Self { client, cache }
+	}
+
+	/// The cache of the responses, to invalidate them.
+	pub fn cache(&self) -> &ResponseCache {
+		&self.cache
+	}
+
+	/// The wrapped client.
+	pub fn inner(&self) -> &C {
+		&self.client
+	}
+}
+
+#[async_trait]
+impl<C> Client for CachedClient<C>
+where
+	C: Client + Send + Sync,
+{
+	async fn notification<'a>(&self, method: &'a str, params: Option<ParamsSer<'a>>) -> Result<(), Error> {
+		self.client.notification(method, params).await
+	}
+
+	async fn request<'a, R>(&self, method: &'a str, params: Option<ParamsSer<'a>>) -> Result<R, Error>
+	where
+		R: DeserializeOwned,
+	{
+		if !self.cache.is_cached(method) {
+			return self.client.request(method, params).await;
+		}
+		if let Some(result) = self.cache.get(method, params.clone()) {
+			return serde_json::from_str(result.get()).map_err(Error::ParseError);
+		}
+
+		let result: JsonValue = self.client.request(method, params.clone()).await?;
+		let raw = serde_json::value::to_raw_value(&result).map_err(Error::ParseError)?;
+		self.cache.insert(method, params, &raw);
+		serde_json::from_value(result).map_err(Error::ParseError)
+	}
+
+	async fn batch_request<'a, R>(&self, batch: Vec<(&'a str, Option<ParamsSer<'a>>)>) -> Result<Vec<R>, Error>
+	where
+		R: DeserializeOwned + Default + Clone,
+	{
+		self.client.batch_request(batch).await
+	}
+
+	async fn send_batch<'a>(&self, batch: BatchRequestBuilder<'a>) -> Result<BatchResponse, Error> {
+		self.client.send_batch(batch).await
+	}
+
+	async fn request_raw<'a>(&self, method: &'a str, params: Option<Box<RawValue>>) -> Result<Box<RawValue>, Error> {
+		if !self.cache.is_cached(method) {
+			return self.client.request_raw(method, params).await;
+		}
+		if let Some(result) = self.cache.get(method, params.as_deref().map(ParamsSer::Raw)) {
+			return Ok((*result).to_owned());
+		}
+
+		let result = self.client.request_raw(method, params.clone()).await?;
+		self.cache.insert(method, params.as_deref().map(ParamsSer::Raw), &result);
+		Ok(result)
+	}
+}
+
+#[async_trait]
+impl<C> SubscriptionClient for CachedClient<C>
+where
+	C: SubscriptionClient + Send + Sync,
+{
+	async fn subscribe<'a, Notif>(
+		&self,
+		subscribe_method: &'a str,
+		params: Option<ParamsSer<'a>>,
+		unsubscribe_method: &'a str,
+	) -> Result<Subscription<Notif>, Error>
+	where
+		Notif: DeserializeOwned,
+	{
+		self.client.subscribe(subscribe_method, params, unsubscribe_method).await
+	}
+
+	async fn subscribe_to_method<'a, Notif>(&self, method: &'a str) -> Result<Subscription<Notif>, Error>
+	where
+		Notif: DeserializeOwned,
+	{
+		self.client.subscribe_to_method(method).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::MockClient;
+	use futures_util::future::FutureExt;
+
+	fn raw(json: &str) -> Box<RawValue> {
+		RawValue::from_string(json.into()).unwrap()
+	}
+
+	fn params(raw: &RawValue) -> Option<ParamsSer<'_>> {
+		Some(ParamsSer::Raw(raw))
+	}
+
+	#[test]
+	fn keys_are_canonical() {
+		let cache = ResponseCache::new();
+		cache.cache_method("get", CachePolicy::new(Duration::from_secs(60)));
+		cache.insert("get", params(&raw(r#"{"b": 1, "a": [1, 2]}"#)), &raw("true"));
+		cache.insert("other", params(&raw("[1]")), &raw("true"));
+
+		assert_eq!(cache.get("get", params(&raw(r#"{"a":[1,2],"b":1}"#))).unwrap().get(), "true");
+		assert!(cache.get("get", params(&raw(r#"{"a":[2,1],"b":1}"#))).is_none());
+		assert!(cache.get("get", None).is_none());
+		assert!(cache.get("other", params(&raw("[1]"))).is_none());
+
+		cache.insert("get", None, &raw("false"));
+		assert_eq!(cache.get("get", None).unwrap().get(), "false");
+		cache.invalidate("get", params(&raw(r#"{"a":[1,2],"b":1}"#)));
+		assert!(cache.get("get", params(&raw(r#"{"a":[1,2],"b":1}"#))).is_none());
+		cache.invalidate_method("get");
+		assert!(cache.get("get", None).is_none());
+	}
+
+	#[test]
+	fn canonical_params_sort_nested_fields() {
+		let params = raw(r#"{"z": {"b": [{"d": 1, "c": 2}], "a": "\"q\""}, "y": null}"#);
+		let canonical = canonical_params(Some(ParamsSer::Raw(&params))).unwrap();
+		assert_eq!(canonical, r#"{"y":null,"z":{"a":"\"q\"","b":[{"c":2,"d":1}]}}"#);
+		assert_eq!(canonical_params(None).unwrap(), "null");
+	}
+
+	#[test]
+	fn entries_expire() {
+		let cache = ResponseCache::new();
+		cache.cache_method("get", CachePolicy::new(Duration::from_millis(20)));
+		cache.insert("get", params(&raw("[1]")), &raw("1"));
+		assert!(cache.get("get", params(&raw("[1]"))).is_some());
+		std::thread::sleep(Duration::from_millis(30));
+		assert!(cache.get("get", params(&raw("[1]"))).is_none());
+	}
+
+	#[test]
+	fn oldest_entries_are_evicted() {
+		let cache = ResponseCache::new();
+		cache.cache_method("get", CachePolicy::new(Duration::from_secs(60)).max_entries(2));
+		for i in 0..3 {
+			cache.insert("get", params(&raw(&format!("[{}]", i))), &raw("1"));
+		}
+		assert!(cache.get("get", params(&raw("[0]"))).is_none());
+		assert!(cache.get("get", params(&raw("[1]"))).is_some());
+		assert!(cache.get("get", params(&raw("[2]"))).is_some());
+
+		// Each entry takes 3 bytes of params and 3 of result.
+		cache.cache_method("get", CachePolicy::new(Duration::from_secs(60)).max_bytes(12));
+		cache.insert("get", params(&raw("[1]")), &raw("123"));
+		cache.insert("get", params(&raw("[2]")), &raw("123"));
+		cache.insert("get", params(&raw("[1]")), &raw("456"));
+		cache.insert("get", params(&raw("[3]")), &raw("123"));
+		assert!(cache.get("get", params(&raw("[2]"))).is_none());
+		assert_eq!(cache.get("get", params(&raw("[1]"))).unwrap().get(), "456");
+		assert!(cache.get("get", params(&raw("[3]"))).is_some());
+		cache.insert("get", params(&raw("[4]")), &raw("1234567890"));
+		assert!(cache.get("get", params(&raw("[4]"))).is_none());
+	}
+
+	#[test]
+	fn client_caches_opted_in_methods() {
+		let mock = MockClient::new();
+		mock.expect("get", |_| Ok("cached".into()));
+		mock.expect("uncached", |_| Ok("first".into()));
+		mock.expect("uncached", |_| Ok("second".into()));
+		let cache = ResponseCache::new();
+		cache.cache_method("get", CachePolicy::new(Duration::from_secs(60)));
+		let client = CachedClient::new(mock, cache);
+
+		let request = |method: &str| client.request::<String>(method, None).now_or_never().unwrap().unwrap();
+		assert_eq!(request("get"), "cached");
+		assert_eq!(request("get"), "cached");
+		assert_eq!(request("uncached"), "first");
+		assert_eq!(request("uncached"), "second");
+
+		client.cache().invalidate("get", None);
+		client.inner().expect("get", |_| Ok("refreshed".into()));
+		assert_eq!(request("get"), "refreshed");
+	}
+
+	#[test]
+	fn client_caches_raw_requests() {
+		let mock = MockClient::new();
+		mock.expect("get", |_| Ok("cached".into()));
+		let cache = ResponseCache::new();
+		cache.cache_method("get", CachePolicy::new(Duration::from_secs(60)));
+		let client = CachedClient::new(mock, cache);
+
+		let request_raw = |params: &str| client.request_raw("get", Some(raw(params))).now_or_never().unwrap().unwrap();
+		assert_eq!(request_raw("[1, 2]").get(), r#""cached""#);
+		assert_eq!(request_raw("[1,2]").get(), r#""cached""#);
+
+		// Raw and typed requests share the cached responses.
+		let params = Some(ParamsSer::Array(vec![1.into(), 2.into()]));
+		assert_eq!(client.request::<String>("get", params).now_or_never().unwrap().unwrap(), "cached");
+	}
+}
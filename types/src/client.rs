@@ -63,15 +63,16 @@ pub enum NotifResponse<Notif> {
 /// Active subscription on the client.
 ///
 /// It will automatically unsubscribe in the [`Subscription::drop`] so no need to explicitly call
-/// the `unsubscribe method` if it is an an subscription based on [`SubscriptionId`].
+/// the `unsubscribe method` if it is an an subscription based on [`SubscriptionId`]. Use
+/// [`Subscription::unsubscribe`] to wait for the server to confirm that the subscription was closed.
 #[derive(Debug)]
 pub struct Subscription<Notif> {
 	/// Channel to send requests to the background task.
 	to_back: mpsc::Sender<FrontToBack>,
 	/// Channel from which we receive notifications from the server, as encoded `JsonValue`s.
 	notifs_rx: mpsc::Receiver<JsonValue>,
-	/// Callback kind.
-	kind: SubscriptionKind,
+	/// Callback kind, `None` once unsubscribed.
+	kind: Option<SubscriptionKind>,
 	/// Marker in order to pin the `Notif` parameter.
 	marker: PhantomData<Notif>,
 }
@@ -87,7 +88,30 @@ impl<Notif> Subscription<Notif> {
 		notifs_rx: mpsc::Receiver<JsonValue>,
 		kind: SubscriptionKind,
 	) -> Self {
-		Self { to_back, notifs_rx, kind, marker: PhantomData }
+		Self { to_back, notifs_rx, kind: Some(kind), marker: PhantomData }
+	}
+
+	/// Unsubscribe and wait for the response of the server.
+	///
+	/// Returns the error of the server if it failed to close the subscription, e.g. because it was already closed,
+	/// and [`Error::RequestTimeout`] if the server didn't respond within the request timeout of the client.
+	/// Subscriptions to a method only unregister their notification handler.
+	pub async fn unsubscribe(mut self) -> Result<(), Error> {
+		let sub_id = match self.kind.take() {
+			Some(SubscriptionKind::Subscription(sub_id)) => sub_id,
+			Some(SubscriptionKind::Method(notif)) => {
+				return self.to_back.send(FrontToBack::UnregisterNotification(notif)).await.map_err(Into::into);
+			}
+			None => return Ok(()),
+		};
+
+		let (send_back_tx, send_back_rx) = oneshot::channel();
+		self.to_back.send(FrontToBack::Unsubscribe(UnsubscribeMessage { sub_id, send_back: send_back_tx })).await?;
+
+		match send_back_rx.await {
+			Ok(res) => res.map(|_| ()),
+			Err(_) => Err(Error::RestartNeeded("unsubscribe response not received".into())),
+		}
 	}
 }
 
@@ -98,8 +122,8 @@ pub struct BatchMessage {
 	pub raw: String,
 	/// Request IDs.
 	pub ids: Vec<u64>,
-	/// One-shot channel over which we send back the result of this request.
-	pub send_back: oneshot::Sender<Result<Vec<JsonValue>, Error>>,
+	/// One-shot channel over which we send back the result of each request of the batch.
+	pub send_back: oneshot::Sender<Result<Vec<Result<JsonValue, Error>>, Error>>,
 }
 
 /// Request message.
@@ -130,6 +154,15 @@ pub struct SubscriptionMessage {
 	pub send_back: oneshot::Sender<Result<(mpsc::Receiver<JsonValue>, SubscriptionId<'static>), Error>>,
 }
 
+/// Unsubscribe message.
+#[derive(Debug)]
+pub struct UnsubscribeMessage {
+	/// ID of the subscription to close.
+	pub sub_id: SubscriptionId<'static>,
+	/// One-shot channel over which we send back the response of the server to the unsubscribe call.
+	pub send_back: oneshot::Sender<Result<JsonValue, Error>>,
+}
+
 /// RegisterNotification message.
 #[derive(Debug)]
 pub struct RegisterNotificationMessage {
@@ -162,6 +195,8 @@ pub enum FrontToBack {
 	// Such operations will be blocked until a response is received or the background
 	// thread has been terminated.
 	SubscriptionClosed(SubscriptionId<'static>),
+	/// User called `unsubscribe` on a subscription and waits for the response of the server.
+	Unsubscribe(UnsubscribeMessage),
 }
 
 impl<Notif> Subscription<Notif>
@@ -203,11 +238,10 @@ impl<Notif> Drop for Subscription<Notif> {
 		// the channel's buffer will be full.
 		// However, when a notification arrives, the background task will realize that the channel
 		// to the `Callback` has been closed.
-		let kind = std::mem::replace(&mut self.kind, SubscriptionKind::Subscription(SubscriptionId::Num(0)));
-
-		let msg = match kind {
-			SubscriptionKind::Method(notif) => FrontToBack::UnregisterNotification(notif),
-			SubscriptionKind::Subscription(sub_id) => FrontToBack::SubscriptionClosed(sub_id),
+		let msg = match self.kind.take() {
+			Some(SubscriptionKind::Method(notif)) => FrontToBack::UnregisterNotification(notif),
+			Some(SubscriptionKind::Subscription(sub_id)) => FrontToBack::SubscriptionClosed(sub_id),
+			None => return,
 		};
 		let _ = self.to_back.send(msg).now_or_never();
 	}
@@ -293,14 +327,156 @@ mod tests {
 
 		assert!(manager.next_request_id().is_ok());
 	}
+
+	#[cfg(feature = "tls")]
+	#[test]
+	fn custom_cert_stores_are_compared() {
+		use super::{CertificateStore, CustomCertStore};
+		use std::sync::Arc;
+
+		let config = Arc::new(
+			rustls::ClientConfig::builder()
+				.with_safe_defaults()
+				.with_root_certificates(rustls::RootCertStore::empty())
+				.with_no_client_auth(),
+		);
+		let custom = CertificateStore::Custom(CustomCertStore::Config(config.clone()));
+		assert_eq!(custom, CertificateStore::Custom(CustomCertStore::Config(config.clone())));
+		assert_ne!(custom, CertificateStore::Custom(CustomCertStore::Config(Arc::new((*config).clone()))));
+
+		let pem = CustomCertStore::from_pem("roots");
+		assert_eq!(pem, CustomCertStore::from_pem("roots"));
+		assert_ne!(pem.clone(), pem.with_client_auth("cert", "key"));
+		assert_ne!(custom, CertificateStore::Native);
+	}
 }
 
 /// What certificate store to use
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub enum CertificateStore {
 	/// Use the native system certificate store
 	Native,
 	/// Use WebPKI's certificate store
 	WebPki,
+	/// Use custom root certificates, and a client certificate if the server requires one
+	#[cfg(feature = "tls")]
+	Custom(CustomCertStore),
+}
+
+/// Custom TLS configuration of [`CertificateStore::Custom`].
+#[cfg(feature = "tls")]
+#[derive(Clone)]
+pub enum CustomCertStore {
+	/// Use this `rustls` client configuration as is.
+	Config(Arc<rustls::ClientConfig>),
+	/// Trust the PEM encoded root certificates, i.e. a private CA.
+	Pem {
+		/// PEM encoded root certificates.
+		roots: Vec<u8>,
+		/// Client certificate to authenticate with.
+		client_auth: Option<ClientAuth>,
+	},
+}
+
+/// PEM encoded client certificate and private key, to authenticate to servers requiring mutual TLS.
+#[cfg(feature = "tls")]
+#[derive(Clone, PartialEq)]
+pub struct ClientAuth {
+	/// PEM encoded certificate chain, starting with the certificate of the client.
+	pub cert_chain: Vec<u8>,
+	/// PEM encoded private key of the client certificate, either PKCS#8, PKCS#1 RSA or SEC1 EC.
+	pub private_key: Vec<u8>,
+}
+
+#[cfg(feature = "tls")]
+impl std::fmt::Debug for CustomCertStore {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Config(_) => f.write_str("Config"),
+			Self::Pem { client_auth, .. } => {
+				f.debug_struct("Pem").field("client_auth", &client_auth.is_some()).finish_non_exhaustive()
+			}
+		}
+	}
+}
+
+#[cfg(feature = "tls")]
+impl PartialEq for CustomCertStore {
+	// `rustls::ClientConfig` can't be compared, the configurations are equal if they are the same.
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Self::Config(config), Self::Config(other)) => Arc::ptr_eq(config, other),
+			(Self::Pem { roots, client_auth }, Self::Pem { roots: other_roots, client_auth: other_client_auth }) => {
+				roots == other_roots && client_auth == other_client_auth
+			}
+			_ => false,
+		}
+	}
+}
+
+#[cfg(feature = "tls")]
+impl std::fmt::Debug for ClientAuth {
+	// The private key is not printed.
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ClientAuth").finish_non_exhaustive()
+	}
+}
+
+#[cfg(feature = "tls")]
+impl CustomCertStore {
+	/// Trust the PEM encoded root certificates `roots`.
+	pub fn from_pem(roots: impl Into<Vec<u8>>) -> Self {
+		Self::Pem { roots: roots.into(), client_auth: None }
+	}
+
+	/// Authenticate with the PEM encoded client certificate chain and private key.
+	///
+	/// The configuration of [`CustomCertStore::Config`] is used as is, so this is ignored for it.
+	pub fn with_client_auth(self, cert_chain: impl Into<Vec<u8>>, private_key: impl Into<Vec<u8>>) -> Self {
+		match self {
+			Self::Pem { roots, .. } => Self::Pem {
+				roots,
+				client_auth: Some(ClientAuth { cert_chain: cert_chain.into(), private_key: private_key.into() }),
+			},
+			config => config,
+		}
+	}
+
+	/// Build the `rustls` client configuration, failing if the PEM files contain no valid root certificate or
+	/// client key.
+	pub fn client_config(&self) -> std::io::Result<Arc<rustls::ClientConfig>> {
+		use std::io::{Error as IoError, ErrorKind};
+
+		let (roots, client_auth) = match self {
+			Self::Config(config) => return Ok(config.clone()),
+			Self::Pem { roots, client_auth } => (roots, client_auth),
+		};
+
+		let mut root_store = rustls::RootCertStore::empty();
+		let (added, _) = root_store.add_parsable_certificates(&rustls_pemfile::certs(&mut roots.as_slice())?);
+		if added == 0 {
+			return Err(IoError::new(ErrorKind::InvalidData, "No valid root certificate found"));
+		}
+		let builder = rustls::ClientConfig::builder().with_safe_defaults().with_root_certificates(root_store);
+
+		let config = match client_auth {
+			None => builder.with_no_client_auth(),
+			Some(ClientAuth { cert_chain, private_key }) => {
+				let cert_chain: Vec<_> =
+					rustls_pemfile::certs(&mut cert_chain.as_slice())?.into_iter().map(rustls::Certificate).collect();
+				let key = rustls_pemfile::read_all(&mut private_key.as_slice())?
+					.into_iter()
+					.find_map(|item| match item {
+						rustls_pemfile::Item::PKCS8Key(key)
+						| rustls_pemfile::Item::RSAKey(key)
+						| rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+						_ => None,
+					})
+					.ok_or_else(|| IoError::new(ErrorKind::InvalidData, "No private key found"))?;
+				builder.with_single_cert(cert_chain, key).map_err(|e| IoError::new(ErrorKind::InvalidData, e))?
+			}
+		};
+		Ok(Arc::new(config))
+	}
 }
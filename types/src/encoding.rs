@@ -0,0 +1,707 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Alternative encodings of the JSON-RPC envelope for WebSocket connections.
+//!
+//! The encoding is negotiated during the handshake through the WebSocket subprotocol. When a binary
+//! encoding is selected, messages are carried in binary frames and have the same structure as their JSON
+//! counterparts, i.e. `Request`, `Response` and `SubscriptionResponse` are encoded as CBOR or MessagePack maps.
+//! Text frames are always treated as JSON.
+//!
+//! Servers serialize and deserialize messages in the encoding directly, only the parts of the messages kept as
+//! raw JSON, such as the params of requests or the data of errors, are transcoded from and to JSON. Clients
+//! handle messages as JSON text, which is transcoded as it is parsed with [`WsEncoding::encode`] and
+//! [`WsEncoding::decode`]. Binary strings, which have no JSON equivalent, are not supported.
+
+use crate::v2::{ErrorCode, Id, Request, TwoPointZero};
+use serde::de::{self, DeserializeOwned, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use std::io;
+
+/// Encoding of the messages exchanged over a WebSocket connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsEncoding {
+	/// JSON in text frames, the default.
+	Json,
+	/// [CBOR](https://www.rfc-editor.org/rfc/rfc8949) in binary frames.
+	Cbor,
+	/// [MessagePack](https://msgpack.org) in binary frames.
+	MessagePack,
+}
+
+/// Error when serializing or deserializing a message.
+#[derive(Debug, thiserror::Error)]
+pub enum EncodingError {
+	/// The message is not valid JSON, or could not be serialized as JSON.
+	#[error("Invalid JSON: {0}")]
+	Json(serde_json::Error),
+	/// The message is not valid CBOR, or could not be encoded as CBOR.
+	#[error("Invalid CBOR: {0}")]
+	Cbor(String),
+	/// The message is not valid MessagePack, or could not be encoded as MessagePack.
+	#[error("Invalid MessagePack: {0}")]
+	MessagePack(String),
+	/// Writing the message failed.
+	#[error("Failed to write message: {0}")]
+	Io(#[from] io::Error),
+}
+
+impl From<serde_json::Error> for EncodingError {
+	fn from(err: serde_json::Error) -> Self {
+		if err.is_io() {
+			EncodingError::Io(err.into())
+		} else {
+			EncodingError::Json(err)
+		}
+	}
+}
+
+impl From<ciborium::ser::Error<io::Error>> for EncodingError {
+	fn from(err: ciborium::ser::Error<io::Error>) -> Self {
+		match err {
+			ciborium::ser::Error::Io(err) => EncodingError::Io(err),
+			ciborium::ser::Error::Value(err) => EncodingError::Cbor(err),
+		}
+	}
+}
+
+impl From<rmp_serde::encode::Error> for EncodingError {
+	fn from(err: rmp_serde::encode::Error) -> Self {
+		match err {
+			rmp_serde::encode::Error::InvalidValueWrite(err) => EncodingError::Io(io::Error::other(err)),
+			err => EncodingError::MessagePack(err.to_string()),
+		}
+	}
+}
+
+impl WsEncoding {
+	/// Binary encodings, in order of preference.
+	pub const BINARY: [WsEncoding; 2] = [WsEncoding::Cbor, WsEncoding::MessagePack];
+
+	/// Returns the WebSocket subprotocol that selects this encoding, `None` for JSON which needs no subprotocol.
+	pub fn subprotocol(&self) -> Option<&'static str> {
+		match self {
+			WsEncoding::Json => None,
+			WsEncoding::Cbor => Some("jsonrpsee.cbor"),
+			WsEncoding::MessagePack => Some("jsonrpsee.msgpack"),
+		}
+	}
+
+	/// Returns the encoding selected by the WebSocket subprotocol `protocol`, if any.
+	pub fn from_subprotocol(protocol: &str) -> Option<Self> {
+		Self::BINARY.iter().copied().find(|encoding| encoding.subprotocol() == Some(protocol))
+	}
+
+	/// Whether messages in this encoding are sent in binary frames.
+	pub fn is_binary(&self) -> bool {
+		*self != WsEncoding::Json
+	}
+
+	/// Serialize a message into `writer`. The raw JSON values it contains are transcoded.
+	pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+		&self,
+		mut writer: W,
+		message: &T,
+	) -> Result<(), EncodingError> {
+		match self {
+			WsEncoding::Json => serde_json::to_writer(writer, message).map_err(Into::into),
+			WsEncoding::Cbor => ciborium::ser::into_writer(&WithRawValues(message), writer).map_err(Into::into),
+			WsEncoding::MessagePack => {
+				rmp_serde::encode::write_named(&mut writer, &WithRawValues(message)).map_err(Into::into)
+			}
+		}
+	}
+
+	/// Serialize a message. The raw JSON values it contains are transcoded.
+	pub fn to_vec<T: Serialize + ?Sized>(&self, message: &T) -> Result<Vec<u8>, EncodingError> {
+		let mut buf = Vec::with_capacity(128);
+		self.to_writer(&mut buf, message)?;
+		Ok(buf)
+	}
+
+	/// Deserialize a message. Use [`EncodedRequest`] to deserialize requests in any encoding.
+	pub fn from_slice<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, EncodingError> {
+		match self {
+			WsEncoding::Json => serde_json::from_slice(data).map_err(Into::into),
+			WsEncoding::Cbor => ciborium::de::from_reader(data).map_err(|e| EncodingError::Cbor(e.to_string())),
+			WsEncoding::MessagePack => {
+				rmp_serde::from_slice(data).map_err(|e| EncodingError::MessagePack(e.to_string()))
+			}
+		}
+	}
+
+	/// Transcode a JSON message into this encoding.
+	pub fn encode(&self, json: &str) -> Result<Vec<u8>, EncodingError> {
+		let mut deserializer = serde_json::Deserializer::from_str(json);
+		let transcoder = serde_transcode::Transcoder::new(&mut deserializer);
+		let mut buf = Vec::with_capacity(json.len());
+
+		match self {
+			WsEncoding::Json => return Ok(json.as_bytes().to_vec()),
+			WsEncoding::Cbor => ciborium::ser::into_writer(&transcoder, &mut buf)?,
+			WsEncoding::MessagePack => rmp_serde::encode::write_named(&mut buf, &transcoder)?,
+		}
+		deserializer.end()?;
+
+		Ok(buf)
+	}
+
+	/// Transcode a message in this encoding into JSON.
+	pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, EncodingError> {
+		match self {
+			WsEncoding::Json => Ok(data.to_vec()),
+			WsEncoding::Cbor | WsEncoding::MessagePack => self.from_slice::<JsonText>(data).map(|json| json.0),
+		}
+	}
+
+	/// Whether the message is a batch, i.e. an array of messages. Any other message is treated as a single message.
+	pub fn is_batch(&self, data: &[u8]) -> bool {
+		let first = match data.first() {
+			Some(first) => *first,
+			None => return false,
+		};
+
+		match self {
+			WsEncoding::Json => first == b'[',
+			// Major type 4.
+			WsEncoding::Cbor => first >> 5 == 4,
+			// fixarray, array 16 and array 32.
+			WsEncoding::MessagePack => first & 0xf0 == 0x90 || first == 0xdc || first == 0xdd,
+		}
+	}
+
+	/// Concatenate the `messages` of a batch response into an array.
+	pub fn encode_batch(&self, messages: &[Vec<u8>]) -> Vec<u8> {
+		let len = messages.len();
+		let mut buf = Vec::with_capacity(messages.iter().map(Vec::len).sum::<usize>() + len + 9);
+
+		match self {
+			WsEncoding::Json => {
+				buf.push(b'[');
+				for (i, message) in messages.iter().enumerate() {
+					if i > 0 {
+						buf.push(b',');
+					}
+					buf.extend_from_slice(message);
+				}
+				buf.push(b']');
+				return buf;
+			}
+			WsEncoding::Cbor => match len {
+				0..=23 => buf.push(0x80 | len as u8),
+				24..=0xff => buf.extend_from_slice(&[0x98, len as u8]),
+				0x100..=0xffff => {
+					buf.push(0x99);
+					buf.extend_from_slice(&(len as u16).to_be_bytes());
+				}
+				_ => {
+					buf.push(0x9a);
+					buf.extend_from_slice(&(len as u32).to_be_bytes());
+				}
+			},
+			WsEncoding::MessagePack => match len {
+				0..=15 => buf.push(0x90 | len as u8),
+				16..=0xffff => {
+					buf.push(0xdc);
+					buf.extend_from_slice(&(len as u16).to_be_bytes());
+				}
+				_ => {
+					buf.push(0xdd);
+					buf.extend_from_slice(&(len as u32).to_be_bytes());
+				}
+			},
+		}
+		for message in messages {
+			buf.extend_from_slice(message);
+		}
+
+		buf
+	}
+
+	/// Figure out if an invalid request is sufficiently complete to extract its [`Id`], to reply with an
+	/// [`ErrorCode::InvalidRequest`], or just plain unparseable garbage.
+	pub fn prepare_error(&self, data: &[u8]) -> (Id<'static>, ErrorCode) {
+		match self.from_slice::<EncodedInvalidRequest>(data) {
+			Ok(EncodedInvalidRequest { id }) => (id, ErrorCode::InvalidRequest),
+			Err(_) => (Id::Null, ErrorCode::ParseError),
+		}
+	}
+}
+
+/// Owned [`Request`] deserialized from any encoding, whose params are transcoded to JSON.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EncodedRequest {
+	/// JSON-RPC version.
+	pub jsonrpc: TwoPointZero,
+	/// Request ID
+	#[serde(deserialize_with = "owned_id")]
+	pub id: Id<'static>,
+	/// Name of the method to be invoked.
+	pub method: String,
+	/// Parameter values of the request, transcoded to JSON.
+	#[serde(default, deserialize_with = "json_params")]
+	pub params: Option<Box<RawValue>>,
+}
+
+impl EncodedRequest {
+	/// Borrow the request as a [`Request`].
+	pub fn as_request(&self) -> Request<'_> {
+		Request {
+			jsonrpc: self.jsonrpc,
+			id: self.id.clone(),
+			method: self.method.as_str().into(),
+			params: self.params.as_deref(),
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct EncodedInvalidRequest {
+	#[serde(deserialize_with = "owned_id")]
+	id: Id<'static>,
+}
+
+fn owned_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Id<'static>, D::Error> {
+	Id::deserialize(deserializer).map(Id::into_owned)
+}
+
+fn json_params<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Box<RawValue>>, D::Error> {
+	let JsonText(json) = JsonText::deserialize(deserializer)?;
+	if json == b"null" {
+		return Ok(None);
+	}
+	let json = String::from_utf8(json).map_err(de::Error::custom)?;
+	RawValue::from_string(json).map(Some).map_err(de::Error::custom)
+}
+
+/// JSON text of a value deserialized from any encoding.
+struct JsonText(Vec<u8>);
+
+impl<'de> Deserialize<'de> for JsonText {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let mut json = Vec::new();
+		serde_transcode::transcode(deserializer, &mut serde_json::Serializer::new(&mut json))
+			.map_err(de::Error::custom)?;
+		Ok(JsonText(json))
+	}
+}
+
+/// Serializes a message with a serializer that transcodes the [`RawValue`]s it contains, which would otherwise be
+/// serialized as a map holding their JSON text.
+struct WithRawValues<'a, T: ?Sized>(&'a T);
+
+impl<'a, T: Serialize + ?Sized> Serialize for WithRawValues<'a, T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		self.0.serialize(RawValueSerializer(serializer))
+	}
+}
+
+/// Name of the struct `serde_json` serializes a [`RawValue`] as.
+const RAW_VALUE_TOKEN: &str = "$serde_json::private::RawValue";
+
+struct RawValueSerializer<S>(S);
+
+/// Compound serializer whose elements are serialized with a [`RawValueSerializer`].
+struct Compound<C>(C);
+
+enum StructCompound<S: Serializer> {
+	Struct(S::SerializeStruct),
+	RawValue(Option<S>, Option<S::Ok>),
+}
+
+impl<S: Serializer> Serializer for RawValueSerializer<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+	type SerializeSeq = Compound<S::SerializeSeq>;
+	type SerializeTuple = Compound<S::SerializeTuple>;
+	type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+	type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+	type SerializeMap = Compound<S::SerializeMap>;
+	type SerializeStruct = StructCompound<S>;
+	type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+	fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> {
+		self.0.serialize_bool(v)
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> {
+		self.0.serialize_i8(v)
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> {
+		self.0.serialize_i16(v)
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> {
+		self.0.serialize_i32(v)
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> {
+		self.0.serialize_i64(v)
+	}
+
+	fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> {
+		self.0.serialize_i128(v)
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> {
+		self.0.serialize_u8(v)
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> {
+		self.0.serialize_u16(v)
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> {
+		self.0.serialize_u32(v)
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> {
+		self.0.serialize_u64(v)
+	}
+
+	fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> {
+		self.0.serialize_u128(v)
+	}
+
+	fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> {
+		self.0.serialize_f32(v)
+	}
+
+	fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> {
+		self.0.serialize_f64(v)
+	}
+
+	fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> {
+		self.0.serialize_char(v)
+	}
+
+	fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> {
+		self.0.serialize_str(v)
+	}
+
+	fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> {
+		self.0.serialize_bytes(v)
+	}
+
+	fn serialize_none(self) -> Result<S::Ok, S::Error> {
+		self.0.serialize_none()
+	}
+
+	fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+		self.0.serialize_some(&WithRawValues(value))
+	}
+
+	fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+		self.0.serialize_unit()
+	}
+
+	fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+		self.0.serialize_unit_struct(name)
+	}
+
+	fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+		self.0.serialize_unit_variant(name, index, variant)
+	}
+
+	fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+		self.0.serialize_newtype_struct(name, &WithRawValues(value))
+	}
+
+	fn serialize_newtype_variant<T: Serialize + ?Sized>(
+		self,
+		name: &'static str,
+		index: u32,
+		variant: &'static str,
+		value: &T,
+	) -> Result<S::Ok, S::Error> {
+		self.0.serialize_newtype_variant(name, index, variant, &WithRawValues(value))
+	}
+
+	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+		self.0.serialize_seq(len).map(Compound)
+	}
+
+	fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+		self.0.serialize_tuple(len).map(Compound)
+	}
+
+	fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+		self.0.serialize_tuple_struct(name, len).map(Compound)
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		name: &'static str,
+		index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeTupleVariant, S::Error> {
+		self.0.serialize_tuple_variant(name, index, variant, len).map(Compound)
+	}
+
+	fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+		self.0.serialize_map(len).map(Compound)
+	}
+
+	fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+		if name == RAW_VALUE_TOKEN {
+			Ok(StructCompound::RawValue(Some(self.0), None))
+		} else {
+			self.0.serialize_struct(name, len).map(StructCompound::Struct)
+		}
+	}
+
+	fn serialize_struct_variant(
+		self,
+		name: &'static str,
+		index: u32,
+		variant: &'static str,
+		len: usize,
+	) -> Result<Self::SerializeStructVariant, S::Error> {
+		self.0.serialize_struct_variant(name, index, variant, len).map(Compound)
+	}
+
+	fn is_human_readable(&self) -> bool {
+		self.0.is_human_readable()
+	}
+}
+
+impl<C: ser::SerializeSeq> ser::SerializeSeq for Compound<C> {
+	type Ok = C::Ok;
+	type Error = C::Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+		self.0.serialize_element(&WithRawValues(value))
+	}
+
+	fn end(self) -> Result<C::Ok, C::Error> {
+		self.0.end()
+	}
+}
+
+impl<C: ser::SerializeTuple> ser::SerializeTuple for Compound<C> {
+	type Ok = C::Ok;
+	type Error = C::Error;
+
+	fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+		self.0.serialize_element(&WithRawValues(value))
+	}
+
+	fn end(self) -> Result<C::Ok, C::Error> {
+		self.0.end()
+	}
+}
+
+impl<C: ser::SerializeTupleStruct> ser::SerializeTupleStruct for Compound<C> {
+	type Ok = C::Ok;
+	type Error = C::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+		self.0.serialize_field(&WithRawValues(value))
+	}
+
+	fn end(self) -> Result<C::Ok, C::Error> {
+		self.0.end()
+	}
+}
+
+impl<C: ser::SerializeTupleVariant> ser::SerializeTupleVariant for Compound<C> {
+	type Ok = C::Ok;
+	type Error = C::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+		self.0.serialize_field(&WithRawValues(value))
+	}
+
+	fn end(self) -> Result<C::Ok, C::Error> {
+		self.0.end()
+	}
+}
+
+impl<C: ser::SerializeMap> ser::SerializeMap for Compound<C> {
+	type Ok = C::Ok;
+	type Error = C::Error;
+
+	fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+		self.0.serialize_key(&WithRawValues(key))
+	}
+
+	fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+		self.0.serialize_value(&WithRawValues(value))
+	}
+
+	fn end(self) -> Result<C::Ok, C::Error> {
+		self.0.end()
+	}
+}
+
+impl<C: ser::SerializeStructVariant> ser::SerializeStructVariant for Compound<C> {
+	type Ok = C::Ok;
+	type Error = C::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+		self.0.serialize_field(key, &WithRawValues(value))
+	}
+
+	fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+		self.0.skip_field(key)
+	}
+
+	fn end(self) -> Result<C::Ok, C::Error> {
+		self.0.end()
+	}
+}
+
+impl<S: Serializer> ser::SerializeStruct for StructCompound<S> {
+	type Ok = S::Ok;
+	type Error = S::Error;
+
+	fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error> {
+		match self {
+			StructCompound::Struct(compound) => compound.serialize_field(key, &WithRawValues(value)),
+			// The only field of a `RawValue` is its JSON text.
+			StructCompound::RawValue(serializer, ok) => {
+				let json = match serde_json::to_value(value) {
+					Ok(serde_json::Value::String(json)) => json,
+					_ => return Err(ser::Error::custom("Invalid raw JSON value")),
+				};
+				let serializer = serializer.take().ok_or_else(|| ser::Error::custom("Invalid raw JSON value"))?;
+				let mut deserializer = serde_json::Deserializer::from_str(&json);
+				*ok = Some(serde_transcode::transcode(&mut deserializer, serializer)?);
+				Ok(())
+			}
+		}
+	}
+
+	fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+		match self {
+			StructCompound::Struct(compound) => compound.skip_field(key),
+			StructCompound::RawValue(..) => Ok(()),
+		}
+	}
+
+	fn end(self) -> Result<S::Ok, S::Error> {
+		match self {
+			StructCompound::Struct(compound) => compound.end(),
+			StructCompound::RawValue(_, ok) => ok.ok_or_else(|| ser::Error::custom("Invalid raw JSON value")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{EncodedRequest, WsEncoding};
+	use crate::v2::{ErrorCode, ErrorObject, Id, Response, RpcError};
+	use serde_json::Value as JsonValue;
+
+	#[test]
+	fn subprotocols_roundtrip() {
+		for encoding in WsEncoding::BINARY.iter() {
+			assert_eq!(WsEncoding::from_subprotocol(encoding.subprotocol().unwrap()), Some(*encoding));
+		}
+		assert_eq!(WsEncoding::Json.subprotocol(), None);
+		assert_eq!(WsEncoding::from_subprotocol("unknown"), None);
+	}
+
+	#[test]
+	fn transcoding_roundtrip() {
+		let json = r#"{"jsonrpc":"2.0","method":"say_hello","params":[1,-2,"three",null,{"four":4.5}],"id":1}"#;
+
+		for encoding in WsEncoding::BINARY.iter() {
+			let encoded = encoding.encode(json).unwrap();
+			assert!(encoded.len() < json.len());
+			let decoded: JsonValue = serde_json::from_slice(&encoding.decode(&encoded).unwrap()).unwrap();
+			assert_eq!(decoded, serde_json::from_str::<JsonValue>(json).unwrap());
+		}
+	}
+
+	#[test]
+	fn invalid_messages_are_rejected() {
+		assert!(WsEncoding::Cbor.encode("not json").is_err());
+		assert!(WsEncoding::Cbor.decode(&[0xff]).is_err());
+		assert!(WsEncoding::MessagePack.decode(&[0xc1]).is_err());
+	}
+
+	#[test]
+	fn raw_values_are_transcoded() {
+		let data = serde_json::value::to_raw_value(&serde_json::json!({ "reason": [1, 2] })).unwrap();
+		let error = ErrorObject { code: ErrorCode::InvalidParams, message: "Invalid".into(), data: Some(&data) };
+		let error = RpcError::new(error, Id::Number(1));
+		let result = serde_json::value::to_raw_value(&"raw").unwrap();
+		let response = Response::new(&*result, Id::Str("a".into()));
+
+		for encoding in WsEncoding::BINARY.iter() {
+			let expected = serde_json::to_value(&error).unwrap();
+			let decoded: JsonValue = encoding.from_slice(&encoding.to_vec(&error).unwrap()).unwrap();
+			assert_eq!(decoded, expected);
+
+			let decoded: JsonValue = encoding.from_slice(&encoding.to_vec(&response).unwrap()).unwrap();
+			assert_eq!(decoded, serde_json::json!({ "jsonrpc": "2.0", "result": "raw", "id": "a" }));
+		}
+	}
+
+	#[test]
+	fn requests_are_decoded_with_json_params() {
+		let request =
+			serde_json::json!({ "jsonrpc": "2.0", "method": "say_hello", "params": [1, { "two": 2 }], "id": "x" });
+		let notification = serde_json::json!({ "jsonrpc": "2.0", "method": "say_hello", "params": null, "id": null });
+
+		for encoding in [WsEncoding::Json, WsEncoding::Cbor, WsEncoding::MessagePack] {
+			let decoded: EncodedRequest = encoding.from_slice(&encoding.to_vec(&request).unwrap()).unwrap();
+			let request = decoded.as_request();
+			assert_eq!(request.id, Id::Str("x".into()));
+			assert_eq!(request.method, "say_hello");
+			assert_eq!(request.params.unwrap().get(), r#"[1,{"two":2}]"#);
+
+			let decoded: EncodedRequest = encoding.from_slice(&encoding.to_vec(&notification).unwrap()).unwrap();
+			assert_eq!(decoded.id, Id::Null);
+			assert!(decoded.params.is_none());
+
+			let invalid = encoding.to_vec(&serde_json::json!({ "method": 1, "id": 2 })).unwrap();
+			assert!(encoding.from_slice::<EncodedRequest>(&invalid).is_err());
+			assert_eq!(encoding.prepare_error(&invalid), (Id::Number(2), ErrorCode::InvalidRequest));
+			assert_eq!(encoding.prepare_error(b""), (Id::Null, ErrorCode::ParseError));
+		}
+	}
+
+	#[test]
+	fn batches_are_encoded_as_arrays() {
+		for encoding in [WsEncoding::Json, WsEncoding::Cbor, WsEncoding::MessagePack] {
+			for len in [0, 1, 15, 16, 23, 24, 300] {
+				let messages: Vec<_> = (0..len).map(|i| encoding.to_vec(&i).unwrap()).collect();
+				let batch = encoding.encode_batch(&messages);
+				assert!(encoding.is_batch(&batch));
+				let decoded: Vec<u64> = encoding.from_slice(&batch).unwrap();
+				assert_eq!(decoded, (0..len).collect::<Vec<u64>>());
+			}
+			assert!(!encoding.is_batch(&encoding.to_vec(&serde_json::json!({ "id": [1] })).unwrap()));
+		}
+	}
+}
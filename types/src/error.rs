@@ -24,6 +24,7 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::v2::error::{ErrorCode, OwnedErrorObject, RpcError, CALL_EXECUTION_FAILED_CODE, UNKNOWN_ERROR_CODE};
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use std::fmt;
@@ -121,6 +122,9 @@ pub enum Error {
 	/// Method was already registered.
 	#[error("Method: {0} was already registered")]
 	MethodAlreadyRegistered(String),
+	/// Method name doesn't start with a version prefix.
+	#[error("Method: {0} must start with a version prefix, e.g. `v1_`")]
+	MissingVersionPrefix(String),
 	/// Method with that name has not yet been registered.
 	#[error("Method: {0} has not yet been registered")]
 	MethodNotFound(String),
@@ -175,6 +179,81 @@ impl Error {
 	{
 		Error::Call(CallError::from_std_error(err))
 	}
+
+	/// Turn an [`Error::Request`] received by a client into the [`Error::Call`] of its JSON-RPC error object, for
+	/// it to be decoded or sent again as it is. Other errors, and requests errors without an error object, are
+	/// returned unchanged.
+	pub fn into_call_error(self) -> Self {
+		match self {
+			Error::Request(raw) => {
+				let object = serde_json::from_str::<RpcError>(&raw).map(|rpc_error| rpc_error.error.into());
+				match object {
+					Ok(object) => Self::from_error_object(object),
+					Err(_) => Error::Request(raw),
+				}
+			}
+			err => err,
+		}
+	}
+}
+
+/// Error type of an RPC method that converts to and from a JSON-RPC error object.
+///
+/// This allows methods to return their own typed errors on the server and to decode a failed call back into the
+/// same type on the client. The conversion from [`Error`] is used for failures that never reached the method,
+/// such as transport errors or errors that didn't come with a JSON-RPC error object.
+pub trait MethodError: From<Error> + Sized {
+	/// Convert the error into the JSON-RPC error object sent to the client.
+	fn into_error_object(self) -> OwnedErrorObject;
+
+	/// Decode the error from the JSON-RPC error object received from the server.
+	fn from_error_object(object: OwnedErrorObject) -> Self;
+
+	/// Convert the error into the [`Error`] returned by the method callbacks.
+	fn into_error(self) -> Error {
+		let OwnedErrorObject { code, message, data } = self.into_error_object();
+		Error::Call(CallError::Custom { code: code.code(), message, data })
+	}
+
+	/// Decode the error from the [`Error`] returned by a client.
+	fn from_error(err: Error) -> Self {
+		match err.into_call_error() {
+			Error::Call(CallError::Custom { code, message, data }) => {
+				Self::from_error_object(OwnedErrorObject { code: code.into(), message, data })
+			}
+			err => err.into(),
+		}
+	}
+}
+
+impl MethodError for Error {
+	fn into_error_object(self) -> OwnedErrorObject {
+		match self {
+			Error::Call(CallError::InvalidParams(e)) => OwnedErrorObject::new(ErrorCode::InvalidParams, e.to_string()),
+			Error::Call(CallError::Failed(e)) => {
+				OwnedErrorObject::new(ErrorCode::ServerError(CALL_EXECUTION_FAILED_CODE), e.to_string())
+			}
+			Error::Call(CallError::Custom { code, message, data }) => {
+				OwnedErrorObject { code: code.into(), message, data }
+			}
+			// This should normally not happen because the most common use case is to
+			// return `Error::Call` in `register_async_method`.
+			e => OwnedErrorObject::new(ErrorCode::ServerError(UNKNOWN_ERROR_CODE), e.to_string()),
+		}
+	}
+
+	fn from_error_object(object: OwnedErrorObject) -> Self {
+		let OwnedErrorObject { code, message, data } = object;
+		Error::Call(CallError::Custom { code: code.code(), message, data })
+	}
+
+	fn into_error(self) -> Error {
+		self
+	}
+
+	fn from_error(err: Error) -> Self {
+		err
+	}
 }
 
 /// A type with a special `subscription_closed` field to detect that
@@ -259,7 +338,9 @@ impl From<hyper::Error> for Error {
 
 #[cfg(test)]
 mod tests {
-	use super::{SubscriptionClosed, SubscriptionClosedReason};
+	use super::{
+		CallError, Error, ErrorCode, MethodError, OwnedErrorObject, SubscriptionClosed, SubscriptionClosedReason,
+	};
 
 	#[test]
 	fn subscription_closed_ser_deser_works() {
@@ -277,6 +358,43 @@ mod tests {
 		}
 	}
 
+	#[derive(Debug, PartialEq)]
+	struct Decoded(Option<OwnedErrorObject>);
+
+	impl From<Error> for Decoded {
+		fn from(_: Error) -> Self {
+			Decoded(None)
+		}
+	}
+
+	impl MethodError for Decoded {
+		fn into_error_object(self) -> OwnedErrorObject {
+			self.0.unwrap()
+		}
+
+		fn from_error_object(object: OwnedErrorObject) -> Self {
+			Decoded(Some(object))
+		}
+	}
+
+	#[test]
+	fn method_error_decodes_rpc_errors() {
+		let err =
+			Error::Request(r#"{"jsonrpc":"2.0","error":{"code":-32100,"message":"Not found","data":7},"id":0}"#.into());
+		let object = Decoded::from_error(err).0.unwrap();
+		assert_eq!(object.code, ErrorCode::ServerError(-32100));
+		assert_eq!(object.message, "Not found");
+		assert_eq!(object.data::<u64>(), Some(7));
+
+		assert_eq!(Decoded::from_error(Error::RequestTimeout), Decoded(None));
+		assert!(
+			matches!(Error::Request("Bad gateway".into()).into_call_error(), Error::Request(raw) if raw == "Bad gateway")
+		);
+
+		let object = Error::Call(CallError::InvalidParams(anyhow::anyhow!("missing field"))).into_error_object();
+		assert_eq!(object, OwnedErrorObject::new(ErrorCode::InvalidParams, "missing field"));
+	}
+
 	#[test]
 	fn subscription_closed_deny_unknown_field() {
 		let ser = r#"{"reason":"Unsubscribed","deny":1}"#;
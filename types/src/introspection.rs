@@ -0,0 +1,139 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Description of the methods registered on a server, as returned by the `rpc_methods` introspection method.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the introspection method.
+pub const RPC_METHODS: &str = "rpc_methods";
+
+/// Response of the introspection method.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RpcMethods {
+	/// All the methods, sorted by name.
+	pub methods: Vec<MethodDescription>,
+}
+
+impl RpcMethods {
+	/// Look up a method by name.
+	pub fn get(&self, name: &str) -> Option<&MethodDescription> {
+		self.methods.iter().find(|method| method.name == name)
+	}
+}
+
+/// Description of a single method.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodDescription {
+	/// Name the method is registered with.
+	pub name: String,
+	/// What calling the method does.
+	#[serde(rename = "type")]
+	pub ty: MethodType,
+	/// Name of the method this one is an alias of.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub alias_of: Option<String>,
+	/// For subscriptions, the method that terminates them.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub unsubscribe: Option<String>,
+	/// For unsubscribe methods, the method that starts the subscriptions they terminate.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub subscribe: Option<String>,
+	/// Whether the method is deprecated.
+	#[serde(default)]
+	pub deprecated: bool,
+	/// Units of each resource a call claims.
+	#[serde(default)]
+	pub resources: BTreeMap<String, u16>,
+	/// How the client sends the params, if known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub param_kind: Option<ParamKind>,
+	/// The params of the method, if known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub params: Option<Vec<ParamDescription>>,
+	/// For unversioned names of versioned methods, the method registered for each version.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub versions: BTreeMap<u32, String>,
+}
+
+/// What calling a method does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MethodType {
+	/// Regular method call.
+	#[default]
+	Method,
+	/// Starts a subscription.
+	Subscription,
+	/// Terminates a subscription.
+	Unsubscribe,
+}
+
+/// How params are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamKind {
+	/// Params are sent as an array, in declaration order.
+	Array,
+	/// Params are sent as an object keyed by their names.
+	Map,
+}
+
+/// Description of a single param.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamDescription {
+	/// Name of the param.
+	pub name: String,
+	/// Rust type of the param.
+	#[serde(rename = "type")]
+	pub ty: String,
+	/// Whether the param can be omitted.
+	#[serde(default)]
+	pub optional: bool,
+	/// Whether the param is the entire params object rather than one of its fields.
+	#[serde(default, skip_serializing_if = "is_false")]
+	pub flatten: bool,
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+	!*value
+}
+
+impl ParamDescription {
+	/// Create a new description.
+	pub fn new(name: impl Into<String>, ty: impl Into<String>, optional: bool) -> Self {
+		Self { name: name.into(), ty: ty.into(), optional, flatten: false }
+	}
+
+	/// Mark the param as the entire params object.
+	pub fn flattened(mut self) -> Self {
+		self.flatten = true;
+		self
+	}
+}
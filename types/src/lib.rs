@@ -49,10 +49,41 @@ pub mod traits;
 /// Middleware trait and implementation.
 pub mod middleware;
 
+/// Binary encodings of JSON-RPC messages over WebSocket.
+#[cfg(feature = "ws-encodings")]
+pub mod encoding;
+
+/// In-process mock client.
+pub mod mock;
+
+/// Batch requests whose calls have different result types.
+pub mod batch;
+
+/// Already serialized JSON-RPC messages forwarded by the clients.
+pub mod raw;
+
+/// Client balancing the calls between several endpoints.
+#[cfg(feature = "client")]
+pub mod pool;
+
+/// Caching of the responses of idempotent methods.
+pub mod cache;
+
+/// Retry policy of the clients.
+#[cfg(feature = "client")]
+pub mod retry;
+
+/// Description of the methods registered on a server.
+pub mod introspection;
+
+/// OpenRPC service descriptions.
+#[cfg(feature = "openrpc")]
+pub mod openrpc;
+
 pub use async_trait::async_trait;
 pub use beef::Cow;
 pub use client::*;
-pub use error::{CallError, Error};
+pub use error::{CallError, Error, MethodError};
 pub use serde::{de::DeserializeOwned, Serialize};
 pub use serde_json::{
 	to_value as to_json_value, value::to_raw_value as to_json_raw_value, value::RawValue as JsonRawValue,
@@ -64,6 +95,8 @@ pub use serde_json::{
 #[doc(hidden)]
 pub mod __reexports {
 	pub use async_trait::async_trait;
+	#[cfg(feature = "openrpc")]
+	pub use schemars;
 	pub use serde;
 	pub use serde_json;
 }
@@ -24,7 +24,18 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-//! Middleware for `jsonrpsee` servers.
+//! Middleware for `jsonrpsee` servers and clients.
+
+use crate::v2::ParamsSer;
+use crate::Error;
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+#[cfg(feature = "client")]
+use std::future::Future;
+use std::time::Duration;
+#[cfg(feature = "client")]
+use std::time::Instant;
 
 /// Defines a middleware with callbacks during the RPC request life-cycle. The primary use case for
 /// this is to collect timings for a larger metrics collection solution but the only constraints on
@@ -48,6 +59,9 @@ pub trait Middleware: Send + Sync + Clone + 'static {
 	/// Called on each JSON-RPC method call, batch requests will trigger `on_call` multiple times.
 	fn on_call(&self, _name: &str) {}
 
+	/// Called on each JSON-RPC method call to a deprecated method, with the name of the method the call was routed to.
+	fn on_deprecated_call(&self, _name: &str) {}
+
 	/// Called on each JSON-RPC method completion, batch requests will trigger `on_result` multiple times.
 	fn on_result(&self, _name: &str, _success: bool, _started_at: Self::Instant) {}
 
@@ -80,6 +94,11 @@ where
 		self.1.on_call(name);
 	}
 
+	fn on_deprecated_call(&self, name: &str) {
+		self.0.on_deprecated_call(name);
+		self.1.on_deprecated_call(name);
+	}
+
 	fn on_result(&self, name: &str, success: bool, started_at: Self::Instant) {
 		self.0.on_result(name, success, started_at.0);
 		self.1.on_result(name, success, started_at.1);
@@ -90,3 +109,236 @@ where
 		self.1.on_response(started_at.1);
 	}
 }
+
+/// Call made by a client, as seen and modified by a [`ClientMiddleware`].
+///
+/// The params are the ones passed to the client, they can only be modified in place when they are owned, e.g.
+/// [`ParamsSer::Map`] or [`ParamsSer::Array`], and otherwise have to be replaced.
+#[derive(Debug, Clone)]
+pub struct ClientRequest<'a> {
+	/// Name of the called method.
+	pub method: Cow<'a, str>,
+	/// Params of the call.
+	pub params: Option<ParamsSer<'a>>,
+	/// Extra HTTP headers sent along with the call. The WebSocket client ignores them with a warning, headers are
+	/// only sent once when it connects.
+	pub headers: Vec<(String, String)>,
+}
+
+impl<'a> ClientRequest<'a> {
+	/// Create a call to `method` with `params`, without extra headers.
+	pub fn new(method: &'a str, params: Option<ParamsSer<'a>>) -> Self {
+		Self { method: Cow::Borrowed(method), params, headers: Vec::new() }
+	}
+
+	/// Params of the call, to be serialized in the request.
+	pub fn params(&self) -> Option<ParamsSer<'_>> {
+		self.params.as_ref().map(ParamsSer::as_borrowed)
+	}
+}
+
+/// Response to a call made through a [`ClientMiddleware`].
+pub trait ClientResponse {
+	/// Pass the response to [`ClientMiddleware::on_response`], if it has one to modify.
+	fn on_response<M: ClientMiddleware>(&mut self, middleware: &M, method: &str);
+}
+
+impl ClientResponse for JsonValue {
+	fn on_response<M: ClientMiddleware>(&mut self, middleware: &M, method: &str) {
+		middleware.on_response(method, self);
+	}
+}
+
+/// Raw responses are forwarded as they are, without decoding them for [`ClientMiddleware::on_response`].
+impl ClientResponse for Box<RawValue> {
+	fn on_response<M: ClientMiddleware>(&mut self, _middleware: &M, _method: &str) {}
+}
+
+impl<T: ClientResponse> ClientResponse for Option<T> {
+	fn on_response<M: ClientMiddleware>(&mut self, middleware: &M, method: &str) {
+		if let Some(response) = self {
+			response.on_response(middleware, method);
+		}
+	}
+}
+
+/// Defines a middleware with callbacks during the life-cycle of the calls made by a client, applied the same way by
+/// the HTTP and WebSocket clients. It can modify the calls and their responses, measure them and retry them, which
+/// is how the [`RetryPolicy`](crate::retry::RetryPolicy) of the clients is applied.
+///
+/// Batch requests trigger the callbacks once per call of the batch, and are only retried if all of their calls may
+/// be. Notifications and subscriptions have no response to modify.
+///
+/// See the [`HttpClientBuilder::set_middleware`](../../jsonrpsee_http_client/struct.HttpClientBuilder.html#method.set_middleware)
+/// or the [`WsClientBuilder::set_middleware`](../../jsonrpsee_ws_client/struct.WsClientBuilder.html#method.set_middleware) method
+/// for examples.
+pub trait ClientMiddleware: Send + Sync + Clone + 'static {
+	/// Intended to carry timestamp of a call, for example `std::time::Instant`. How the middleware measures time, if
+	/// at all, is entirely up to the implementation.
+	type Instant: Send + Copy;
+
+	/// Whether the calls are passed through the middleware. It is `false` for the middleware which neither modify
+	/// nor measure the calls, such as `()`, which lets the calls be sent as they are without copying them.
+	const ENABLED: bool = true;
+
+	/// Called before each attempt to send a call, which may be modified, e.g. to add headers or params.
+	fn on_request(&self, request: &mut ClientRequest<'_>) -> Self::Instant;
+
+	/// Called on each successful response before it's decoded, which may be modified.
+	fn on_response(&self, _method: &str, _response: &mut JsonValue) {}
+
+	/// Called on each completion of an attempt to send a call.
+	fn on_result(&self, _method: &str, _success: bool, _started_at: Self::Instant) {}
+
+	/// Called when an attempt to send the `requests` sent together failed, they are sent again after the returned
+	/// backoff if any. `attempt` starts at 1 for the first attempt.
+	fn retry_after(&self, _requests: &[ClientRequest<'_>], _error: &Error, _attempt: u32) -> Option<Duration> {
+		None
+	}
+
+	/// Timeout of each attempt to send a call, within the request timeout of the client bounding the whole call.
+	fn attempt_timeout(&self) -> Option<Duration> {
+		None
+	}
+}
+
+impl ClientMiddleware for () {
+	type Instant = ();
+
+	const ENABLED: bool = false;
+
+	fn on_request(&self, _request: &mut ClientRequest<'_>) -> Self::Instant {}
+}
+
+impl<A, B> ClientMiddleware for (A, B)
+where
+	A: ClientMiddleware,
+	B: ClientMiddleware,
+{
+	type Instant = (A::Instant, B::Instant);
+
+	const ENABLED: bool = A::ENABLED || B::ENABLED;
+
+	fn on_request(&self, request: &mut ClientRequest<'_>) -> Self::Instant {
+		(self.0.on_request(request), self.1.on_request(request))
+	}
+
+	fn on_response(&self, method: &str, response: &mut JsonValue) {
+		self.0.on_response(method, response);
+		self.1.on_response(method, response);
+	}
+
+	fn on_result(&self, method: &str, success: bool, started_at: Self::Instant) {
+		self.0.on_result(method, success, started_at.0);
+		self.1.on_result(method, success, started_at.1);
+	}
+
+	fn retry_after(&self, requests: &[ClientRequest<'_>], error: &Error, attempt: u32) -> Option<Duration> {
+		self.0.retry_after(requests, error, attempt).or_else(|| self.1.retry_after(requests, error, attempt))
+	}
+
+	fn attempt_timeout(&self) -> Option<Duration> {
+		match (self.0.attempt_timeout(), self.1.attempt_timeout()) {
+			(Some(a), Some(b)) => Some(a.min(b)),
+			(a, b) => a.or(b),
+		}
+	}
+}
+
+/// Sends the `calls` with `send` through the `middleware`, returns the responses to the calls.
+///
+/// `send` is given the calls modified by the middleware, and called again for as long as the middleware asks to
+/// retry them. The whole call, retries and the backoff between them included, fails with [`Error::RequestTimeout`]
+/// once `timeout` elapsed.
+#[cfg(feature = "client")]
+pub async fn call_with_middleware<'a, M, F, Fut, T>(
+	middleware: &M,
+	timeout: Duration,
+	calls: Vec<ClientRequest<'a>>,
+	mut send: F,
+) -> Result<Vec<T>, Error>
+where
+	M: ClientMiddleware,
+	F: FnMut(&[ClientRequest<'a>]) -> Fut,
+	Fut: Future<Output = Result<Vec<T>, Error>>,
+	T: ClientResponse,
+{
+	let responses = call_batch_with_middleware(middleware, timeout, calls, |requests| {
+		let fut = send(requests);
+		async move { fut.await.map(|responses| responses.into_iter().map(Ok).collect()) }
+	})
+	.await?;
+	responses.into_iter().collect()
+}
+
+/// Same as [`call_with_middleware`], for the calls of a batch which may fail independently: `send` returns the
+/// result of each call, and only fails if the batch as a whole failed.
+///
+/// The middleware sees the failed calls as unsuccessful, but they are not retried.
+#[cfg(feature = "client")]
+pub async fn call_batch_with_middleware<'a, M, F, Fut, T>(
+	middleware: &M,
+	timeout: Duration,
+	calls: Vec<ClientRequest<'a>>,
+	mut send: F,
+) -> Result<Vec<Result<T, Error>>, Error>
+where
+	M: ClientMiddleware,
+	F: FnMut(&[ClientRequest<'a>]) -> Fut,
+	Fut: Future<Output = Result<Vec<Result<T, Error>>, Error>>,
+	T: ClientResponse,
+{
+	// `None` if the timeout is too large to be reached.
+	let deadline = Instant::now().checked_add(timeout);
+	let mut attempt = 0;
+	loop {
+		attempt += 1;
+		// The calls are only copied when the middleware may modify them.
+		let mut requests = Cow::Borrowed(&calls[..]);
+		let started_at: Vec<_> = if M::ENABLED {
+			requests.to_mut().iter_mut().map(|request| middleware.on_request(request)).collect()
+		} else {
+			Vec::new()
+		};
+
+		let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+		let attempt_timeout = match (remaining, middleware.attempt_timeout()) {
+			(Some(remaining), Some(attempt_timeout)) => Some(remaining.min(attempt_timeout)),
+			(remaining, attempt_timeout) => remaining.or(attempt_timeout),
+		};
+		let res = match attempt_timeout {
+			Some(attempt_timeout) => {
+				tokio::time::timeout(attempt_timeout, send(&requests)).await.unwrap_or(Err(Error::RequestTimeout))
+			}
+			None => send(&requests).await,
+		};
+
+		match res {
+			Ok(mut responses) => {
+				for (request, response) in requests.iter().zip(responses.iter_mut()) {
+					if let Ok(response) = response {
+						response.on_response(middleware, &request.method);
+					}
+				}
+				for ((request, started_at), response) in requests.iter().zip(started_at).zip(&responses) {
+					middleware.on_result(&request.method, response.is_ok(), started_at);
+				}
+				return Ok(responses);
+			}
+			Err(err) => {
+				for (request, started_at) in requests.iter().zip(started_at) {
+					middleware.on_result(&request.method, false, started_at);
+				}
+				let backoff = match middleware.retry_after(&requests, &err, attempt) {
+					Some(backoff) if !requests.is_empty() => backoff,
+					_ => return Err(err),
+				};
+				if matches!(deadline, Some(deadline) if Instant::now() + backoff >= deadline) {
+					return Err(err);
+				}
+				tracing::debug!("Retrying failed attempt {} in {:?}: {}", attempt, backoff, err);
+				tokio::time::sleep(backoff).await;
+			}
+		}
+	}
+}
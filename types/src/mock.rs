@@ -0,0 +1,257 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::batch::{BatchRequestBuilder, BatchResponse};
+#[cfg(feature = "client")]
+use crate::pool::PooledClient;
+use crate::traits::{Client, SubscriptionClient};
+use crate::v2::{Id, Params, ParamsSer, RpcError};
+use crate::{Error, FrontToBack, MethodError, Subscription, SubscriptionKind};
+use async_trait::async_trait;
+use futures_channel::mpsc;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Mutex;
+
+/// Handler answering one expected call on the [`MockClient`].
+///
+/// It receives the params of the call and returns the JSON result, or for subscriptions a JSON array of the
+/// notifications to deliver.
+pub type MockHandler = Box<dyn FnOnce(Params) -> Result<JsonValue, Error> + Send>;
+
+/// Client that answers calls from a queue of expectations instead of talking to a server.
+///
+/// Every call to a method consumes the next expectation registered for it with [`MockClient::expect`], calls without
+/// one fail with [`Error::Custom`]. The errors of the handlers are returned as [`Error::Request`], like the error
+/// responses of a server are by the other clients, except for the errors raised by the clients themselves, e.g.
+/// [`Error::Transport`] or [`Error::RequestTimeout`], which are returned as-is to simulate failures of the connection.
+/// Dropping the client panics if some of the expectations were not consumed, see
+/// [`MockClient::checkpoint`].
+///
+/// The `#[rpc(client, mock)]` macro generates typed `expect_*` methods for it, and since it implements
+/// [`SubscriptionClient`] the generated client trait is available on it as well.
+pub struct MockClient {
+	/// Expectations by method name, answered in the order they were registered.
+	expectations: Mutex<HashMap<String, VecDeque<MockHandler>>>,
+	/// Channel given to the subscriptions, they only use it to unregister themselves.
+	to_back: mpsc::Sender<FrontToBack>,
+	/// Kept alive so that closing a subscription never fails.
+	_from_front: mpsc::Receiver<FrontToBack>,
+}
+
+impl fmt::Debug for MockClient {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MockClient").field("pending", &self.pending()).finish()
+	}
+}
+
+impl Default for MockClient {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl MockClient {
+	/// Create a mock client without any expectations.
+	pub fn new() -> Self {
+		let (to_back, from_front) = mpsc::channel(0);
+		Self { expectations: Mutex::new(HashMap::new()), to_back, _from_front: from_front }
+	}
+
+	/// Expect one call to `method`, answered by `handler`.
+	pub fn expect<F>(&self, method: impl Into<String>, handler: F) -> &Self
+	where
+		F: FnOnce(Params) -> Result<JsonValue, Error> + Send + 'static,
+	{
+		let mut expectations = self.expectations.lock().expect("lock poisoned; qed");
+		expectations.entry(method.into()).or_default().push_back(Box::new(handler));
+		self
+	}
+
+	/// Names of the methods with expectations that were not consumed yet, once per expected call.
+	pub fn pending(&self) -> Vec<String> {
+		let expectations = self.expectations.lock().expect("lock poisoned; qed");
+		let mut pending: Vec<String> =
+			expectations.iter().flat_map(|(method, handlers)| handlers.iter().map(move |_| method.clone())).collect();
+		pending.sort();
+		pending
+	}
+
+	/// Panic if some of the expectations were not consumed yet.
+	pub fn checkpoint(&self) {
+		let pending = self.pending();
+		if !pending.is_empty() {
+			panic!("MockClient: expected calls were not made: {:?}", pending);
+		}
+	}
+
+	fn call(&self, method: &str, params: Option<ParamsSer>) -> Result<JsonValue, Error> {
+		let handler = {
+			let mut expectations = self.expectations.lock().expect("lock poisoned; qed");
+			expectations.get_mut(method).and_then(VecDeque::pop_front)
+		};
+		let handler = handler.ok_or_else(|| Error::Custom(format!("MockClient: unexpected call to `{}`", method)))?;
+		let raw = params.map(|params| serde_json::to_string(&params)).transpose()?;
+		handler(Params::new(raw.as_deref())).map_err(|err| {
+			if is_client_error(&err) {
+				return err;
+			}
+			// Same error as when a server answers with the error response of the handler.
+			let err = err.into_error_object();
+			let response = RpcError::new(err.as_error_object(), Id::Number(0)).to_string();
+			Error::Request(response)
+		})
+	}
+
+	fn subscription<Notif>(&self, method: &str, params: Option<ParamsSer>) -> Result<Subscription<Notif>, Error> {
+		let notifs = match self.call(method, params)? {
+			JsonValue::Array(notifs) => notifs,
+			_ => return Err(Error::Custom(format!("MockClient: `{}` must be answered with an array", method))),
+		};
+		let (mut tx, rx) = mpsc::channel(notifs.len());
+		for notif in notifs {
+			tx.try_send(notif).expect("Channel has capacity for all notifications; qed");
+		}
+		Ok(Subscription::new(self.to_back.clone(), rx, SubscriptionKind::Method(method.to_owned())))
+	}
+}
+
+/// Whether the error is raised by the clients rather than sent by the server.
+fn is_client_error(err: &Error) -> bool {
+	matches!(
+		err,
+		Error::Transport(_)
+			| Error::RequestTimeout
+			| Error::RestartNeeded(_)
+			| Error::Internal(_)
+			| Error::MaxSlotsExceeded
+	)
+}
+
+impl Drop for MockClient {
+	fn drop(&mut self) {
+		if !std::thread::panicking() {
+			self.checkpoint();
+		}
+	}
+}
+
+#[cfg(feature = "client")]
+impl PooledClient for MockClient {}
+
+#[async_trait]
+impl Client for MockClient {
+	async fn notification<'a>(&self, method: &'a str, params: Option<ParamsSer<'a>>) -> Result<(), Error> {
+		self.call(method, params).map(|_| ())
+	}
+
+	async fn request<'a, R>(&self, method: &'a str, params: Option<ParamsSer<'a>>) -> Result<R, Error>
+	where
+		R: DeserializeOwned,
+	{
+		serde_json::from_value(self.call(method, params)?).map_err(Into::into)
+	}
+
+	async fn batch_request<'a, R>(&self, batch: Vec<(&'a str, Option<ParamsSer<'a>>)>) -> Result<Vec<R>, Error>
+	where
+		R: DeserializeOwned + Default + Clone,
+	{
+		batch
+			.into_iter()
+			.map(|(method, params)| serde_json::from_value(self.call(method, params)?).map_err(Into::into))
+			.collect()
+	}
+
+	async fn send_batch<'a>(&self, batch: BatchRequestBuilder<'a>) -> Result<BatchResponse, Error> {
+		Ok(BatchResponse::new(
+			batch.id(),
+			batch.into_calls().into_iter().map(|(method, params)| self.call(method, params)).collect(),
+		))
+	}
+}
+
+#[async_trait]
+impl SubscriptionClient for MockClient {
+	async fn subscribe<'a, Notif>(
+		&self,
+		subscribe_method: &'a str,
+		params: Option<ParamsSer<'a>>,
+		_unsubscribe_method: &'a str,
+	) -> Result<Subscription<Notif>, Error>
+	where
+		Notif: DeserializeOwned,
+	{
+		self.subscription(subscribe_method, params)
+	}
+
+	async fn subscribe_to_method<'a, Notif>(&self, method: &'a str) -> Result<Subscription<Notif>, Error>
+	where
+		Notif: DeserializeOwned,
+	{
+		self.subscription(method, None)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures_util::{future::FutureExt, stream::StreamExt};
+
+	// The mock answers synchronously, so none of the futures below are ever pending.
+	#[test]
+	fn mock_client_answers_expectations_in_order() {
+		let client = MockClient::new();
+		client.expect("add", |params| {
+			let (a, b): (u64, u64) = params.parse()?;
+			Ok((a + b).into())
+		});
+		client.expect("add", |_| Err(Error::Custom("overflow".into())));
+		client.expect("ticks", |_| Ok(serde_json::json!([1, 2])));
+		assert_eq!(client.pending(), vec!["add", "add", "ticks"]);
+
+		let params = Some(ParamsSer::Array(vec![1.into(), 2.into()]));
+		let sum = client.request::<u64>("add", params).now_or_never().unwrap();
+		assert_eq!(sum.unwrap(), 3);
+		let overflow = client.request::<u64>("add", None).now_or_never().unwrap();
+		assert!(matches!(overflow, Err(Error::Request(e)) if e.contains("overflow")));
+		let unexpected = client.request::<u64>("add", None).now_or_never().unwrap();
+		assert!(matches!(unexpected, Err(Error::Custom(_))));
+
+		let sub = client.subscribe::<u64>("ticks", None, "unticks").now_or_never().unwrap().unwrap();
+		let notifs: Vec<u64> = sub.map(Result::unwrap).collect::<Vec<_>>().now_or_never().unwrap();
+		assert_eq!(notifs, vec![1, 2]);
+		client.checkpoint();
+	}
+
+	#[test]
+	#[should_panic(expected = "expected calls were not made")]
+	fn mock_client_panics_on_unmet_expectations() {
+		let client = MockClient::new();
+		client.expect("add", |_| Ok(JsonValue::Null));
+	}
+}
@@ -0,0 +1,275 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! [OpenRPC](https://spec.open-rpc.org) service descriptions.
+//!
+//! The document is usually generated by the `rpc` proc macro when the `openrpc` argument is set, and served
+//! by the `rpc.discover` method. Types are described with JSON Schema through [`schemars`], thus all the
+//! parameter, return and subscription item types of the API must implement [`JsonSchema`].
+
+use std::collections::BTreeMap;
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::introspection::is_false;
+
+/// Version of the OpenRPC specification the documents conform to.
+pub const OPENRPC_VERSION: &str = "1.2.6";
+
+/// Name of the method serving the OpenRPC document.
+pub const DISCOVER_METHOD: &str = "rpc.discover";
+
+/// Root of an OpenRPC document.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenRpc {
+	/// Version of the OpenRPC specification.
+	pub openrpc: String,
+	/// Metadata about the API.
+	pub info: Info,
+	/// Methods of the API.
+	pub methods: Vec<Method>,
+	/// Reusable schemas referenced by the methods.
+	#[serde(default, skip_serializing_if = "Components::is_empty")]
+	pub components: Components,
+}
+
+/// Metadata about the API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Info {
+	/// Title of the API.
+	pub title: String,
+	/// Version of the API.
+	pub version: String,
+}
+
+/// Reusable objects of the document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Components {
+	/// Schemas of the named types, referenced as `#/components/schemas/<name>`.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub schemas: BTreeMap<String, Schema>,
+}
+
+impl Components {
+	fn is_empty(&self) -> bool {
+		self.schemas.is_empty()
+	}
+}
+
+/// Description of a method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Method {
+	/// Name of the method, including its namespace.
+	pub name: String,
+	/// Documentation of the method.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub description: Option<String>,
+	/// Parameters of the method, in positional order.
+	pub params: Vec<ContentDescriptor>,
+	/// Result of the method, `None` for notifications and subscriptions.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub result: Option<ContentDescriptor>,
+	/// Whether the method is deprecated.
+	#[serde(default, skip_serializing_if = "is_false")]
+	pub deprecated: bool,
+	/// Whether params are accepted by name, by position or both.
+	pub param_structure: ParamStructure,
+	/// Alternative names the method can be called by.
+	#[serde(default, rename = "x-aliases", skip_serializing_if = "Vec::is_empty")]
+	pub aliases: Vec<String>,
+	/// Notifications sent by the method, if it starts a subscription.
+	#[serde(default, rename = "x-subscription", skip_serializing_if = "Option::is_none")]
+	pub subscription: Option<Subscription>,
+}
+
+/// Whether params are accepted by name, by position or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParamStructure {
+	/// Params must be passed as an object.
+	ByName,
+	/// Params must be passed as an array.
+	ByPosition,
+	/// Params may be passed as an object or as an array.
+	Either,
+}
+
+/// Describes the notifications of a subscription. This is an extension to the OpenRPC specification,
+/// which has no notion of subscriptions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subscription {
+	/// Value of the `method` field of the notifications.
+	pub notification: String,
+	/// Name of the method that terminates the subscription.
+	pub unsubscribe: String,
+	/// Item sent in each notification.
+	pub item: ContentDescriptor,
+}
+
+/// Describes a parameter or a result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentDescriptor {
+	/// Name of the parameter or result.
+	pub name: String,
+	/// Whether the parameter must be provided.
+	#[serde(default, skip_serializing_if = "is_false")]
+	pub required: bool,
+	/// JSON Schema of the value.
+	pub schema: Schema,
+}
+
+/// Builds an [`OpenRpc`] document, collecting the schemas of the named types into its components.
+#[derive(Debug)]
+pub struct OpenRpcBuilder {
+	info: Info,
+	methods: Vec<Method>,
+	schemas: SchemaGenerator,
+}
+
+impl OpenRpcBuilder {
+	/// Create a document for the API `title` at `version`.
+	pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+		let settings = SchemaSettings::draft07().with(|s| {
+			s.definitions_path = "#/components/schemas/".into();
+		});
+
+		Self {
+			info: Info { title: title.into(), version: version.into() },
+			methods: Vec::new(),
+			schemas: settings.into_generator(),
+		}
+	}
+
+	/// Describe a value of type `T`.
+	pub fn content<T: JsonSchema>(&mut self, name: impl Into<String>, required: bool) -> ContentDescriptor {
+		ContentDescriptor { name: name.into(), required, schema: self.schemas.subschema_for::<T>() }
+	}
+
+	/// Describe the fields of `T`, for a type that is the entire params object. Falls back to a single `params`
+	/// descriptor if `T` isn't described as an object with properties.
+	pub fn flattened_content<T: JsonSchema>(&mut self) -> Vec<ContentDescriptor> {
+		let schema = self.schemas.subschema_for::<T>();
+		let object = match &schema {
+			Schema::Object(SchemaObject { reference: Some(reference), .. }) => reference
+				.strip_prefix(&self.schemas.settings().definitions_path)
+				.and_then(|name| self.schemas.definitions().get(name)),
+			_ => Some(&schema),
+		};
+
+		match object {
+			Some(Schema::Object(SchemaObject { object: Some(object), .. })) => object
+				.properties
+				.iter()
+				.map(|(name, schema)| ContentDescriptor {
+					name: name.clone(),
+					required: object.required.contains(name),
+					schema: schema.clone(),
+				})
+				.collect(),
+			_ => vec![ContentDescriptor { name: "params".into(), required: true, schema }],
+		}
+	}
+
+	/// Add a method to the document.
+	pub fn method(&mut self, method: Method) {
+		self.methods.push(method);
+	}
+
+	/// Build the document.
+	pub fn build(mut self) -> OpenRpc {
+		let schemas = self.schemas.take_definitions().into_iter().collect();
+
+		OpenRpc {
+			openrpc: OPENRPC_VERSION.into(),
+			info: self.info,
+			methods: self.methods,
+			components: Components { schemas },
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[derive(JsonSchema)]
+	#[allow(dead_code)]
+	struct Block {
+		number: u64,
+		hash: String,
+	}
+
+	#[test]
+	fn document_works() {
+		let mut builder = OpenRpcBuilder::new("Chain", "1.0.0");
+		let params = vec![builder.content::<u64>("number", true), builder.content::<Option<bool>>("full", false)];
+		let result = Some(builder.content::<Block>("result", true));
+		builder.method(Method {
+			name: "chain_getBlock".into(),
+			description: Some("Returns a block.".into()),
+			params,
+			result,
+			deprecated: false,
+			param_structure: ParamStructure::Either,
+			aliases: vec!["chain_block".into()],
+			subscription: None,
+		});
+
+		let doc = serde_json::to_value(builder.build()).unwrap();
+
+		assert_eq!(doc["openrpc"], OPENRPC_VERSION);
+		assert_eq!(doc["info"], json!({ "title": "Chain", "version": "1.0.0" }));
+		let method = &doc["methods"][0];
+		assert_eq!(method["name"], "chain_getBlock");
+		assert_eq!(method["paramStructure"], "either");
+		assert_eq!(method["x-aliases"], json!(["chain_block"]));
+		assert_eq!(method["params"][0]["required"], true);
+		assert!(method["params"][1].get("required").is_none());
+		assert!(method.get("deprecated").is_none());
+		assert_eq!(method["result"]["schema"], json!({ "$ref": "#/components/schemas/Block" }));
+		assert_eq!(doc["components"]["schemas"]["Block"]["required"], json!(["hash", "number"]));
+	}
+
+	#[test]
+	fn flattened_content_works() {
+		let mut builder = OpenRpcBuilder::new("Chain", "1.0.0");
+		let params = builder.flattened_content::<Block>();
+
+		assert_eq!(params.len(), 2);
+		assert_eq!(params[0].name, "hash");
+		assert!(params[0].required);
+		assert_eq!(serde_json::to_value(&params[1].schema).unwrap()["type"], "integer");
+
+		let params = builder.flattened_content::<u64>();
+		assert_eq!(params.len(), 1);
+		assert_eq!(params[0].name, "params");
+	}
+}
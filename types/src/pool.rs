@@ -0,0 +1,557 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::batch::{BatchRequestBuilder, BatchResponse};
+use crate::traits::{Client, SubscriptionClient};
+use crate::v2::ParamsSer;
+use crate::{Error, Subscription};
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use futures_util::lock::Mutex as AsyncMutex;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Opens the connection to an endpoint, given its URL.
+type Connect<C> = Arc<dyn Fn(String) -> BoxFuture<'static, Result<C, Error>> + Send + Sync>;
+
+/// How [`ClientPool`] picks the endpoint of a call among the healthy ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Balance {
+	/// Use each endpoint in turn.
+	RoundRobin,
+	/// Use the endpoint with the least calls in flight, in turn if several have as many.
+	LeastInFlight,
+}
+
+/// Client which can be pooled by a [`ClientPool`], telling the errors caused by its endpoint apart from the others.
+pub trait PooledClient {
+	/// Whether a call failed because of its endpoint rather than of the call itself, in which case the endpoint
+	/// is considered unhealthy and the call is failed over to another one.
+	///
+	/// See [`is_endpoint_failure`] for the default.
+	fn is_endpoint_failure(err: &Error) -> bool {
+		is_endpoint_failure(err)
+	}
+}
+
+/// Whether a call failed because of its endpoint: it timed out, the connection was lost, or the transport error
+/// is caused by an I/O, an HTTP or a WebSocket error which is not a misuse of the client.
+///
+/// Client-side transport errors, such as a request too large, are not counted.
+pub fn is_endpoint_failure(err: &Error) -> bool {
+	match err {
+		Error::RequestTimeout | Error::RestartNeeded(_) | Error::Internal(_) => true,
+		Error::Transport(err) => err.chain().any(|err| {
+			err.is::<std::io::Error>()
+				|| err.is::<soketto::handshake::Error>()
+				|| err.is::<soketto::connection::Error>()
+				|| matches!(err.downcast_ref::<hyper::Error>(), Some(err) if !err.is_user())
+		}),
+		_ => false,
+	}
+}
+
+/// Builder for [`ClientPool`].
+#[derive(Debug, Clone)]
+pub struct ClientPoolBuilder {
+	balance: Balance,
+	max_failures: u32,
+	ejection_time: Duration,
+	failover_timeouts: bool,
+}
+
+impl Default for ClientPoolBuilder {
+	fn default() -> Self {
+		Self {
+			balance: Balance::RoundRobin,
+			max_failures: 3,
+			ejection_time: Duration::from_secs(30),
+			failover_timeouts: false,
+		}
+	}
+}
+
+impl ClientPoolBuilder {
+	/// Set how the endpoint of a call is picked (default is [`Balance::RoundRobin`]).
+	pub fn balance(mut self, balance: Balance) -> Self {
+		self.balance = balance;
+		self
+	}
+
+	/// Set the number of consecutive failed calls after which an endpoint is ejected (default is 3).
+	pub fn max_failures(mut self, max_failures: u32) -> Self {
+		self.max_failures = max_failures.max(1);
+		self
+	}
+
+	/// Set for how long an ejected endpoint is not used (default is 30 seconds).
+	pub fn ejection_time(mut self, ejection_time: Duration) -> Self {
+		self.ejection_time = ejection_time;
+		self
+	}
+
+	/// Set whether a call which timed out is failed over to another endpoint (default is false).
+	///
+	/// The endpoint may still have executed a call which timed out, so this must only be enabled if the calls are
+	/// idempotent.
+	pub fn failover_timeouts(mut self, failover_timeouts: bool) -> Self {
+		self.failover_timeouts = failover_timeouts;
+		self
+	}
+
+	/// Build a pool over already connected clients, given with the URL of their endpoint.
+	pub fn build<C>(self, clients: impl IntoIterator<Item = (String, C)>) -> ClientPool<C> {
+		let endpoints = clients.into_iter().map(|(url, client)| Endpoint::new(url, Some(client))).collect();
+		ClientPool { config: self, endpoints, next: AtomicUsize::new(0), connect: None }
+	}
+
+	/// Build a pool over `urls`, connecting to an endpoint with `connect` when it is first used and again after it
+	/// was ejected.
+	///
+	/// This is how clients whose connection can't recover, such as WebSocket clients, are pooled.
+	pub fn connect_with<C, F, Fut>(self, urls: impl IntoIterator<Item = String>, connect: F) -> ClientPool<C>
+	where
+		F: Fn(String) -> Fut + Send + Sync + 'static,
+		Fut: Future<Output = Result<C, Error>> + Send + 'static,
+	{
+		let endpoints = urls.into_iter().map(|url| Endpoint::new(url, None)).collect();
+		let connect: Connect<C> = Arc::new(move |url| Box::pin(connect(url)));
+		ClientPool { config: self, endpoints, next: AtomicUsize::new(0), connect: Some(connect) }
+	}
+}
+
+/// Statistics of an endpoint of a [`ClientPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStats {
+	/// URL of the endpoint.
+	pub url: String,
+	/// Number of calls in flight.
+	pub in_flight: usize,
+	/// Number of successful calls, including the ones answered with a JSON-RPC error.
+	pub successes: u64,
+	/// Number of calls which failed because of the endpoint, including timeouts.
+	pub failures: u64,
+	/// Number of calls which timed out.
+	pub timeouts: u64,
+	/// Whether the endpoint is currently ejected.
+	pub ejected: bool,
+}
+
+/// Mutable state of an endpoint.
+#[derive(Debug)]
+struct Health {
+	consecutive_failures: u32,
+	ejected_until: Option<Instant>,
+}
+
+struct Endpoint<C> {
+	url: String,
+	client: AsyncMutex<Option<Arc<C>>>,
+	health: Mutex<Health>,
+	in_flight: AtomicUsize,
+	successes: AtomicU64,
+	failures: AtomicU64,
+	timeouts: AtomicU64,
+}
+
+impl<C> Endpoint<C> {
+	fn new(url: String, client: Option<C>) -> Self {
+		Self {
+			url,
+			client: AsyncMutex::new(client.map(Arc::new)),
+			health: Mutex::new(Health { consecutive_failures: 0, ejected_until: None }),
+			in_flight: AtomicUsize::new(0),
+			successes: AtomicU64::new(0),
+			failures: AtomicU64::new(0),
+			timeouts: AtomicU64::new(0),
+		}
+	}
+
+	fn is_ejected(&self, now: Instant) -> bool {
+		let health = self.health.lock().expect("lock poisoned; qed");
+		matches!(health.ejected_until, Some(until) if until > now)
+	}
+}
+
+/// Decrements the calls in flight of an endpoint when dropped, the call future may be dropped before completion.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+/// Client load-balancing the calls between several equivalent endpoints.
+///
+/// A call which fails because of its endpoint, see [`PooledClient::is_endpoint_failure`], is failed over to the next
+/// endpoint until all of them were tried. The error of the last one is returned. A call which timed out may still
+/// have been executed by the endpoint, so it is only failed over if [`ClientPoolBuilder::failover_timeouts`] is
+/// enabled.
+///
+/// An endpoint failing [`ClientPoolBuilder::max_failures`] calls in a row is ejected for
+/// [`ClientPoolBuilder::ejection_time`], after which it is tried again. If all the endpoints are ejected, they are
+/// all tried rather than failing the call right away.
+pub struct ClientPool<C> {
+	config: ClientPoolBuilder,
+	endpoints: Vec<Endpoint<C>>,
+	/// Round-robin counter.
+	next: AtomicUsize,
+	connect: Option<Connect<C>>,
+}
+
+impl<C> std::fmt::Debug for ClientPool<C> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ClientPool").field("config", &self.config).field("endpoints", &self.stats()).finish()
+	}
+}
+
+impl<C> ClientPool<C> {
+	/// Statistics of the endpoints, in the order they were given.
+	pub fn stats(&self) -> Vec<EndpointStats> {
+		let now = Instant::now();
+		self.endpoints
+			.iter()
+			.map(|endpoint| EndpointStats {
+				url: endpoint.url.clone(),
+				in_flight: endpoint.in_flight.load(Ordering::SeqCst),
+				successes: endpoint.successes.load(Ordering::SeqCst),
+				failures: endpoint.failures.load(Ordering::SeqCst),
+				timeouts: endpoint.timeouts.load(Ordering::SeqCst),
+				ejected: endpoint.is_ejected(now),
+			})
+			.collect()
+	}
+
+	/// Pick the endpoint of the next attempt of a call, among the ones not `tried` yet.
+	fn pick(&self, tried: &[usize]) -> Option<usize> {
+		let now = Instant::now();
+		let len = self.endpoints.len();
+		let start = self.next.fetch_add(1, Ordering::Relaxed);
+		let untried = || (0..len).map(move |i| (start + i) % len).filter(|idx| !tried.contains(idx));
+
+		let mut healthy = untried().filter(|&idx| !self.endpoints[idx].is_ejected(now));
+		let picked = match self.config.balance {
+			Balance::RoundRobin => healthy.next(),
+			Balance::LeastInFlight => healthy.min_by_key(|&idx| self.endpoints[idx].in_flight.load(Ordering::SeqCst)),
+		};
+		picked.or_else(|| untried().next())
+	}
+
+	async fn client(&self, endpoint: &Endpoint<C>) -> Result<Arc<C>, Error> {
+		let mut client = endpoint.client.lock().await;
+		if let Some(client) = &*client {
+			return Ok(client.clone());
+		}
+		let connect = self.connect.as_ref().ok_or_else(|| Error::Custom("Endpoint without client".into()))?;
+		let connected = Arc::new(connect(endpoint.url.clone()).await?);
+		*client = Some(connected.clone());
+		Ok(connected)
+	}
+
+	fn record_success(&self, endpoint: &Endpoint<C>) {
+		endpoint.successes.fetch_add(1, Ordering::SeqCst);
+		endpoint.health.lock().expect("lock poisoned; qed").consecutive_failures = 0;
+	}
+
+	/// Record a call which failed because of `endpoint`, returns whether its connection must be opened again.
+	fn record_failure(&self, endpoint: &Endpoint<C>, err: &Error) -> bool {
+		endpoint.failures.fetch_add(1, Ordering::SeqCst);
+		if matches!(err, Error::RequestTimeout) {
+			endpoint.timeouts.fetch_add(1, Ordering::SeqCst);
+		}
+
+		let mut health = endpoint.health.lock().expect("lock poisoned; qed");
+		health.consecutive_failures += 1;
+		let ejected = health.consecutive_failures >= self.config.max_failures;
+		if ejected {
+			tracing::warn!("Ejecting endpoint {} after {} failures", endpoint.url, health.consecutive_failures);
+			health.consecutive_failures = 0;
+			health.ejected_until = Some(Instant::now() + self.config.ejection_time);
+		}
+		(ejected || matches!(err, Error::RestartNeeded(_))) && self.connect.is_some()
+	}
+
+	/// Run `call` on the endpoints until one does not fail because of the endpoint.
+	async fn call<T, F, Fut>(&self, call: F) -> Result<T, Error>
+	where
+		C: PooledClient,
+		F: Fn(Arc<C>) -> Fut,
+		Fut: Future<Output = Result<T, Error>>,
+	{
+		let mut tried = Vec::with_capacity(self.endpoints.len());
+		let mut last_err = None;
+
+		while let Some(idx) = self.pick(&tried) {
+			tried.push(idx);
+			let endpoint = &self.endpoints[idx];
+
+			// Scoped so that the result, which may not be `Send`, is not held across the await below.
+			let err = {
+				endpoint.in_flight.fetch_add(1, Ordering::SeqCst);
+				let in_flight = InFlight(&endpoint.in_flight);
+				let res = match self.client(endpoint).await {
+					Ok(client) => call(client).await,
+					Err(err) => Err(err),
+				};
+				drop(in_flight);
+
+				match res {
+					Err(err) if C::is_endpoint_failure(&err) => err,
+					res => {
+						self.record_success(endpoint);
+						return res;
+					}
+				}
+			};
+			tracing::debug!("Call to endpoint {} failed: {:?}", endpoint.url, err);
+			if self.record_failure(endpoint, &err) {
+				// The connection is opened again when the endpoint is used next.
+				*endpoint.client.lock().await = None;
+			}
+			if matches!(err, Error::RequestTimeout) && !self.config.failover_timeouts {
+				return Err(err);
+			}
+			last_err = Some(err);
+		}
+
+		Err(last_err.unwrap_or_else(|| Error::Custom("No endpoint in the pool".into())))
+	}
+}
+
+#[async_trait]
+impl<C> Client for ClientPool<C>
+where
+	C: Client + PooledClient + Send + Sync,
+{
+	async fn notification<'a>(&self, method: &'a str, params: Option<ParamsSer<'a>>) -> Result<(), Error> {
+		self.call(|client| {
+			let params = params.clone();
+			async move { client.notification(method, params).await }
+		})
+		.await
+	}
+
+	async fn request<'a, R>(&self, method: &'a str, params: Option<ParamsSer<'a>>) -> Result<R, Error>
+	where
+		R: DeserializeOwned,
+	{
+		self.call(|client| {
+			let params = params.clone();
+			async move { client.request(method, params).await }
+		})
+		.await
+	}
+
+	async fn batch_request<'a, R>(&self, batch: Vec<(&'a str, Option<ParamsSer<'a>>)>) -> Result<Vec<R>, Error>
+	where
+		R: DeserializeOwned + Default + Clone,
+	{
+		self.call(|client| {
+			let batch = batch.clone();
+			async move { client.batch_request(batch).await }
+		})
+		.await
+	}
+
+	async fn send_batch<'a>(&self, batch: BatchRequestBuilder<'a>) -> Result<BatchResponse, Error> {
+		self.call(|client| {
+			let batch = batch.clone();
+			async move { client.send_batch(batch).await }
+		})
+		.await
+	}
+}
+
+#[async_trait]
+impl<C> SubscriptionClient for ClientPool<C>
+where
+	C: SubscriptionClient + PooledClient + Send + Sync,
+{
+	async fn subscribe<'a, Notif>(
+		&self,
+		subscribe_method: &'a str,
+		params: Option<ParamsSer<'a>>,
+		unsubscribe_method: &'a str,
+	) -> Result<Subscription<Notif>, Error>
+	where
+		Notif: DeserializeOwned,
+	{
+		self.call(|client| {
+			let params = params.clone();
+			async move { client.subscribe(subscribe_method, params, unsubscribe_method).await }
+		})
+		.await
+	}
+
+	async fn subscribe_to_method<'a, Notif>(&self, method: &'a str) -> Result<Subscription<Notif>, Error>
+	where
+		Notif: DeserializeOwned,
+	{
+		self.call(|client| async move { client.subscribe_to_method(method).await }).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::MockClient;
+	use futures_util::future::FutureExt;
+	use serde_json::Value as JsonValue;
+
+	fn transport_error() -> Error {
+		Error::Transport(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+	}
+
+	fn request(pool: &ClientPool<MockClient>) -> Result<String, Error> {
+		// The mock answers synchronously.
+		pool.request::<String>("hello", None).now_or_never().unwrap()
+	}
+
+	fn pool(clients: Vec<MockClient>, builder: ClientPoolBuilder) -> ClientPool<MockClient> {
+		builder.build(clients.into_iter().enumerate().map(|(i, client)| (format!("mock-{}", i), client)))
+	}
+
+	#[test]
+	fn round_robin_works() {
+		let clients: Vec<_> = (0..2)
+			.map(|i| {
+				let client = MockClient::new();
+				for _ in 0..2 {
+					client.expect("hello", move |_| Ok(format!("mock-{}", i).into()));
+				}
+				client
+			})
+			.collect();
+		let pool = pool(clients, ClientPoolBuilder::default());
+
+		let answers: Vec<_> = (0..4).map(|_| request(&pool).unwrap()).collect();
+		assert_eq!(answers, vec!["mock-0", "mock-1", "mock-0", "mock-1"]);
+	}
+
+	#[test]
+	fn failover_and_ejection_works() {
+		let failing = MockClient::new();
+		failing.expect("hello", |_| Err(transport_error()));
+		failing.expect("hello", |_| Err(Error::RequestTimeout));
+		let healthy = MockClient::new();
+		for _ in 0..3 {
+			healthy.expect("hello", |_| Ok("hello".into()));
+		}
+		let pool = pool(vec![failing, healthy], ClientPoolBuilder::default().max_failures(2).failover_timeouts(true));
+
+		for _ in 0..3 {
+			assert_eq!(request(&pool).unwrap(), "hello");
+		}
+		let stats = pool.stats();
+		assert_eq!((stats[0].failures, stats[0].timeouts, stats[0].ejected), (2, 1, true));
+		assert_eq!((stats[1].successes, stats[1].failures, stats[1].ejected), (3, 0, false));
+	}
+
+	#[test]
+	fn call_errors_are_not_failed_over() {
+		let client = MockClient::new();
+		client.expect("hello", |_| Err(Error::Custom("invalid params".into())));
+		let other = MockClient::new();
+		let pool = pool(vec![client, other], ClientPoolBuilder::default());
+
+		assert!(matches!(request(&pool), Err(Error::Request(_))));
+		assert_eq!(pool.stats()[0].successes, 1);
+	}
+
+	#[test]
+	fn client_side_transport_errors_are_not_failed_over() {
+		let client = MockClient::new();
+		client.expect("hello", |_| Err(Error::Transport(anyhow::anyhow!("The request body was too large"))));
+		let other = MockClient::new();
+		let pool = pool(vec![client, other], ClientPoolBuilder::default());
+
+		assert!(matches!(request(&pool), Err(Error::Transport(_))));
+		assert_eq!((pool.stats()[0].successes, pool.stats()[0].failures), (1, 0));
+	}
+
+	#[test]
+	fn timeouts_are_not_failed_over_by_default() {
+		let client = MockClient::new();
+		client.expect("hello", |_| Err(Error::RequestTimeout));
+		let other = MockClient::new();
+		let pool = pool(vec![client, other], ClientPoolBuilder::default());
+
+		assert!(matches!(request(&pool), Err(Error::RequestTimeout)));
+		assert_eq!((pool.stats()[0].failures, pool.stats()[0].timeouts), (1, 1));
+	}
+
+	#[test]
+	fn least_in_flight_prefers_idle_endpoints() {
+		let busy = MockClient::new();
+		let idle = MockClient::new();
+		idle.expect("hello", |_| Ok("idle".into()));
+		let pool = pool(vec![busy, idle], ClientPoolBuilder::default().balance(Balance::LeastInFlight));
+
+		pool.endpoints[0].in_flight.store(3, Ordering::SeqCst);
+		assert_eq!(request(&pool).unwrap(), "idle");
+	}
+
+	#[test]
+	fn all_endpoints_failing_returns_last_error() {
+		let clients: Vec<_> = (0..2)
+			.map(|_| {
+				let client = MockClient::new();
+				client.expect("hello", |_| Err(Error::RequestTimeout));
+				client
+			})
+			.collect();
+		let pool = pool(clients, ClientPoolBuilder::default().failover_timeouts(true));
+
+		assert!(matches!(request(&pool), Err(Error::RequestTimeout)));
+		assert!(pool.stats().iter().all(|stats| stats.timeouts == 1));
+	}
+
+	#[test]
+	fn connect_with_reconnects_after_failures() {
+		let connects = Arc::new(AtomicUsize::new(0));
+		let count = connects.clone();
+		let pool = ClientPoolBuilder::default().max_failures(1).ejection_time(Duration::from_secs(0)).connect_with(
+			vec!["mock".to_owned()],
+			move |_url| {
+				let client = MockClient::new();
+				if count.fetch_add(1, Ordering::SeqCst) == 0 {
+					client.expect("hello", |_| Err(Error::RestartNeeded("connection closed".into())));
+				} else {
+					client.expect("hello", |_| Ok(JsonValue::from("reconnected")));
+				}
+				async move { Ok(client) }
+			},
+		);
+
+		assert!(matches!(request(&pool), Err(Error::RestartNeeded(_))));
+		assert_eq!(request(&pool).unwrap(), "reconnected");
+		assert_eq!(connects.load(Ordering::SeqCst), 2);
+	}
+}
@@ -0,0 +1,263 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::middleware::ClientRequest;
+use crate::v2::{Id, NotificationSer, ParamsSer, RequestSer, Response, RpcError, TwoPointZero};
+use crate::Error;
+use beef::Cow;
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+/// Call of a [`RawMessage`], a request if it has an ID, even `null`, or a notification otherwise.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RawCall<'a> {
+	#[allow(dead_code)]
+	jsonrpc: TwoPointZero,
+	#[serde(borrow)]
+	method: Cow<'a, str>,
+	#[serde(borrow)]
+	params: Option<&'a RawValue>,
+	#[serde(borrow, default, deserialize_with = "present_id")]
+	id: Option<Id<'a>>,
+}
+
+/// Deserialize the ID of a call which has one, so that an `"id": null` member isn't taken for a missing ID.
+fn present_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Id<'de>>, D::Error> {
+	Id::deserialize(deserializer).map(Some)
+}
+
+/// Already serialized JSON-RPC message forwarded by a client: a request, a notification or a batch of them.
+///
+/// The client sends the message with the IDs of its own requests in place of the IDs of the message, which are
+/// restored in the response. The calls of the message go through the middleware of the client, see
+/// [`RawMessage::calls`].
+#[derive(Debug)]
+pub struct RawMessage<'a> {
+	calls: Vec<RawCall<'a>>,
+	batch: bool,
+}
+
+impl<'a> RawMessage<'a> {
+	/// Parse the message.
+	pub fn parse(message: &'a str) -> Result<Self, Error> {
+		if message.trim_start().starts_with('[') {
+			let calls: Vec<RawCall> = serde_json::from_str(message)?;
+			if calls.is_empty() {
+				return Err(Error::Custom("Empty batch request".into()));
+			}
+			Ok(Self { calls, batch: true })
+		} else {
+			Ok(Self { calls: vec![serde_json::from_str(message)?], batch: false })
+		}
+	}
+
+	/// Whether the message is a batch.
+	pub fn is_batch(&self) -> bool {
+		self.batch
+	}
+
+	/// Number of requests of the message, the calls which are answered.
+	pub fn num_requests(&self) -> usize {
+		self.calls.iter().filter(|call| call.id.is_some()).count()
+	}
+
+	/// The calls of the message, to pass through the middleware of the client, with their params as they are.
+	pub fn calls(&self) -> Vec<ClientRequest<'_>> {
+		self.calls.iter().map(|call| ClientRequest::new(&call.method, call.params.map(ParamsSer::Raw))).collect()
+	}
+
+	/// Serialize the `calls` of the message, which may have been modified by the middleware, with the `ids` of the
+	/// client in place of the IDs of its requests, in order.
+	pub fn serialize(&self, calls: &[ClientRequest<'_>], ids: &[u64]) -> Result<String, Error> {
+		let mut ids = ids.iter();
+		let mut serialized = Vec::with_capacity(calls.len());
+		for (call, request) in self.calls.iter().zip(calls) {
+			let call = match call.id {
+				Some(_) => {
+					let id = *ids.next().ok_or(Error::InvalidRequestId)?;
+					serde_json::to_string(&RequestSer::new(Id::Number(id), &request.method, request.params()))?
+				}
+				None => serde_json::to_string(&NotificationSer::new(&request.method, request.params()))?,
+			};
+			serialized.push(call);
+		}
+		Ok(if self.batch { format!("[{}]", serialized.join(",")) } else { serialized.remove(0) })
+	}
+
+	/// The result of each call of the message, from the `responses` to its requests sent with the `ids`, which are
+	/// either the result of the request or the [`Error::Request`] with its error object.
+	///
+	/// Notifications have no result, and requests which weren't answered fail with [`Error::InvalidRequestId`].
+	pub fn call_results(
+		&self,
+		ids: &[u64],
+		mut responses: Vec<(u64, Result<Box<RawValue>, Error>)>,
+	) -> Vec<Result<Option<Box<RawValue>>, Error>> {
+		let mut ids = ids.iter();
+		self.calls
+			.iter()
+			.map(|call| {
+				if call.id.is_none() {
+					return Ok(None);
+				}
+				let id = ids.next().ok_or(Error::InvalidRequestId)?;
+				let pos = responses.iter().position(|(response_id, _)| response_id == id);
+				let pos = pos.ok_or(Error::InvalidRequestId)?;
+				responses.swap_remove(pos).1.map(Some)
+			})
+			.collect()
+	}
+
+	/// Build the response to the message from the result of each of its calls, see [`RawMessage::call_results`].
+	/// Returns `None` if the message has no request.
+	///
+	/// Fails if a request failed with any other error than [`Error::Request`].
+	pub fn into_response(
+		self,
+		results: Vec<Result<Option<Box<RawValue>>, Error>>,
+	) -> Result<Option<Box<RawValue>>, Error> {
+		let mut entries = Vec::with_capacity(results.len());
+		for (call, result) in self.calls.into_iter().zip(results) {
+			let request_id = match call.id {
+				Some(id) => id,
+				None => continue,
+			};
+			let entry = match result {
+				Ok(Some(result)) => serde_json::to_string(&Response::new(result, request_id))?,
+				Ok(None) => return Err(Error::InvalidRequestId),
+				Err(Error::Request(raw)) => {
+					let mut err: RpcError = serde_json::from_str(&raw)?;
+					err.id = request_id;
+					serde_json::to_string(&err)?
+				}
+				Err(err) => return Err(err),
+			};
+			entries.push(entry);
+		}
+
+		let response = match entries.len() {
+			0 => return Ok(None),
+			_ if self.batch => format!("[{}]", entries.join(",")),
+			_ => entries.remove(0),
+		};
+		RawValue::from_string(response).map(Some).map_err(Error::ParseError)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::RawMessage;
+	use crate::v2::ParamsSer;
+	use crate::Error;
+	use serde_json::value::RawValue;
+
+	fn raw(json: &str) -> Box<RawValue> {
+		RawValue::from_string(json.into()).unwrap()
+	}
+
+	#[test]
+	fn rewrites_ids_of_requests() {
+		let message = RawMessage::parse(r#"{"jsonrpc":"2.0","method":"say_hello","params":[1, 2],"id":"a"}"#).unwrap();
+		assert!(!message.is_batch());
+		assert_eq!(message.num_requests(), 1);
+		assert_eq!(
+			message.serialize(&message.calls(), &[7]).unwrap(),
+			r#"{"jsonrpc":"2.0","id":7,"method":"say_hello","params":[1, 2]}"#
+		);
+
+		let results = message.call_results(&[7], vec![(7, Ok(raw(r#""hello""#)))]);
+		let response = message.into_response(results).unwrap().unwrap();
+		assert_eq!(response.get(), r#"{"jsonrpc":"2.0","result":"hello","id":"a"}"#);
+	}
+
+	#[test]
+	fn batch_with_notifications_and_errors() {
+		let message = RawMessage::parse(
+			r#"[
+				{"jsonrpc":"2.0","method":"say_hello","id":1},
+				{"jsonrpc":"2.0","method":"notif","params":{"a":1}},
+				{"jsonrpc":"2.0","method":"missing","id":1}
+			]"#,
+		)
+		.unwrap();
+		assert!(message.is_batch());
+		assert_eq!(message.num_requests(), 2);
+		assert_eq!(
+			message.serialize(&message.calls(), &[3, 4]).unwrap(),
+			r#"[{"jsonrpc":"2.0","id":3,"method":"say_hello"},{"jsonrpc":"2.0","method":"notif","params":{"a":1}},{"jsonrpc":"2.0","id":4,"method":"missing"}]"#
+		);
+
+		let err = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":4}"#;
+		let responses = vec![(4, Err(Error::Request(err.into()))), (3, Ok(raw(r#""hello""#)))];
+		let results = message.call_results(&[3, 4], responses);
+		let response = message.into_response(results).unwrap().unwrap();
+		assert_eq!(
+			response.get(),
+			r#"[{"jsonrpc":"2.0","result":"hello","id":1},{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}]"#
+		);
+	}
+
+	#[test]
+	fn notifications_have_no_response() {
+		let message = RawMessage::parse(r#"{"jsonrpc":"2.0","method":"notif"}"#).unwrap();
+		assert_eq!(message.num_requests(), 0);
+		let results = message.call_results(&[], Vec::new());
+		assert!(message.into_response(results).unwrap().is_none());
+	}
+
+	#[test]
+	fn null_id_is_a_request() {
+		let message = RawMessage::parse(r#"{"jsonrpc":"2.0","method":"say_hello","id":null}"#).unwrap();
+		assert_eq!(message.num_requests(), 1);
+
+		let results = message.call_results(&[5], vec![(5, Ok(raw("1")))]);
+		let response = message.into_response(results).unwrap().unwrap();
+		assert_eq!(response.get(), r#"{"jsonrpc":"2.0","result":1,"id":null}"#);
+	}
+
+	#[test]
+	fn modified_calls_are_serialized() {
+		let message = RawMessage::parse(r#"{"jsonrpc":"2.0","method":"say_hello","params":[1],"id":1}"#).unwrap();
+		let mut calls = message.calls();
+		calls[0].method = "say_hi".into();
+		calls[0].params = Some(ParamsSer::Array(vec![2.into()]));
+		assert_eq!(
+			message.serialize(&calls, &[3]).unwrap(),
+			r#"{"jsonrpc":"2.0","id":3,"method":"say_hi","params":[2]}"#
+		);
+	}
+
+	#[test]
+	fn unanswered_requests_fail() {
+		let message = RawMessage::parse(r#"{"jsonrpc":"2.0","method":"say_hello","id":1}"#).unwrap();
+		let results = message.call_results(&[3], Vec::new());
+		assert!(matches!(results[0], Err(Error::InvalidRequestId)));
+		assert!(matches!(message.into_response(results), Err(Error::InvalidRequestId)));
+		assert!(RawMessage::parse("[]").is_err());
+		assert!(RawMessage::parse(r#"{"method":"say_hello","id":1}"#).is_err());
+	}
+}
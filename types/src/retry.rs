@@ -0,0 +1,269 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::middleware::{ClientMiddleware, ClientRequest};
+use crate::v2::RpcError;
+use crate::{CallError, Error};
+use rand::Rng;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Class of errors a [`RetryPolicy`] retries the calls on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryOn {
+	/// Networking or low-level protocol errors, including the error statuses of HTTP responses such as
+	/// `503 Service Unavailable`.
+	Transport,
+	/// An attempt timed out, see [`RetryPolicy::attempt_timeout`].
+	Timeout,
+	/// The server answered with a JSON-RPC error object with this code.
+	Code(i32),
+}
+
+/// Policy retrying the calls of a client that failed with a transient error.
+///
+/// A call may have reached the server before it failed, so only the methods that are safe to call again are
+/// retried: those of the allow-list set with [`RetryPolicy::retry_method`], or all of them after
+/// [`RetryPolicy::retry_all_methods`]. A batch is retried only if all of its methods are.
+///
+/// The failed attempts are retried after an exponential backoff with jitter, for as long as the request timeout of
+/// the client isn't reached: it bounds the whole call, including the retries and the backoff between them.
+///
+/// The policy is a [`ClientMiddleware`], applied by the clients along with their own middleware which may retry
+/// the calls as well.
+///
+/// ```
+/// use jsonrpsee_types::retry::{RetryOn, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new(5)
+///     .backoff(Duration::from_millis(50), Duration::from_secs(2))
+///     .retry_on([RetryOn::Transport, RetryOn::Code(-32005)])
+///     .retry_method("state_getStorage")
+///     .retry_method("chain_getBlockHash");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+	max_attempts: u32,
+	initial_backoff: Duration,
+	max_backoff: Duration,
+	jitter: bool,
+	attempt_timeout: Option<Duration>,
+	retry_on: HashSet<RetryOn>,
+	/// Methods safe to retry, `None` if all of them are.
+	methods: Option<HashSet<String>>,
+}
+
+impl Default for RetryPolicy {
+	/// Policy never retrying the calls.
+	fn default() -> Self {
+		Self::new(1)
+	}
+}
+
+impl RetryPolicy {
+	/// Create a policy making up to `max_attempts` attempts of each call, including the first one.
+	///
+	/// It retries on [`RetryOn::Transport`] and [`RetryOn::Timeout`] errors, after a backoff starting at 100
+	/// milliseconds and capped to 10 seconds, but no method until they are allowed.
+	pub fn new(max_attempts: u32) -> Self {
+		Self {
+			max_attempts: max_attempts.max(1),
+			initial_backoff: Duration::from_millis(100),
+			max_backoff: Duration::from_secs(10),
+			jitter: true,
+			attempt_timeout: None,
+			retry_on: vec![RetryOn::Transport, RetryOn::Timeout].into_iter().collect(),
+			methods: Some(HashSet::new()),
+		}
+	}
+
+	/// Set the backoff before the first retry, doubled before each of the next ones up to `max`.
+	pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+		self.initial_backoff = initial;
+		self.max_backoff = max.max(initial);
+		self
+	}
+
+	/// Set whether the backoff is randomized, between half and all of it (default is `true`). It keeps the clients
+	/// which failed together from retrying together.
+	pub fn jitter(mut self, jitter: bool) -> Self {
+		self.jitter = jitter;
+		self
+	}
+
+	/// Set the timeout of each attempt, within the request timeout of the client bounding the whole call (default is
+	/// none, an attempt may take the whole request timeout).
+	pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+		self.attempt_timeout = Some(timeout);
+		self
+	}
+
+	/// Set the classes of errors the calls are retried on, replacing the default ones.
+	pub fn retry_on(mut self, retry_on: impl IntoIterator<Item = RetryOn>) -> Self {
+		self.retry_on = retry_on.into_iter().collect();
+		self
+	}
+
+	/// Allow the calls to `method` to be retried.
+	pub fn retry_method(mut self, method: impl Into<String>) -> Self {
+		if let Some(methods) = &mut self.methods {
+			methods.insert(method.into());
+		}
+		self
+	}
+
+	/// Allow the calls to all methods to be retried, for servers whose methods are all idempotent.
+	pub fn retry_all_methods(mut self) -> Self {
+		self.methods = None;
+		self
+	}
+
+	/// Maximum number of attempts of a call, including the first one.
+	pub fn max_attempts(&self) -> u32 {
+		self.max_attempts
+	}
+
+	/// Returns whether the `requests` sent together may be sent again after their `attempt` failed with `error`.
+	/// `attempt` starts at 1 for the first attempt.
+	pub fn should_retry(&self, requests: &[ClientRequest<'_>], error: &Error, attempt: u32) -> bool {
+		attempt < self.max_attempts
+			&& !requests.is_empty()
+			&& self.is_retryable(error)
+			&& requests.iter().all(|request| self.is_allowed(&request.method))
+	}
+
+	/// Backoff before retrying a call whose `attempt` failed, starting at 1 for the first attempt.
+	pub fn backoff_after(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+		let backoff = self.initial_backoff.checked_mul(factor).map_or(self.max_backoff, |b| b.min(self.max_backoff));
+		if self.jitter && !backoff.is_zero() {
+			rand::thread_rng().gen_range(backoff / 2..=backoff)
+		} else {
+			backoff
+		}
+	}
+
+	fn is_allowed(&self, method: &str) -> bool {
+		match &self.methods {
+			Some(methods) => methods.contains(method),
+			None => true,
+		}
+	}
+
+	fn is_retryable(&self, error: &Error) -> bool {
+		match error {
+			Error::Transport(_) => self.retry_on.contains(&RetryOn::Transport),
+			Error::RequestTimeout => self.retry_on.contains(&RetryOn::Timeout),
+			Error::Request(raw) => match serde_json::from_str::<RpcError>(raw) {
+				Ok(err) => self.retry_on.contains(&RetryOn::Code(err.error.code.code())),
+				Err(_) => false,
+			},
+			Error::Call(CallError::Custom { code, .. }) => self.retry_on.contains(&RetryOn::Code(*code)),
+			_ => false,
+		}
+	}
+}
+
+impl ClientMiddleware for RetryPolicy {
+	type Instant = ();
+
+	// The calls are not modified, only retried.
+	const ENABLED: bool = false;
+
+	fn on_request(&self, _request: &mut ClientRequest<'_>) -> Self::Instant {}
+
+	fn retry_after(&self, requests: &[ClientRequest<'_>], error: &Error, attempt: u32) -> Option<Duration> {
+		if self.should_retry(requests, error, attempt) {
+			Some(self.backoff_after(attempt))
+		} else {
+			None
+		}
+	}
+
+	fn attempt_timeout(&self) -> Option<Duration> {
+		self.attempt_timeout
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn call(method: &str) -> ClientRequest<'_> {
+		ClientRequest::new(method, None)
+	}
+
+	fn transport_error() -> Error {
+		Error::Transport(anyhow::anyhow!("connection reset"))
+	}
+
+	#[test]
+	fn default_policy_never_retries() {
+		let policy = RetryPolicy::default().retry_all_methods();
+		assert!(!policy.should_retry(&[call("say_hello")], &transport_error(), 1));
+	}
+
+	#[test]
+	fn retries_allowed_methods_only() {
+		let policy = RetryPolicy::new(3).retry_method("say_hello");
+		assert!(policy.should_retry(&[call("say_hello")], &transport_error(), 1));
+		assert!(policy.should_retry(&[call("say_hello")], &Error::RequestTimeout, 2));
+		assert!(!policy.should_retry(&[call("say_hello")], &transport_error(), 3));
+		assert!(!policy.should_retry(&[call("transfer")], &transport_error(), 1));
+		assert!(!policy.should_retry(&[call("say_hello"), call("transfer")], &transport_error(), 1));
+		assert!(!policy.should_retry(&[call("say_hello")], &Error::InvalidRequestId, 1));
+
+		let policy = RetryPolicy::new(3).retry_all_methods();
+		assert!(policy.should_retry(&[call("say_hello"), call("transfer")], &transport_error(), 1));
+	}
+
+	#[test]
+	fn retries_on_error_codes() {
+		let policy = RetryPolicy::new(3).retry_on([RetryOn::Code(-32005)]).retry_all_methods();
+		let raw = |code: i32| format!(r#"{{"jsonrpc":"2.0","error":{{"code":{},"message":"Busy"}},"id":0}}"#, code);
+		assert!(policy.should_retry(&[call("say_hello")], &Error::Request(raw(-32005)), 1));
+		assert!(!policy.should_retry(&[call("say_hello")], &Error::Request(raw(-32000)), 1));
+		let custom = Error::Call(CallError::Custom { code: -32005, message: "Busy".into(), data: None });
+		assert!(policy.should_retry(&[call("say_hello")], &custom, 1));
+		assert!(!policy.should_retry(&[call("say_hello")], &transport_error(), 1));
+	}
+
+	#[test]
+	fn backoff_grows_exponentially_with_jitter() {
+		let policy = RetryPolicy::new(10).backoff(Duration::from_millis(100), Duration::from_secs(1)).jitter(false);
+		let backoffs: Vec<_> = (1..=6).map(|attempt| policy.backoff_after(attempt).as_millis()).collect();
+		assert_eq!(backoffs, [100, 200, 400, 800, 1000, 1000]);
+		assert_eq!(policy.backoff_after(u32::MAX), Duration::from_secs(1));
+
+		let policy = policy.jitter(true);
+		for attempt in 1..=6 {
+			let backoff = policy.backoff_after(attempt);
+			assert!(backoff >= policy.clone().jitter(false).backoff_after(attempt) / 2);
+			assert!(backoff <= policy.clone().jitter(false).backoff_after(attempt));
+		}
+	}
+}
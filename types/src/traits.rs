@@ -24,8 +24,9 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::batch::{BatchRequestBuilder, BatchResponse};
 use crate::v2::ParamsSer;
-use crate::{Error, Subscription};
+use crate::{Error, JsonValue, Subscription};
 use async_trait::async_trait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -51,6 +52,32 @@ pub trait Client {
 	async fn batch_request<'a, R>(&self, batch: Vec<(&'a str, Option<ParamsSer<'a>>)>) -> Result<Vec<R>, Error>
 	where
 		R: DeserializeOwned + Default + Clone;
+
+	/// Send a [batch request](https://www.jsonrpc.org/specification#batch) whose calls may have different result
+	/// types, see [`BatchRequestBuilder`].
+	///
+	/// Returns `Ok` if the batch was answered, even if some of its calls failed, whose errors are read from the
+	/// [`BatchResponse`]. Returns `Error` if the batch as a whole failed.
+	///
+	/// The default implementation sends the batch with [`Client::batch_request`], which fails the whole batch if any
+	/// of its calls fails.
+	async fn send_batch<'a>(&self, batch: BatchRequestBuilder<'a>) -> Result<BatchResponse, Error> {
+		let id = batch.id();
+		let responses = self.batch_request::<JsonValue>(batch.into_calls()).await?;
+		Ok(BatchResponse::new(id, responses.into_iter().map(Ok).collect()))
+	}
+
+	/// Send a [method call request](https://www.jsonrpc.org/specification#request_object) with already serialized
+	/// `params`, which must be an array or an object, and return its result without decoding it.
+	///
+	/// The HTTP and WebSocket clients send the params as they are, through their middleware and retry policy, but
+	/// don't decode the response for [`on_response`](crate::middleware::ClientMiddleware::on_response). The HTTP client
+	/// returns the `result` of the response as it was received, while the WebSocket client decodes the messages it
+	/// receives in its background task, so the result is serialized again and isn't zero-copy. The default
+	/// implementation sends the call with [`Client::request`].
+	async fn request_raw<'a>(&self, method: &'a str, params: Option<Box<RawValue>>) -> Result<Box<RawValue>, Error> {
+		self.request(method, params.as_deref().map(ParamsSer::Raw)).await
+	}
 }
 
 /// [JSON-RPC](https://www.jsonrpc.org/specification) client interface that can make requests, notifications and subscriptions.
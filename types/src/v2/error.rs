@@ -26,6 +26,7 @@
 
 use crate::v2::params::{Id, TwoPointZero};
 use beef::Cow;
+use serde::de::DeserializeOwned;
 use serde::de::Deserializer;
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
@@ -94,6 +95,53 @@ impl<'a> PartialEq for ErrorObject<'a> {
 	}
 }
 
+/// Owned [`ErrorObject`], for errors that outlive the request they failed, e.g. errors of typed methods.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OwnedErrorObject {
+	/// Code
+	pub code: ErrorCode,
+	/// Message
+	pub message: String,
+	/// Optional data
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub data: Option<Box<RawValue>>,
+}
+
+impl OwnedErrorObject {
+	/// Create a new `OwnedErrorObject` without data.
+	pub fn new(code: impl Into<ErrorCode>, message: impl Into<String>) -> Self {
+		Self { code: code.into(), message: message.into(), data: None }
+	}
+
+	/// Attach data to the error object.
+	pub fn with_data<T: Serialize>(mut self, data: &T) -> Result<Self, serde_json::Error> {
+		self.data = Some(serde_json::value::to_raw_value(data)?);
+		Ok(self)
+	}
+
+	/// Decode the data of the error object, `None` if there is none or if it isn't a `T`.
+	pub fn data<T: DeserializeOwned>(&self) -> Option<T> {
+		self.data.as_ref().and_then(|data| serde_json::from_str(data.get()).ok())
+	}
+
+	/// Borrow as an [`ErrorObject`].
+	pub fn as_error_object(&self) -> ErrorObject<'_> {
+		ErrorObject { code: self.code, message: self.message.as_str().into(), data: self.data.as_deref() }
+	}
+}
+
+impl<'a> From<ErrorObject<'a>> for OwnedErrorObject {
+	fn from(object: ErrorObject<'a>) -> Self {
+		Self { code: object.code, message: object.message.into_owned(), data: object.data.map(ToOwned::to_owned) }
+	}
+}
+
+impl PartialEq for OwnedErrorObject {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_error_object() == other.as_error_object()
+	}
+}
+
 /// Parse error code.
 pub const PARSE_ERROR_CODE: i32 = -32700;
 /// Oversized request error code.
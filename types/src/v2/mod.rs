@@ -35,7 +35,7 @@ pub mod request;
 /// JSON-RPC response object related types.
 pub mod response;
 
-pub use error::{ErrorCode, ErrorObject, RpcError};
+pub use error::{ErrorCode, ErrorObject, OwnedErrorObject, RpcError};
 pub use params::{Id, Params, ParamsSequence, ParamsSer, SubscriptionId, TwoPointZero};
 pub use request::{InvalidRequest, Notification, NotificationSer, Request, RequestSer};
-pub use response::{Response, SubscriptionPayload, SubscriptionResponse};
+pub use response::{parse_batch_response, BatchResponseEntry, Response, SubscriptionPayload, SubscriptionResponse};
@@ -34,7 +34,7 @@ use beef::Cow;
 use serde::de::{self, Deserializer, Unexpected, Visitor};
 use serde::ser::Serializer;
 use serde::{Deserialize, Serialize};
-use serde_json::Value as JsonValue;
+use serde_json::{value::RawValue, Value as JsonValue};
 use std::{convert::TryFrom, fmt};
 
 /// JSON-RPC v2 marker type.
@@ -265,6 +265,23 @@ pub enum ParamsSer<'a> {
 	ArrayRef(&'a [JsonValue]),
 	/// Params by name.
 	Map(BTreeMap<&'a str, JsonValue>),
+	/// Params by name (reference).
+	MapRef(&'a BTreeMap<&'a str, JsonValue>),
+	/// Already serialized params, which must be an array or an object. They are sent as they are.
+	Raw(&'a RawValue),
+}
+
+impl<'a> ParamsSer<'a> {
+	/// Borrow the params, without copying them.
+	pub fn as_borrowed(&self) -> ParamsSer<'_> {
+		match self {
+			Self::Array(params) => ParamsSer::ArrayRef(params),
+			Self::ArrayRef(params) => ParamsSer::ArrayRef(params),
+			Self::Map(params) => ParamsSer::MapRef(params),
+			Self::MapRef(params) => ParamsSer::MapRef(params),
+			Self::Raw(params) => ParamsSer::Raw(params),
+		}
+	}
 }
 
 impl<'a> From<BTreeMap<&'a str, JsonValue>> for ParamsSer<'a> {
@@ -442,6 +459,8 @@ mod test {
 		for (initial_ser, params) in test_vector {
 			let serialized = serde_json::to_string(params).unwrap();
 			assert_eq!(&serialized, initial_ser);
+			let serialized = serde_json::to_string(&params.as_borrowed()).unwrap();
+			assert_eq!(&serialized, initial_ser);
 		}
 	}
 
@@ -27,10 +27,13 @@
 //! Types pertaining to JSON-RPC responses.
 
 use crate::v2::{
+	error::RpcError,
 	params::{Id, SubscriptionId, TwoPointZero},
 	request::Notification,
 };
+use crate::Error;
 use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value as JsonValue};
 
 /// JSON-RPC successful response object as defined in the [spec](https://www.jsonrpc.org/specification#response_object).
 #[derive(Serialize, Deserialize, Debug)]
@@ -65,9 +68,32 @@ pub struct SubscriptionPayload<'a, T> {
 /// Subscription response object, embedding a [`SubscriptionPayload`] in the `params` member.
 pub type SubscriptionResponse<'a, T> = Notification<'a, SubscriptionPayload<'a, T>>;
 
+/// ID and result of a call of a batch, parsed by [`parse_batch_response`].
+pub type BatchResponseEntry<'a, T = JsonValue> = (Id<'a>, Result<T, Error>);
+
+/// Parses a batch response, whose entries are either the response to a call of the batch or the error object it
+/// failed with. Returns the ID and the result of each entry, the errors as [`Error::Request`] with the error object.
+pub fn parse_batch_response<'a, T>(raw: &'a [u8]) -> Result<Vec<BatchResponseEntry<'a, T>>, serde_json::Error>
+where
+	T: Deserialize<'a>,
+{
+	let entries: Vec<&RawValue> = serde_json::from_slice(raw)?;
+	entries
+		.into_iter()
+		.map(|entry| match serde_json::from_str::<Response<T>>(entry.get()) {
+			Ok(response) => Ok((response.id, Ok(response.result))),
+			Err(_) => {
+				let err: RpcError = serde_json::from_str(entry.get())?;
+				let raw = err.to_string();
+				Ok((err.id, Err(Error::Request(raw))))
+			}
+		})
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
-	use super::{Id, Response, TwoPointZero};
+	use super::{parse_batch_response, Error, Id, JsonValue, Response, TwoPointZero};
 
 	#[test]
 	fn serialize_call_response() {
@@ -84,4 +110,21 @@ mod tests {
 		assert_eq!(dsr.result, exp.result);
 		assert_eq!(dsr.id, exp.id);
 	}
+
+	#[test]
+	fn parse_batch_with_errors() {
+		let raw = br#"[
+			{"jsonrpc":"2.0","result":"hello","id":1},
+			{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":2}
+		]"#;
+		let entries = parse_batch_response::<JsonValue>(raw).unwrap();
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].0, Id::Number(1));
+		assert_eq!(entries[0].1.as_ref().unwrap(), "hello");
+		assert_eq!(entries[1].0, Id::Number(2));
+		assert!(matches!(&entries[1].1, Err(Error::Request(err)) if err.contains("-32601")));
+
+		assert!(parse_batch_response::<JsonValue>(br#"[{"jsonrpc":"2.0","id":1}]"#).is_err());
+		assert!(parse_batch_response::<JsonValue>(br#"{"jsonrpc":"2.0","result":"hello","id":1}"#).is_err());
+	}
 }
@@ -0,0 +1,188 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! `permessage-deflate` extension (RFC 7692) limiting the size of the inflated messages.
+//!
+//! The size limit of a WebSocket connection applies to the messages as received, which are compressed when
+//! `permessage-deflate` is used. [`BoundedDeflate`] stops inflating a message as soon as it exceeds its own limit,
+//! so that a small compressed message can't allocate an unbounded amount of memory.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use flate2::write::DeflateDecoder;
+use soketto::base::{Header, OpCode};
+use soketto::extension::{deflate::Deflate, Extension, Param};
+use soketto::{BoxedError, Mode, Storage};
+
+/// Error of the [`BoundedDeflate`] extension when a message inflates to more than its limit.
+///
+/// The message was received entirely, the connection can still be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InflatedTooLarge {
+	/// Max size in bytes of an inflated message.
+	pub maximum: usize,
+}
+
+impl fmt::Display for InflatedTooLarge {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Inflated message is too big (max is {} bytes)", self.maximum)
+	}
+}
+
+impl std::error::Error for InflatedTooLarge {}
+
+/// The `permessage-deflate` extension of `soketto`, failing with [`InflatedTooLarge`] when a message inflates to
+/// more than `max_message_size` bytes.
+#[derive(Debug)]
+pub struct BoundedDeflate {
+	inner: Deflate,
+	max_message_size: usize,
+	buffer: Vec<u8>,
+	await_last_fragment: bool,
+}
+
+impl BoundedDeflate {
+	/// Create a new deflate extension either on client or server side, inflating messages up to
+	/// `max_message_size` bytes.
+	pub fn new(mode: Mode, max_message_size: usize) -> Self {
+		Self { inner: Deflate::new(mode), max_message_size, buffer: Vec::new(), await_last_fragment: false }
+	}
+}
+
+impl Extension for BoundedDeflate {
+	fn is_enabled(&self) -> bool {
+		self.inner.is_enabled()
+	}
+
+	fn name(&self) -> &str {
+		self.inner.name()
+	}
+
+	fn params(&self) -> &[Param<'_>] {
+		self.inner.params()
+	}
+
+	fn configure(&mut self, params: &[Param]) -> Result<(), BoxedError> {
+		self.inner.configure(params)
+	}
+
+	fn encode(&mut self, header: &mut Header, data: &mut Storage) -> Result<(), BoxedError> {
+		self.inner.encode(header, data)
+	}
+
+	// Same as `Deflate::decode`, with the inflated message written through a `BoundedWriter`.
+	fn decode(&mut self, header: &mut Header, data: &mut Vec<u8>) -> Result<(), BoxedError> {
+		if data.is_empty() {
+			return Ok(());
+		}
+
+		match header.opcode() {
+			OpCode::Binary | OpCode::Text if header.is_rsv1() => {
+				if !header.is_fin() {
+					self.await_last_fragment = true;
+					return Ok(());
+				}
+			}
+			OpCode::Continue if header.is_fin() && self.await_last_fragment => self.await_last_fragment = false,
+			_ => return Ok(()),
+		}
+
+		// Restore LEN and NLEN, cf. RFC 7692, 7.2.2.
+		data.extend_from_slice(&[0, 0, 0xFF, 0xFF]);
+
+		self.buffer.clear();
+		let inflated = {
+			let writer = BoundedWriter { buffer: &mut self.buffer, max_len: self.max_message_size };
+			let mut decoder = DeflateDecoder::new(writer);
+			decoder.write_all(data).and_then(|_| decoder.try_finish())
+		};
+
+		if let Err(err) = inflated {
+			self.buffer.clear();
+			return match err.get_ref().and_then(|err| err.downcast_ref::<InflatedTooLarge>()) {
+				Some(too_large) => Err(Box::new(*too_large)),
+				None => Err(err.into()),
+			};
+		}
+
+		std::mem::swap(data, &mut self.buffer);
+		header.set_rsv1(false);
+		header.set_payload_len(data.len());
+
+		Ok(())
+	}
+
+	fn reserved_bits(&self) -> (bool, bool, bool) {
+		self.inner.reserved_bits()
+	}
+}
+
+/// Writer into a buffer failing with [`InflatedTooLarge`] once more than `max_len` bytes are written.
+struct BoundedWriter<'a> {
+	buffer: &'a mut Vec<u8>,
+	max_len: usize,
+}
+
+impl Write for BoundedWriter<'_> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if self.buffer.len() + buf.len() > self.max_len {
+			return Err(io::Error::other(InflatedTooLarge { maximum: self.max_len }));
+		}
+		self.buffer.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn compressed(deflate: &mut BoundedDeflate, len: usize) -> (Header, Vec<u8>) {
+		let mut header = Header::new(OpCode::Text);
+		let mut data = Storage::Owned(vec![b'a'; len]);
+		deflate.encode(&mut header, &mut data).unwrap();
+		(header, data.as_ref().to_vec())
+	}
+
+	#[test]
+	fn inflates_messages_up_to_the_limit() {
+		let mut deflate = BoundedDeflate::new(Mode::Server, 1024);
+
+		let (mut header, mut data) = compressed(&mut deflate, 1024);
+		deflate.decode(&mut header, &mut data).unwrap();
+		assert_eq!(data, vec![b'a'; 1024]);
+
+		let (mut header, mut data) = compressed(&mut deflate, 10 * 1024 * 1024);
+		let err = deflate.decode(&mut header, &mut data).unwrap_err();
+		assert_eq!(err.downcast_ref::<InflatedTooLarge>(), Some(&InflatedTooLarge { maximum: 1024 }));
+		assert!(deflate.buffer.capacity() < 64 * 1024);
+	}
+}
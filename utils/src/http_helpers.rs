@@ -36,9 +36,27 @@ use jsonrpsee_types::error::GenericTransportError;
 /// Returns `Err` if the body was too large or the body couldn't be read.
 pub async fn read_body(
 	headers: &hyper::HeaderMap,
-	mut body: hyper::Body,
+	body: hyper::Body,
 	max_request_body_size: u32,
 ) -> Result<(Vec<u8>, bool), GenericTransportError<hyper::Error>> {
+	let received_data = read_raw_body(headers, body, max_request_body_size).await?;
+
+	let single = match received_data.first() {
+		Some(b'{') => true,
+		Some(b'[') => false,
+		_ => return Err(GenericTransportError::Malformed),
+	};
+
+	Ok((received_data, single))
+}
+
+/// Read a data from a [`hyper::Body`] and return the data if it is within the allowed size range, without
+/// inspecting its content. Used for bodies that have to be decoded before they are parsed.
+pub async fn read_raw_body(
+	headers: &hyper::HeaderMap,
+	mut body: hyper::Body,
+	max_request_body_size: u32,
+) -> Result<Vec<u8>, GenericTransportError<hyper::Error>> {
 	// NOTE(niklasad1): Values bigger than `u32::MAX` will be turned into zero here. This is unlikely to occur in
 	// practice and for that case we fallback to allocating in the while-loop below instead of pre-allocating.
 	let body_size = read_header_content_length(headers).unwrap_or(0);
@@ -47,21 +65,7 @@ pub async fn read_body(
 		return Err(GenericTransportError::TooLarge);
 	}
 
-	let first_chunk =
-		body.next().await.ok_or(GenericTransportError::Malformed)?.map_err(GenericTransportError::Inner)?;
-
-	if first_chunk.len() > max_request_body_size as usize {
-		return Err(GenericTransportError::TooLarge);
-	}
-
-	let single = match first_chunk.get(0) {
-		Some(b'{') => true,
-		Some(b'[') => false,
-		_ => return Err(GenericTransportError::Malformed),
-	};
-
 	let mut received_data = Vec::with_capacity(body_size as usize);
-	received_data.extend_from_slice(&first_chunk);
 
 	while let Some(chunk) = body.next().await {
 		let chunk = chunk.map_err(GenericTransportError::Inner)?;
@@ -71,7 +75,7 @@ pub async fn read_body(
 		}
 		received_data.extend_from_slice(&chunk);
 	}
-	Ok((received_data, single))
+	Ok(received_data)
 }
 
 /// Read the `Content-Length` HTTP Header. Must fit into a `u32`; returns `None` otherwise.
@@ -105,7 +109,7 @@ pub fn read_header_values<'a>(
 
 #[cfg(test)]
 mod tests {
-	use super::{read_body, read_header_content_length};
+	use super::{read_body, read_header_content_length, read_raw_body};
 
 	#[tokio::test]
 	async fn body_to_bytes_size_limit_works() {
@@ -114,6 +118,13 @@ mod tests {
 		assert!(read_body(&headers, body, 127).await.is_err());
 	}
 
+	#[tokio::test]
+	async fn raw_body_size_limit_works() {
+		let headers = hyper::header::HeaderMap::new();
+		assert!(read_raw_body(&headers, hyper::Body::from(vec![0; 128]), 127).await.is_err());
+		assert_eq!(read_raw_body(&headers, hyper::Body::from(vec![0; 128]), 128).await.unwrap(), vec![0; 128]);
+	}
+
 	#[test]
 	fn read_content_length_works() {
 		let mut headers = hyper::header::HeaderMap::new();
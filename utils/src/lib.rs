@@ -39,3 +39,11 @@ pub mod server;
 /// Shared code for JSON-RPC clients.
 #[cfg(feature = "client")]
 pub mod client;
+
+/// Shared code to connect through an HTTP proxy.
+#[cfg(feature = "proxy")]
+pub mod proxy;
+
+/// `permessage-deflate` WebSocket extension limiting the size of the inflated messages.
+#[cfg(feature = "deflate")]
+pub mod deflate;
@@ -0,0 +1,398 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Structured access logging for the JSON-RPC servers.
+//!
+//! When an [`AccessLog`] is configured on a server, one record is emitted per JSON-RPC call and per request that
+//! failed to parse, at the moment the response (or error) for it is handed over to the transport. Each record is
+//! serialized as a single line of JSON and passed to an [`AccessLogWriter`]:
+//!
+//! ```text
+//! {"timestamp":1638316800000,"transport":"http","remote_addr":"127.0.0.1:51234","method":"say_hello","params_size":2,"success":true,"error_code":null,"latency_us":87,"response_size":38}
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use jsonrpsee_types::v2::Request;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value as JsonValue};
+
+/// Placeholder that replaces the value of a redacted parameter.
+pub const REDACTED: &str = "<redacted>";
+
+/// Destination for access log records. Each call receives one record serialized as a single line of JSON,
+/// without a trailing newline.
+pub trait AccessLogWriter: Send + Sync + 'static {
+	/// Write a single access log record.
+	fn write_record(&self, line: &str);
+}
+
+/// Writes access log records as `tracing` events with the target `jsonrpsee::access_log` at `INFO` level.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TracingWriter;
+
+impl AccessLogWriter for TracingWriter {
+	fn write_record(&self, line: &str) {
+		tracing::info!(target: "jsonrpsee::access_log", "{}", line);
+	}
+}
+
+impl<W: Write + Send + 'static> AccessLogWriter for Mutex<W> {
+	fn write_record(&self, line: &str) {
+		let mut writer = self.lock();
+		if let Err(err) = writer.write_all(line.as_bytes()).and_then(|_| writer.write_all(b"\n")) {
+			tracing::error!("Failed to write access log record: {:?}", err);
+		}
+	}
+}
+
+impl<T: AccessLogWriter + ?Sized> AccessLogWriter for Arc<T> {
+	fn write_record(&self, line: &str) {
+		(**self).write_record(line)
+	}
+}
+
+/// Access log configuration, shared by all connections of a server.
+///
+/// ```
+/// use jsonrpsee_utils::server::access_log::AccessLog;
+///
+/// let access_log = AccessLog::new()
+///     // Log one out of ten calls.
+///     .sample_rate(0.1)
+///     .log_params(true)
+///     .redact_params(["password", "seed"]);
+/// ```
+#[derive(Clone)]
+pub struct AccessLog {
+	sample_rate: f64,
+	log_params: bool,
+	redacted_params: Arc<[String]>,
+	writer: Arc<dyn AccessLogWriter>,
+}
+
+impl fmt::Debug for AccessLog {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AccessLog")
+			.field("sample_rate", &self.sample_rate)
+			.field("log_params", &self.log_params)
+			.field("redacted_params", &self.redacted_params)
+			.finish()
+	}
+}
+
+impl Default for AccessLog {
+	fn default() -> Self {
+		Self { sample_rate: 1.0, log_params: false, redacted_params: Arc::new([]), writer: Arc::new(TracingWriter) }
+	}
+}
+
+impl AccessLog {
+	/// Create an access log that records every call through [`TracingWriter`] and does not include the params.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set the fraction of calls that are logged, between `0.0` (none) and `1.0` (all). Default is `1.0`.
+	pub fn sample_rate(mut self, rate: f64) -> Self {
+		self.sample_rate = rate.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Include the call params in the record. Default is false.
+	pub fn log_params(mut self, enabled: bool) -> Self {
+		self.log_params = enabled;
+		self
+	}
+
+	/// Replace the values of the params with the given names by [`REDACTED`] before they are logged.
+	///
+	/// Names are matched against the keys of `map` params, including keys of nested objects. Params passed
+	/// as an array have no names and are never redacted, so disable [`AccessLog::log_params`] if they
+	/// may carry sensitive values.
+	pub fn redact_params<Name, List>(mut self, names: List) -> Self
+	where
+		List: IntoIterator<Item = Name>,
+		Name: Into<String>,
+	{
+		self.redacted_params = names.into_iter().map(Into::into).collect();
+		self
+	}
+
+	/// Set the destination of the records. Default is [`TracingWriter`].
+	pub fn writer(mut self, writer: impl AccessLogWriter) -> Self {
+		self.writer = Arc::new(writer);
+		self
+	}
+
+	/// Start a record for a call, returns `None` if the call was not sampled.
+	pub fn start(
+		&self,
+		transport: &'static str,
+		remote_addr: Option<SocketAddr>,
+		req: &Request,
+	) -> Option<AccessLogEntry> {
+		self.start_record(transport, remote_addr, req.method.to_string(), req.params)
+	}
+
+	/// Start a record for a request that failed to parse, returns `None` if the request was not sampled.
+	///
+	/// The method of the record is the `method` of the request if it has one, and is empty otherwise.
+	pub fn start_invalid(
+		&self,
+		transport: &'static str,
+		remote_addr: Option<SocketAddr>,
+		request: &[u8],
+	) -> Option<AccessLogEntry> {
+		#[derive(Deserialize)]
+		struct InvalidRequest<'a> {
+			#[serde(borrow)]
+			method: Option<Cow<'a, str>>,
+		}
+
+		let method = serde_json::from_slice::<InvalidRequest>(request).ok().and_then(|req| req.method);
+		self.start_record(transport, remote_addr, method.map(Cow::into_owned).unwrap_or_default(), None)
+	}
+
+	fn start_record(
+		&self,
+		transport: &'static str,
+		remote_addr: Option<SocketAddr>,
+		method: String,
+		params: Option<&RawValue>,
+	) -> Option<AccessLogEntry> {
+		if self.sample_rate < 1.0 && rand::random::<f64>() >= self.sample_rate {
+			return None;
+		}
+
+		let params_size = params.map_or(0, |p| p.get().len());
+		let params = if self.log_params {
+			params.and_then(|p| serde_json::from_str(p.get()).ok()).map(|mut params| {
+				self.redact(&mut params);
+				params
+			})
+		} else {
+			None
+		};
+
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+
+		Some(AccessLogEntry {
+			inner: Arc::new(Mutex::new(Some(PendingRecord {
+				timestamp,
+				started_at: Instant::now(),
+				transport,
+				remote_addr,
+				method,
+				params_size,
+				params,
+			}))),
+			writer: self.writer.clone(),
+		})
+	}
+
+	fn redact(&self, value: &mut JsonValue) {
+		if self.redacted_params.is_empty() {
+			return;
+		}
+
+		match value {
+			JsonValue::Object(map) => {
+				for (key, value) in map.iter_mut() {
+					if self.redacted_params.iter().any(|name| name == key) {
+						*value = JsonValue::String(REDACTED.into());
+					} else {
+						self.redact(value);
+					}
+				}
+			}
+			JsonValue::Array(values) => values.iter_mut().for_each(|value| self.redact(value)),
+			_ => {}
+		}
+	}
+}
+
+/// Data collected when the call starts, completed once the response is known.
+#[derive(Debug)]
+struct PendingRecord {
+	timestamp: u64,
+	started_at: Instant,
+	transport: &'static str,
+	remote_addr: Option<SocketAddr>,
+	method: String,
+	params_size: usize,
+	params: Option<JsonValue>,
+}
+
+/// A single access log record.
+#[derive(Debug, Serialize)]
+pub struct AccessLogRecord<'a> {
+	/// Milliseconds since the UNIX epoch when the call was received.
+	pub timestamp: u64,
+	/// Transport the call was received on, `http` or `ws`.
+	pub transport: &'a str,
+	/// Address of the remote peer, if known.
+	pub remote_addr: Option<SocketAddr>,
+	/// Name of the called method, empty if the request failed to parse and has no method.
+	pub method: &'a str,
+	/// Size in bytes of the raw JSON params.
+	pub params_size: usize,
+	/// Params of the call, with redacted values replaced. Only present if enabled.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub params: Option<&'a JsonValue>,
+	/// Whether the call succeeded.
+	pub success: bool,
+	/// JSON-RPC error code of the response if the call failed.
+	pub error_code: Option<i32>,
+	/// Time in microseconds between receiving the call and producing its response.
+	pub latency_us: u64,
+	/// Size in bytes of the JSON-RPC response.
+	pub response_size: usize,
+}
+
+/// Handle to a started access log record. It is written once, by the first call to [`AccessLogEntry::finish`].
+#[derive(Clone)]
+pub struct AccessLogEntry {
+	inner: Arc<Mutex<Option<PendingRecord>>>,
+	writer: Arc<dyn AccessLogWriter>,
+}
+
+impl fmt::Debug for AccessLogEntry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("AccessLogEntry").field("inner", &self.inner).finish()
+	}
+}
+
+impl AccessLogEntry {
+	/// Complete the record with the outcome of the call and write it.
+	pub fn finish(&self, error_code: Option<i32>, response_size: usize) {
+		let pending = match self.inner.lock().take() {
+			Some(pending) => pending,
+			None => return,
+		};
+
+		let record = AccessLogRecord {
+			timestamp: pending.timestamp,
+			transport: pending.transport,
+			remote_addr: pending.remote_addr,
+			method: &pending.method,
+			params_size: pending.params_size,
+			params: pending.params.as_ref(),
+			success: error_code.is_none(),
+			error_code,
+			latency_us: pending.started_at.elapsed().as_micros() as u64,
+			response_size,
+		};
+
+		match serde_json::to_string(&record) {
+			Ok(line) => self.writer.write_record(&line),
+			Err(err) => tracing::error!("Failed to serialize access log record: {:?}", err),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Default)]
+	struct Records(Mutex<Vec<String>>);
+
+	impl AccessLogWriter for Records {
+		fn write_record(&self, line: &str) {
+			self.0.lock().push(line.to_owned());
+		}
+	}
+
+	#[test]
+	fn redacts_named_params() {
+		let records = Arc::new(Records::default());
+		let log = AccessLog::new().log_params(true).redact_params(["password"]).writer(records.clone());
+		let req: Request = serde_json::from_str(
+			r#"{"jsonrpc":"2.0","method":"login","params":{"user":"bob","auth":{"password":"hunter2"}},"id":1}"#,
+		)
+		.unwrap();
+
+		log.start("http", None, &req).unwrap().finish(None, 10);
+
+		let record: JsonValue = serde_json::from_str(&records.0.lock()[0]).unwrap();
+		assert_eq!(record["method"], "login");
+		assert_eq!(record["params"]["user"], "bob");
+		assert_eq!(record["params"]["auth"]["password"], REDACTED);
+		assert_eq!(record["success"], true);
+		assert_eq!(record["response_size"], 10);
+	}
+
+	#[test]
+	fn record_is_written_once() {
+		let records = Arc::new(Records::default());
+		let log = AccessLog::new().writer(records.clone());
+		let req: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"foo","params":[1,2],"id":1}"#).unwrap();
+
+		let entry = log.start("ws", None, &req).unwrap();
+		entry.finish(Some(-32601), 5);
+		entry.finish(None, 5);
+
+		let records = records.0.lock();
+		assert_eq!(records.len(), 1);
+		let record: JsonValue = serde_json::from_str(&records[0]).unwrap();
+		assert_eq!(record["params_size"], 5);
+		assert_eq!(record["success"], false);
+		assert_eq!(record["error_code"], -32601);
+		assert!(record.get("params").is_none());
+	}
+
+	#[test]
+	fn zero_sample_rate_logs_nothing() {
+		let log = AccessLog::new().sample_rate(0.0);
+		let req: Request = serde_json::from_str(r#"{"jsonrpc":"2.0","method":"foo","id":1}"#).unwrap();
+		assert!(log.start("http", None, &req).is_none());
+	}
+
+	#[test]
+	fn invalid_requests_are_logged() {
+		let records = Arc::new(Records::default());
+		let log = AccessLog::new().log_params(true).writer(records.clone());
+
+		log.start_invalid("ws", None, br#"{"jsonrpc":"2.0","method":"foo","params":[1],"id":{}}"#)
+			.unwrap()
+			.finish(Some(-32600), 7);
+		log.start_invalid("ws", None, b"{ nope").unwrap().finish(Some(-32700), 7);
+
+		let records: Vec<JsonValue> = records.0.lock().iter().map(|r| serde_json::from_str(r).unwrap()).collect();
+		assert_eq!(records[0]["method"], "foo");
+		assert_eq!(records[0]["params_size"], 0);
+		assert_eq!(records[0]["error_code"], -32600);
+		assert_eq!(records[1]["method"], "");
+		assert_eq!(records[1]["error_code"], -32700);
+	}
+}
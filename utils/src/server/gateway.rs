@@ -0,0 +1,156 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+use crate::server::rpc_module::{Methods, RpcModule};
+use jsonrpsee_types::{
+	error::{Error, SubscriptionClosedReason},
+	traits::SubscriptionClient,
+	v2::ParamsSer,
+	JsonValue,
+};
+use serde_json::value::RawValue;
+use std::fmt;
+
+/// Gateway forwarding a set of methods and subscriptions to an upstream server through a client, such as an
+/// `HttpClient` or a `WsClient`.
+///
+/// The forwarded calls are sent upstream as they are, and the results, errors and notifications of the upstream
+/// server are sent back as they are. Unsubscribing from a forwarded subscription unsubscribes upstream. Only the
+/// methods and subscriptions added to the gateway are forwarded, the gateway is then converted into [`Methods`]
+/// which can be merged with the local ones of the server.
+///
+/// ```no_run
+/// use jsonrpsee_types::{traits::SubscriptionClient, Error};
+/// use jsonrpsee_utils::server::{gateway::Gateway, rpc_module::RpcModule};
+///
+/// fn methods(upstream: impl SubscriptionClient + Send + Sync + 'static) -> Result<RpcModule<()>, Error> {
+///     let mut gateway = Gateway::new(upstream);
+///     gateway.forward_method("system_name")?;
+///     gateway.forward_subscription("chain_subscribeNewHeads", "chain_newHead", "chain_unsubscribeNewHeads")?;
+///
+///     let mut module = RpcModule::new(());
+///     module.register_method("local_hello", |_, _| Ok("hello"))?;
+///     module.merge(gateway)?;
+///     Ok(module)
+/// }
+/// ```
+pub struct Gateway<C> {
+	module: RpcModule<C>,
+}
+
+impl<C> fmt::Debug for Gateway<C> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Gateway").field("methods", &self.module.method_names().collect::<Vec<_>>()).finish()
+	}
+}
+
+impl<C> From<Gateway<C>> for Methods {
+	fn from(gateway: Gateway<C>) -> Methods {
+		gateway.module.into()
+	}
+}
+
+impl<C: SubscriptionClient + Send + Sync + 'static> Gateway<C> {
+	/// Create a gateway forwarding to the upstream server of the `client`, with no method forwarded yet.
+	pub fn new(client: C) -> Self {
+		Self { module: RpcModule::new(client) }
+	}
+
+	/// Forward the calls to `method` upstream.
+	pub fn forward_method(&mut self, method: &'static str) -> Result<(), Error> {
+		self.module.register_async_method(method, move |params, client| async move {
+			let params: Option<Box<RawValue>> = params.parse()?;
+			client.request_raw(method, params).await.map_err(Error::into_call_error)
+		})?;
+		Ok(())
+	}
+
+	/// Forward the subscriptions made with `subscribe_method` upstream, see
+	/// [`RpcModule::register_subscription`] for the arguments.
+	///
+	/// The subscription ID is sent back before the upstream subscription is made: if it fails, the subscription is
+	/// closed with the error. The subscription is also closed when the upstream one is.
+	pub fn forward_subscription(
+		&mut self,
+		subscribe_method: &'static str,
+		notif_method: &'static str,
+		unsubscribe_method: &'static str,
+	) -> Result<(), Error> {
+		self.module.register_async_subscription(
+			subscribe_method,
+			notif_method,
+			unsubscribe_method,
+			move |params, mut sink, client| async move {
+				let params: Option<Box<RawValue>> = match params.parse() {
+					Ok(params) => params,
+					Err(err) => {
+						sink.close(&err.to_string());
+						return Ok(());
+					}
+				};
+				let params = params.as_deref().map(ParamsSer::Raw);
+				let mut upstream =
+					match client.subscribe::<JsonValue>(subscribe_method, params, unsubscribe_method).await {
+						Ok(upstream) => upstream,
+						Err(err) => {
+							sink.close(&err.into_call_error().to_string());
+							return Ok(());
+						}
+					};
+
+				// Dropping the upstream subscription unsubscribes from it.
+				loop {
+					tokio::select! {
+						notif = upstream.next() => match notif {
+							Some(Ok(notif)) => {
+								if sink.send(&notif).is_err() {
+									break;
+								}
+							}
+							Some(Err(Error::SubscriptionClosed(closed))) => {
+								match closed.close_reason() {
+									SubscriptionClosedReason::Server(reason) => sink.close(reason),
+									reason => sink.close(&format!("Upstream subscription closed: {:?}", reason)),
+								}
+								break;
+							}
+							Some(Err(err)) => {
+								sink.close(&err.to_string());
+								break;
+							}
+							None => {
+								sink.close("Upstream subscription terminated");
+								break;
+							}
+						},
+						_ = sink.closed() => break,
+					}
+				}
+				Ok(())
+			},
+		)
+	}
+}
@@ -24,18 +24,23 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
+use crate::server::access_log::AccessLogEntry;
+use crate::server::resource_limiting::ResourceGuard;
 use futures_channel::mpsc;
 use futures_util::stream::StreamExt;
-use jsonrpsee_types::error::{CallError, Error};
+#[cfg(feature = "ws-encodings")]
+use jsonrpsee_types::encoding::WsEncoding;
+use jsonrpsee_types::error::{Error, MethodError};
 use jsonrpsee_types::to_json_raw_value;
 use jsonrpsee_types::v2::error::{OVERSIZED_RESPONSE_CODE, OVERSIZED_RESPONSE_MSG};
-use jsonrpsee_types::v2::{
-	error::{CALL_EXECUTION_FAILED_CODE, UNKNOWN_ERROR_CODE},
-	ErrorCode, ErrorObject, Id, InvalidRequest, Response, RpcError,
-};
+use jsonrpsee_types::v2::{ErrorCode, ErrorObject, Id, InvalidRequest, OwnedErrorObject, Response, RpcError};
+use parking_lot::Mutex;
 use serde::Serialize;
+use serde_json::value::RawValue;
 
+use std::fmt;
 use std::io;
+use std::sync::Arc;
 
 /// Bounded writer that allows writing at most `max_len` bytes.
 ///
@@ -81,24 +86,249 @@ impl<'a> io::Write for &'a mut BoundedWriter {
 	}
 }
 
+/// Writer that fails once more than `max_len` bytes were written to `inner`.
+struct LimitedWriter<W> {
+	inner: W,
+	max_len: usize,
+	written: usize,
+}
+
+impl<W: io::Write> io::Write for LimitedWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if self.written + buf.len() > self.max_len {
+			return Err(io::Error::new(io::ErrorKind::OutOfMemory, "Memory capacity exceeded"));
+		}
+		let n = self.inner.write(buf)?;
+		self.written += n;
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+/// Serializes a response into the given writer.
+type SerializeFn = Box<dyn FnOnce(&mut dyn io::Write) -> serde_json::Result<()> + Send>;
+
+/// A JSON-RPC response that is serialized while the transport writes it out, instead of being buffered
+/// in memory first. See [`MethodSink::send_streaming_response`].
+pub struct StreamingResponse {
+	serialize: SerializeFn,
+	max_response_size: u32,
+	access_log: Option<AccessLogEntry>,
+	/// Resources claimed by the call, released once the response is written.
+	_claimed: Option<ResourceGuard>,
+}
+
+impl fmt::Debug for StreamingResponse {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("StreamingResponse").field("max_response_size", &self.max_response_size).finish()
+	}
+}
+
+impl StreamingResponse {
+	/// Serialize the response into `writer` and return the number of bytes written.
+	///
+	/// Fails with an [`io::ErrorKind::OutOfMemory`] error as soon as the serialization exceeds the maximum
+	/// response size, in which case the response written so far is incomplete.
+	pub fn write_to(self, writer: impl io::Write) -> io::Result<usize> {
+		let mut writer = LimitedWriter { inner: writer, max_len: self.max_response_size as usize, written: 0 };
+
+		let result = (self.serialize)(&mut writer).map_err(io::Error::from).and_then(|_| io::Write::flush(&mut writer));
+
+		if let Some(entry) = &self.access_log {
+			let error_code = match &result {
+				Ok(_) => None,
+				Err(err) if err.kind() == io::ErrorKind::OutOfMemory => Some(OVERSIZED_RESPONSE_CODE),
+				Err(_) => Some(ErrorCode::InternalError.code()),
+			};
+			entry.finish(error_code, writer.written);
+		}
+
+		result.map(|_| writer.written)
+	}
+}
+
+/// Copy of the response sent through a [`MethodSink`], see [`MethodSink::keeping_response`].
+#[derive(Debug, Clone)]
+pub enum KeptResponse {
+	/// Result of the successful call.
+	Result(Arc<RawValue>),
+	/// Error of the failed call.
+	Error(OwnedErrorObject),
+}
+
+impl KeptResponse {
+	/// Send the kept response to the `sink`, as the response to the call `id`. Returns whether the call succeeded.
+	pub fn send_to(&self, sink: &MethodSink, id: Id) -> bool {
+		match self {
+			KeptResponse::Result(result) => sink.send_response(id, &**result),
+			KeptResponse::Error(err) => sink.send_error(id, err.as_error_object()),
+		}
+	}
+}
+
+/// Slot the response sent through a [`MethodSink`] is kept in, see [`MethodSink::keeping_response`].
+#[derive(Debug, Clone, Default)]
+pub struct ResponseSlot(Arc<Mutex<Option<KeptResponse>>>);
+
+impl ResponseSlot {
+	/// Returns the kept response, `None` if no response was sent, or if it was sent with [`MethodSink::send_raw`].
+	pub fn get(&self) -> Option<KeptResponse> {
+		self.0.lock().clone()
+	}
+
+	fn keep(&self, response: KeptResponse) {
+		*self.0.lock() = Some(response);
+	}
+}
+
+/// Channel of the messages sent to the client.
+#[derive(Clone, Debug)]
+enum SinkTx {
+	/// JSON messages.
+	Json(mpsc::UnboundedSender<String>),
+	/// Messages in a binary encoding.
+	#[cfg(feature = "ws-encodings")]
+	Encoded(WsEncoding, mpsc::UnboundedSender<Vec<u8>>),
+}
+
+/// Message serialized for the channel of a [`MethodSink`].
+#[derive(Debug)]
+pub(crate) enum SinkMessage {
+	Json(String),
+	#[cfg(feature = "ws-encodings")]
+	Encoded(Vec<u8>),
+}
+
+impl SinkMessage {
+	fn len(&self) -> usize {
+		match self {
+			SinkMessage::Json(json) => json.len(),
+			#[cfg(feature = "ws-encodings")]
+			SinkMessage::Encoded(encoded) => encoded.len(),
+		}
+	}
+}
+
+/// Error when serializing a [`SinkMessage`].
+#[derive(Debug)]
+enum SerializeError {
+	/// The message exceeds the maximum size.
+	TooLarge,
+	/// The message could not be serialized.
+	Invalid(String),
+}
+
+impl SinkTx {
+	/// Serialize a message of at most `max_len` bytes for this channel.
+	fn serialize(&self, message: &impl Serialize, max_len: u32) -> Result<SinkMessage, SerializeError> {
+		let mut writer = BoundedWriter::new(max_len as usize);
+
+		match self {
+			SinkTx::Json(_) => match serde_json::to_writer(&mut writer, message) {
+				// Safety - serde_json does not emit invalid UTF-8.
+				Ok(()) => Ok(SinkMessage::Json(unsafe { String::from_utf8_unchecked(writer.into_bytes()) })),
+				Err(err) if err.is_io() => Err(SerializeError::TooLarge),
+				Err(err) => Err(SerializeError::Invalid(err.to_string())),
+			},
+			#[cfg(feature = "ws-encodings")]
+			SinkTx::Encoded(encoding, _) => match encoding.to_writer(&mut writer, message) {
+				Ok(()) => Ok(SinkMessage::Encoded(writer.into_bytes())),
+				Err(jsonrpsee_types::encoding::EncodingError::Io(_)) => Err(SerializeError::TooLarge),
+				Err(err) => Err(SerializeError::Invalid(err.to_string())),
+			},
+		}
+	}
+
+	fn send(&self, message: SinkMessage) -> Result<(), mpsc::SendError> {
+		match (self, message) {
+			(SinkTx::Json(tx), SinkMessage::Json(json)) => tx.unbounded_send(json).map_err(|e| e.into_send_error()),
+			#[cfg(feature = "ws-encodings")]
+			(SinkTx::Encoded(_, tx), SinkMessage::Encoded(encoded)) => {
+				tx.unbounded_send(encoded).map_err(|e| e.into_send_error())
+			}
+			#[cfg(feature = "ws-encodings")]
+			_ => unreachable!("messages are serialized for the channel of the sink; qed"),
+		}
+	}
+
+	fn is_closed(&self) -> bool {
+		match self {
+			SinkTx::Json(tx) => tx.is_closed(),
+			#[cfg(feature = "ws-encodings")]
+			SinkTx::Encoded(_, tx) => tx.is_closed(),
+		}
+	}
+
+	fn close_channel(&self) {
+		match self {
+			SinkTx::Json(tx) => tx.close_channel(),
+			#[cfg(feature = "ws-encodings")]
+			SinkTx::Encoded(_, tx) => tx.close_channel(),
+		}
+	}
+}
+
 /// Sink that is used to send back the result to the server for a specific method.
 #[derive(Clone, Debug)]
 pub struct MethodSink {
 	/// Channel sender
-	tx: mpsc::UnboundedSender<String>,
+	tx: SinkTx,
 	/// Max response size in bytes for a executed call.
 	max_response_size: u32,
+	/// Access log record of the call this sink responds to.
+	access_log: Option<AccessLogEntry>,
+	/// Channel for streaming responses, `None` if the transport does not support them.
+	streams: Option<mpsc::UnboundedSender<StreamingResponse>>,
+	/// Slot keeping a copy of the response, if it's shared with other calls.
+	kept: Option<ResponseSlot>,
 }
 
 impl MethodSink {
 	/// Create a new `MethodSink` with unlimited response size
 	pub fn new(tx: mpsc::UnboundedSender<String>) -> Self {
-		MethodSink { tx, max_response_size: u32::MAX }
+		Self::new_with_limit(tx, u32::MAX)
 	}
 
 	/// Create a new `MethodSink` with a limited response size
 	pub fn new_with_limit(tx: mpsc::UnboundedSender<String>, max_response_size: u32) -> Self {
-		MethodSink { tx, max_response_size }
+		MethodSink { tx: SinkTx::Json(tx), max_response_size, access_log: None, streams: None, kept: None }
+	}
+
+	/// Create a new `MethodSink` with a limited response size, which serializes the messages in a binary `encoding`.
+	#[cfg(feature = "ws-encodings")]
+	pub fn new_encoded(encoding: WsEncoding, tx: mpsc::UnboundedSender<Vec<u8>>, max_response_size: u32) -> Self {
+		let tx = SinkTx::Encoded(encoding, tx);
+		MethodSink { tx, max_response_size, access_log: None, streams: None, kept: None }
+	}
+
+	/// Returns a `MethodSink` sending to the same channel which completes the access log `entry`
+	/// with the first response or error sent through it.
+	pub fn with_access_log(&self, entry: Option<AccessLogEntry>) -> Self {
+		MethodSink {
+			tx: self.tx.clone(),
+			max_response_size: self.max_response_size,
+			access_log: entry,
+			streams: self.streams.clone(),
+			kept: self.kept.clone(),
+		}
+	}
+
+	/// Returns a `MethodSink` sending to the same channel which also keeps a copy of the response or error sent
+	/// through it in the returned slot, to share it with other calls. The result is then serialized once, before
+	/// the size of the response is checked. The slot is shared if the sink already keeps its response.
+	pub fn keeping_response(&self) -> (Self, ResponseSlot) {
+		let slot = self.kept.clone().unwrap_or_default();
+		(MethodSink { kept: Some(slot.clone()), ..self.clone() }, slot)
+	}
+
+	/// Returns a `MethodSink` which sends the responses of [`MethodSink::send_streaming_response`] to `streams`
+	/// instead of buffering them.
+	pub fn with_streaming(mut self, streams: mpsc::UnboundedSender<StreamingResponse>) -> Self {
+		self.streams = Some(streams);
+		self
 	}
 
 	/// Returns whether this channel is closed without needing a context.
@@ -109,31 +339,51 @@ impl MethodSink {
 	/// Send a JSON-RPC response to the client. If the serialization of `result` exceeds `max_response_size`,
 	/// an error will be sent instead.
 	pub fn send_response(&self, id: Id, result: impl Serialize) -> bool {
-		let mut writer = BoundedWriter::new(self.max_response_size as usize);
+		match self.keep_result(&result) {
+			Some(Ok(result)) => self.write_response(id, &*result),
+			Some(Err(())) => self.send_error(id, ErrorCode::InternalError.into()),
+			None => self.write_response(id, result),
+		}
+	}
 
-		let json = match serde_json::to_writer(&mut writer, &Response::new(result, id.clone())) {
-			Ok(_) => {
-				// Safety - serde_json does not emit invalid UTF-8.
-				unsafe { String::from_utf8_unchecked(writer.into_bytes()) }
-			}
+	/// Serialize the `result` and keep it if the sink keeps its response. `None` if it doesn't.
+	fn keep_result(&self, result: &impl Serialize) -> Option<Result<Arc<RawValue>, ()>> {
+		let slot = self.kept.as_ref()?;
+		let result: Arc<RawValue> = match serde_json::value::to_raw_value(result) {
+			Ok(result) => result.into(),
 			Err(err) => {
 				tracing::error!("Error serializing response: {:?}", err);
+				return Some(Err(()));
+			}
+		};
+		slot.keep(KeptResponse::Result(result.clone()));
+		Some(Ok(result))
+	}
 
-				if err.is_io() {
-					let data = to_json_raw_value(&format!("Exceeded max limit {}", self.max_response_size)).ok();
-					let err = ErrorObject {
-						code: ErrorCode::ServerError(OVERSIZED_RESPONSE_CODE),
-						message: OVERSIZED_RESPONSE_MSG.into(),
-						data: data.as_deref(),
-					};
-					return self.send_error(id, err);
-				} else {
-					return self.send_error(id, ErrorCode::InternalError.into());
-				}
+	fn write_response(&self, id: Id, result: impl Serialize) -> bool {
+		let message = match self.tx.serialize(&Response::new(result, id.clone()), self.max_response_size) {
+			Ok(message) => message,
+			Err(SerializeError::TooLarge) => {
+				tracing::error!("Error serializing response: exceeded max limit {}", self.max_response_size);
+				let data = to_json_raw_value(&format!("Exceeded max limit {}", self.max_response_size)).ok();
+				let err = ErrorObject {
+					code: ErrorCode::ServerError(OVERSIZED_RESPONSE_CODE),
+					message: OVERSIZED_RESPONSE_MSG.into(),
+					data: data.as_deref(),
+				};
+				return self.send_error(id, err);
+			}
+			Err(SerializeError::Invalid(err)) => {
+				tracing::error!("Error serializing response: {:?}", err);
+				return self.send_error(id, ErrorCode::InternalError.into());
 			}
 		};
 
-		if let Err(err) = self.tx.unbounded_send(json) {
+		if let Some(entry) = &self.access_log {
+			entry.finish(None, message.len());
+		}
+
+		if let Err(err) = self.tx.send(message) {
 			tracing::error!("Error sending response to the client: {:?}", err);
 			false
 		} else {
@@ -141,10 +391,50 @@ impl MethodSink {
 		}
 	}
 
+	/// Send a JSON-RPC response to the client without serializing it up front. The transport serializes the
+	/// response while writing it out, and aborts it once it exceeds `max_response_size`. The `claimed` resources
+	/// are released once the response is written.
+	///
+	/// Falls back to [`MethodSink::send_response`] if the transport does not support streaming responses, which is
+	/// the case of WebSocket connections using a compression extension or a binary encoding.
+	pub fn send_streaming_response<R>(&self, id: Id, result: R, claimed: Option<ResourceGuard>) -> bool
+	where
+		R: Serialize + Send + 'static,
+	{
+		let streams = match &self.streams {
+			Some(streams) => streams,
+			None => return self.send_response(id, result),
+		};
+
+		let id = id.into_owned();
+		let serialize: SerializeFn = match self.keep_result(&result) {
+			Some(Ok(result)) => Box::new(move |writer| serde_json::to_writer(writer, &Response::new(&*result, id))),
+			Some(Err(())) => return self.send_error(id, ErrorCode::InternalError.into()),
+			None => Box::new(move |writer| serde_json::to_writer(writer, &Response::new(result, id))),
+		};
+		let response = StreamingResponse {
+			serialize,
+			max_response_size: self.max_response_size,
+			access_log: self.access_log.clone(),
+			_claimed: claimed,
+		};
+
+		if let Err(err) = streams.unbounded_send(response) {
+			tracing::error!("Error sending streaming response to the client: {:?}", err);
+			false
+		} else {
+			true
+		}
+	}
+
 	/// Send a JSON-RPC error to the client
 	pub fn send_error(&self, id: Id, error: ErrorObject) -> bool {
-		let json = match serde_json::to_string(&RpcError::new(error, id)) {
-			Ok(json) => json,
+		if let Some(slot) = &self.kept {
+			slot.keep(KeptResponse::Error(error.clone().into()));
+		}
+		let code = error.code.code();
+		let message = match self.tx.serialize(&RpcError::new(error, id), u32::MAX) {
+			Ok(message) => message,
 			Err(err) => {
 				tracing::error!("Error serializing error message: {:?}", err);
 
@@ -152,7 +442,11 @@ impl MethodSink {
 			}
 		};
 
-		if let Err(err) = self.tx.unbounded_send(json) {
+		if let Some(entry) = &self.access_log {
+			entry.finish(Some(code), message.len());
+		}
+
+		if let Err(err) = self.tx.send(message) {
 			tracing::error!("Could not send error response to the client: {:?}", err)
 		}
 
@@ -161,26 +455,41 @@ impl MethodSink {
 
 	/// Helper for sending the general purpose `Error` as a JSON-RPC errors to the client
 	pub fn send_call_error(&self, id: Id, err: Error) -> bool {
-		let (code, message, data) = match err {
-			Error::Call(CallError::InvalidParams(e)) => (ErrorCode::InvalidParams, e.to_string(), None),
-			Error::Call(CallError::Failed(e)) => {
-				(ErrorCode::ServerError(CALL_EXECUTION_FAILED_CODE), e.to_string(), None)
+		let err = err.into_error_object();
+
+		self.send_error(id, err.as_error_object())
+	}
+
+	/// Send a raw JSON-RPC message to the client, `MethodSink` does not check verify the validity
+	/// of the JSON being sent. The message is transcoded if the sink uses a binary encoding.
+	pub fn send_raw(&self, raw_json: String) -> Result<(), Error> {
+		let message = match &self.tx {
+			SinkTx::Json(_) => SinkMessage::Json(raw_json),
+			#[cfg(feature = "ws-encodings")]
+			SinkTx::Encoded(encoding, _) => {
+				SinkMessage::Encoded(encoding.encode(&raw_json).map_err(|err| Error::Transport(err.into()))?)
 			}
-			Error::Call(CallError::Custom { code, message, data }) => (code.into(), message, data),
-			// This should normally not happen because the most common use case is to
-			// return `Error::Call` in `register_async_method`.
-			e => (ErrorCode::ServerError(UNKNOWN_ERROR_CODE), e.to_string(), None),
 		};
+		self.send_message(message)
+	}
 
-		let err = ErrorObject { code, message: message.into(), data: data.as_deref() };
+	/// Send a message already serialized in the binary encoding of the sink, e.g. a batch response.
+	#[cfg(feature = "ws-encodings")]
+	pub fn send_encoded(&self, encoded: Vec<u8>) -> Result<(), Error> {
+		match &self.tx {
+			SinkTx::Encoded(..) => self.send_message(SinkMessage::Encoded(encoded)),
+			SinkTx::Json(_) => Err(Error::Custom("The sink does not use a binary encoding".into())),
+		}
+	}
 
-		self.send_error(id, err)
+	/// Serialize a message for the channel of this sink.
+	pub(crate) fn serialize_message(&self, message: &impl Serialize) -> Result<SinkMessage, Error> {
+		self.tx.serialize(message, u32::MAX).map_err(|err| Error::Custom(format!("{:?}", err)))
 	}
 
-	/// Send a raw JSON-RPC message to the client, `MethodSink` does not check verify the validity
-	/// of the JSON being sent.
-	pub fn send_raw(&self, raw_json: String) -> Result<(), mpsc::TrySendError<String>> {
-		self.tx.unbounded_send(raw_json)
+	/// Send a message serialized by [`MethodSink::serialize_message`].
+	pub(crate) fn send_message(&self, message: SinkMessage) -> Result<(), Error> {
+		self.tx.send(message).map_err(Into::into)
 	}
 
 	/// Close the channel for any further messages.
@@ -218,7 +527,11 @@ pub async fn collect_batch_response(rx: mpsc::UnboundedReceiver<String>) -> Stri
 
 #[cfg(test)]
 mod tests {
-	use super::{BoundedWriter, Id, Response};
+	use super::{BoundedWriter, Id, KeptResponse, MethodSink, Response};
+	use crate::server::resource_limiting::Resources;
+	use futures_channel::mpsc;
+	use futures_util::stream::StreamExt;
+	use jsonrpsee_types::v2::ErrorCode;
 
 	#[test]
 	fn bounded_serializer_work() {
@@ -235,4 +548,67 @@ mod tests {
 		// NOTE: `"` is part of the serialization so 101 characters.
 		assert!(serde_json::to_writer(&mut writer, &"x".repeat(99)).is_err());
 	}
+
+	#[tokio::test]
+	async fn streaming_response_works() {
+		let (tx, _rx) = mpsc::unbounded();
+		let (streams_tx, mut streams_rx) = mpsc::unbounded();
+		let sink = MethodSink::new_with_limit(tx, 100).with_streaming(streams_tx);
+
+		assert!(sink.send_streaming_response(Id::Number(1), "success", None));
+		assert!(sink.send_streaming_response(Id::Number(2), "x".repeat(100), None));
+
+		let mut buf = Vec::new();
+		let response = streams_rx.next().await.unwrap();
+		assert_eq!(response.write_to(&mut buf).unwrap(), buf.len());
+		assert_eq!(String::from_utf8(buf).unwrap(), r#"{"jsonrpc":"2.0","result":"success","id":1}"#);
+
+		let mut buf = Vec::new();
+		let response = streams_rx.next().await.unwrap();
+		assert_eq!(response.write_to(&mut buf).unwrap_err().kind(), std::io::ErrorKind::OutOfMemory);
+		assert!(buf.len() <= 100);
+	}
+
+	#[tokio::test]
+	async fn streaming_response_without_transport_support_is_buffered() {
+		let (tx, mut rx) = mpsc::unbounded();
+		let sink = MethodSink::new(tx);
+
+		assert!(sink.send_streaming_response(Id::Number(1), "success", None));
+		assert_eq!(rx.next().await.unwrap(), r#"{"jsonrpc":"2.0","result":"success","id":1}"#);
+	}
+
+	#[tokio::test]
+	async fn streaming_response_holds_claimed_resources() {
+		let mut resources = Resources::default();
+		resources.register("cpu", 1, 1).unwrap();
+		let (tx, _rx) = mpsc::unbounded();
+		let (streams_tx, mut streams_rx) = mpsc::unbounded();
+		let sink = MethodSink::new(tx).with_streaming(streams_tx);
+
+		let claimed = resources.claim(resources.defaults).unwrap();
+		assert!(sink.send_streaming_response(Id::Number(1), "success", Some(claimed)));
+		assert!(resources.claim(resources.defaults).is_err());
+
+		streams_rx.next().await.unwrap().write_to(Vec::new()).unwrap();
+		assert!(resources.claim(resources.defaults).is_ok());
+	}
+
+	#[tokio::test]
+	async fn kept_response_is_sent_with_the_limit_of_each_sink() {
+		let (tx, mut rx) = mpsc::unbounded();
+		let (small_tx, mut small_rx) = mpsc::unbounded();
+		let (sink, slot) = MethodSink::new(tx).keeping_response();
+		let small_sink = MethodSink::new_with_limit(small_tx, 50);
+
+		assert!(sink.send_response(Id::Number(1), "x".repeat(20)));
+		let kept = slot.get().unwrap();
+		assert!(matches!(&kept, KeptResponse::Result(result) if result.get().len() == 22));
+		assert!(!kept.send_to(&small_sink, Id::Number(3)));
+		assert_eq!(rx.next().await.unwrap(), format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, "x".repeat(20)));
+		assert!(small_rx.next().await.unwrap().contains(r#""id":3"#));
+
+		assert!(!sink.send_error(Id::Number(4), ErrorCode::InvalidParams.into()));
+		assert!(matches!(slot.get(), Some(KeptResponse::Error(err)) if err.code == ErrorCode::InvalidParams));
+	}
 }
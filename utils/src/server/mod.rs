@@ -26,6 +26,10 @@
 
 //! Shared modules for the JSON-RPC servers.
 
+/// Structured access logging, one record per JSON-RPC call.
+pub mod access_log;
+/// Forwarding of methods and subscriptions to an upstream server through a client.
+pub mod gateway;
 /// Helpers.
 pub mod helpers;
 /// Resource limiting. Create generic "resources" and configure their limits to ensure servers are not overloaded.
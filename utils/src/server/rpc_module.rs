@@ -24,19 +24,24 @@
 // IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
 // DEALINGS IN THE SOFTWARE.
 
-use crate::server::helpers::MethodSink;
+use crate::server::helpers::{KeptResponse, MethodSink, ResponseSlot, SinkMessage};
 use crate::server::resource_limiting::{ResourceGuard, ResourceTable, ResourceVec, Resources};
 use beef::Cow;
 use futures_channel::{mpsc, oneshot};
-use futures_util::{future::BoxFuture, FutureExt, StreamExt};
+use futures_util::{
+	future::{self, BoxFuture},
+	FutureExt, StreamExt,
+};
 use jsonrpsee_types::error::{SubscriptionClosed, SubscriptionClosedReason};
 use jsonrpsee_types::to_json_raw_value;
 use jsonrpsee_types::v2::error::{invalid_subscription_err, CALL_EXECUTION_FAILED_CODE};
 use jsonrpsee_types::{
+	cache::{canonical_params, CachePolicy, ResponseCache},
 	error::Error,
+	introspection::{MethodDescription, MethodType, ParamDescription, ParamKind, RpcMethods, RPC_METHODS},
 	traits::ToRpcParams,
 	v2::{
-		ErrorCode, Id, Params, Request, Response, SubscriptionId as RpcSubscriptionId, SubscriptionPayload,
+		ErrorCode, Id, Params, ParamsSer, Request, Response, SubscriptionId as RpcSubscriptionId, SubscriptionPayload,
 		SubscriptionResponse,
 	},
 	DeserializeOwned,
@@ -44,8 +49,10 @@ use jsonrpsee_types::{
 
 use parking_lot::Mutex;
 use rustc_hash::FxHashMap;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
 use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug};
 use std::future::Future;
 use std::ops::{Deref, DerefMut};
@@ -66,8 +73,15 @@ pub type ConnectionId = usize;
 pub type SubscriptionId = u64;
 /// Raw RPC response.
 pub type RawRpcResponse = (String, mpsc::UnboundedReceiver<String>, mpsc::UnboundedSender<String>);
+/// Field of the params that selects the version of a versioned method, see [`Methods::register_version`].
+pub const VERSION_PARAM: &str = "version";
 
 type Subscribers = Arc<Mutex<FxHashMap<SubscriptionKey, (MethodSink, oneshot::Receiver<()>)>>>;
+/// Call waiting for the identical call in flight to respond: the sink and the ID of its request, and the channel
+/// notified once the response was sent to it.
+type Follower = (MethodSink, Id<'static>, oneshot::Sender<bool>);
+/// Followers of the calls in flight to a single-flight method, by the canonical form of the params of the calls.
+type Flights = Arc<Mutex<FxHashMap<String, Vec<Follower>>>>;
 
 /// Represent a unique subscription entry based on [`SubscriptionId`] and [`ConnectionId`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -100,6 +114,14 @@ enum MethodResources {
 pub struct MethodCallback {
 	callback: MethodKind,
 	resources: MethodResources,
+	/// Reported by the introspection method, the name is filled in when it's registered.
+	description: MethodDescription,
+	/// For unversioned names of versioned methods, the method registered for each version.
+	versions: BTreeMap<u32, &'static str>,
+	/// Cache of the responses of the method, if they are cached.
+	cache: Option<ResponseCache>,
+	/// Calls in flight, if the identical calls to the method share one execution.
+	single_flight: Option<Flights>,
 }
 
 /// Result of a method, either direct value or a future of one.
@@ -137,16 +159,42 @@ impl<'a> MethodResourcesBuilder<'a> {
 impl<'a> Drop for MethodResourcesBuilder<'a> {
 	fn drop(&mut self) {
 		self.callback.resources = MethodResources::Uninitialized(self.build[..].into());
+		self.callback.description.resources =
+			self.build.iter().map(|&(label, units)| (label.to_owned(), units)).collect();
 	}
 }
 
 impl MethodCallback {
 	fn new_sync(callback: SyncMethod) -> Self {
-		MethodCallback { callback: MethodKind::Sync(callback), resources: MethodResources::Uninitialized([].into()) }
+		MethodCallback {
+			callback: MethodKind::Sync(callback),
+			resources: MethodResources::Uninitialized([].into()),
+			description: MethodDescription::default(),
+			versions: BTreeMap::new(),
+			cache: None,
+			single_flight: None,
+		}
 	}
 
 	fn new_async(callback: AsyncMethod<'static>) -> Self {
-		MethodCallback { callback: MethodKind::Async(callback), resources: MethodResources::Uninitialized([].into()) }
+		MethodCallback {
+			callback: MethodKind::Async(callback),
+			resources: MethodResources::Uninitialized([].into()),
+			description: MethodDescription::default(),
+			versions: BTreeMap::new(),
+			cache: None,
+			single_flight: None,
+		}
+	}
+
+	fn with_description(mut self, description: MethodDescription) -> Self {
+		self.description = description;
+		self
+	}
+
+	/// Returns whether the method is deprecated, see [`Methods::deprecate`].
+	pub fn is_deprecated(&self) -> bool {
+		self.description.deprecated
 	}
 
 	/// Attempt to claim resources prior to executing a method. On success returns a guard that releases
@@ -204,6 +252,116 @@ impl MethodCallback {
 	}
 }
 
+impl MethodCallback {
+	/// Join the identical call in flight if the method is single-flight, otherwise lead the call, and the new
+	/// flight if the method is single-flight.
+	fn join_flight(&self, sink: &MethodSink, req: &Request) -> Flight {
+		let (flights, key) = match (&self.single_flight, canonical_params(req.params.map(ParamsSer::Raw))) {
+			(Some(flights), Some(key)) => (flights, key),
+			_ => return Flight::Lead(None),
+		};
+
+		match flights.lock().entry(key) {
+			Entry::Occupied(mut followers) => {
+				let (tx, rx) = oneshot::channel();
+				followers.get_mut().push((sink.clone(), req.id.clone().into_owned(), tx));
+				Flight::Joined(MethodResult::Async(rx.map(|sent| sent.unwrap_or(false)).boxed()))
+			}
+			Entry::Vacant(vacant) => {
+				let leader = FlightLeader { flights: flights.clone(), key: vacant.key().clone(), landed: false };
+				vacant.insert(Vec::new());
+				Flight::Lead(Some(leader))
+			}
+		}
+	}
+
+	/// Execute the callback, sending the response to the followers of the flight too if it leads one.
+	fn execute_in_flight(
+		&self,
+		sink: &MethodSink,
+		req: Request<'_>,
+		conn_id: ConnectionId,
+		claimed: Option<ResourceGuard>,
+		leader: Option<FlightLeader>,
+	) -> MethodResult<bool> {
+		let leader = match leader {
+			Some(leader) => leader,
+			None => return self.execute(sink, req, conn_id, claimed),
+		};
+
+		let (sink, slot) = sink.keeping_response();
+		match self.execute(&sink, req, conn_id, claimed) {
+			MethodResult::Sync(sent) => {
+				leader.land(&slot);
+				MethodResult::Sync(sent)
+			}
+			MethodResult::Async(fut) => MethodResult::Async(
+				async move {
+					let sent = fut.await;
+					leader.land(&slot);
+					sent
+				}
+				.boxed(),
+			),
+		}
+	}
+}
+
+/// Call to a method, see [`MethodCallback::join_flight`].
+enum Flight {
+	/// The call executes the method, leading the flight of the identical calls if the method is single-flight.
+	Lead(Option<FlightLeader>),
+	/// The call joined the identical call in flight, its result is available once the call in flight responds.
+	Joined(MethodResult<bool>),
+}
+
+/// Leader of a flight of identical calls to a single-flight method, the call executing the method. The followers
+/// are answered with an internal error if it's dropped before landing, or if the method didn't send a response
+/// they can share.
+struct FlightLeader {
+	flights: Flights,
+	key: String,
+	landed: bool,
+}
+
+impl FlightLeader {
+	/// Send the response the leader kept in the `slot` to the followers, each sink checking its own size limit.
+	fn land(mut self, slot: &ResponseSlot) {
+		let response = match slot.get() {
+			Some(response) => response,
+			// Answered with an internal error on drop.
+			None => return,
+		};
+
+		for (sink, id, tx) in self.take_followers() {
+			let _ = tx.send(response.send_to(&sink, id));
+		}
+	}
+
+	/// Answer the followers with the error `code`, the call failed before it was executed.
+	fn abort(mut self, code: ErrorCode) {
+		for (sink, id, tx) in self.take_followers() {
+			let _ = tx.send(sink.send_error(id, code.into()));
+		}
+	}
+
+	/// Take the followers and end the flight, the next identical call starts a new one.
+	fn take_followers(&mut self) -> Vec<Follower> {
+		if std::mem::replace(&mut self.landed, true) {
+			return Vec::new();
+		}
+		self.flights.lock().remove(&self.key).unwrap_or_default()
+	}
+}
+
+impl Drop for FlightLeader {
+	fn drop(&mut self) {
+		for (sink, id, tx) in self.take_followers() {
+			let _ = tx.send(sink.send_error(id, ErrorCode::InternalError.into()));
+		}
+	}
+}
+
 impl Debug for MethodKind {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		match self {
@@ -282,23 +440,221 @@ impl Methods {
 	}
 
 	/// Merge two [`Methods`]'s by adding all [`MethodCallback`]s from `other` into `self`.
-	/// Fails if any of the methods in `other` is present already.
+	/// Fails if any of the methods in `other` is present already, except for the unversioned names of versioned
+	/// methods whose versions are combined, see [`Methods::register_version`].
 	pub fn merge(&mut self, other: impl Into<Methods>) -> Result<(), Error> {
 		let mut other = other.into();
-
-		for name in other.callbacks.keys() {
-			self.verify_method_name(name)?;
+		let mut versioned = Vec::new();
+
+		for (&name, callback) in other.callbacks.iter() {
+			match self.callbacks.get(name) {
+				Some(existing) if !existing.versions.is_empty() && !callback.versions.is_empty() => {
+					let mut versions = existing.versions.clone();
+					for (&version, &versioned_method) in callback.versions.iter() {
+						if versions.insert(version, versioned_method).is_some() {
+							return Err(Error::MethodAlreadyRegistered(versioned_method.into()));
+						}
+					}
+					versioned.push((name, versions));
+				}
+				Some(_) => return Err(Error::MethodAlreadyRegistered(name.into())),
+				None => {}
+			}
 		}
 
 		let callbacks = self.mut_callbacks();
 
 		for (name, callback) in other.mut_callbacks().drain() {
-			callbacks.insert(name, callback);
+			// The unversioned names present on both sides are registered again below with the combined versions.
+			if versioned.iter().any(|&(versioned_name, _)| versioned_name == name) {
+				continue;
+			}
+			match callbacks.entry(name) {
+				Entry::Occupied(_) => return Err(Error::MethodAlreadyRegistered(name.into())),
+				Entry::Vacant(vacant) => {
+					vacant.insert(callback);
+				}
+			}
+		}
+
+		for (name, versions) in versioned {
+			self.route_versions(name, versions)?;
+		}
+
+		Ok(())
+	}
+
+	/// Register a versioned method, e.g. `v2_foo`, as version `2` of the method named after its version prefix,
+	/// e.g. `foo`.
+	///
+	/// The versioned method stays callable by its own name. The unversioned name is an alias of the latest version
+	/// registered, unless the params of the call are an object with a [`VERSION_PARAM`] field, e.g.
+	/// `{"version": 1}` or `{"version": "v1"}`, which selects the version to call instead.
+	/// Register the versions once their description is complete, like aliases they don't follow later changes.
+	pub fn register_version(&mut self, versioned_method: &'static str) -> Result<(), Error> {
+		let (version, method_name) = match split_version(versioned_method) {
+			Some(split) => split,
+			None => return Err(Error::MissingVersionPrefix(versioned_method.into())),
+		};
+		if !self.callbacks.contains_key(versioned_method) {
+			return Err(Error::MethodNotFound(versioned_method.into()));
+		}
+
+		let mut versions = match self.callbacks.get(method_name) {
+			Some(existing) if !existing.versions.is_empty() => existing.versions.clone(),
+			Some(_) => return Err(Error::MethodAlreadyRegistered(method_name.into())),
+			None => BTreeMap::new(),
+		};
+		if versions.insert(version, versioned_method).is_some() {
+			return Err(Error::MethodAlreadyRegistered(versioned_method.into()));
+		}
+
+		self.route_versions(method_name, versions)
+	}
+
+	/// (Re)register the unversioned name of a versioned method as an alias of the latest version.
+	fn route_versions(
+		&mut self,
+		method_name: &'static str,
+		versions: BTreeMap<u32, &'static str>,
+	) -> Result<(), Error> {
+		let latest = versions.values().next_back().copied().expect("A versioned method has one version at least; qed");
+		let mut callback = match self.callbacks.get(latest) {
+			Some(callback) => callback.clone(),
+			None => return Err(Error::MethodNotFound(latest.into())),
+		};
+
+		callback.description.alias_of = Some(latest.to_owned());
+		callback.description.versions =
+			versions.iter().map(|(&version, &versioned_method)| (version, versioned_method.to_owned())).collect();
+		callback.versions = versions;
+
+		self.mut_callbacks().insert(method_name, callback);
+
+		Ok(())
+	}
+
+	/// Mark a method as deprecated. Calls to deprecated methods are logged and reported to the middleware.
+	pub fn deprecate(&mut self, method_name: &str) -> Result<(), Error> {
+		match self.mut_callbacks().get_mut(method_name) {
+			Some(callback) => {
+				callback.description.deprecated = true;
+				Ok(())
+			}
+			None => Err(Error::MethodNotFound(method_name.into())),
+		}
+	}
+
+	/// Cache the successful responses of a method in `cache` following `policy`, see [`ResponseCache`]. The
+	/// method isn't called when a cached response to the call is found.
+	///
+	/// The aliases of the method share its cached responses, whether they are registered before or after.
+	pub fn cache_method(&mut self, method_name: &str, cache: &ResponseCache, policy: CachePolicy) -> Result<(), Error> {
+		let (name, ty) = match self.callbacks.get_key_value(method_name) {
+			Some((&name, callback)) => (name, callback.description.ty),
+			None => return Err(Error::MethodNotFound(method_name.into())),
+		};
+		if matches!(ty, MethodType::Subscription | MethodType::Unsubscribe) {
+			return Err(Error::Custom(format!("Subscription method {} can't be cached", name)));
+		}
+
+		cache.cache_method(name, policy);
+		for callback in self.method_and_aliases(name) {
+			callback.callback = match callback.callback.clone() {
+				MethodKind::Sync(method) => {
+					let cache = cache.clone();
+					MethodKind::Sync(Arc::new(move |id, params, sink, conn_id| {
+						let cache_params: Option<Box<RawValue>> = params.parse().unwrap_or_default();
+						if let Some(result) = cache.get(name, cache_params.as_deref().map(ParamsSer::Raw)) {
+							return sink.send_response(id, &*result);
+						}
+
+						let (sink, slot) = sink.keeping_response();
+						let sent = method(id, params, &sink, conn_id);
+						cache_kept_result(&cache, name, cache_params.as_deref(), &slot);
+						sent
+					}))
+				}
+				MethodKind::Async(method) => {
+					let cache = cache.clone();
+					MethodKind::Async(Arc::new(move |id, params, sink, claimed| {
+						let cache_params: Option<Box<RawValue>> = params.parse().unwrap_or_default();
+						if let Some(result) = cache.get(name, cache_params.as_deref().map(ParamsSer::Raw)) {
+							return future::ready(sink.send_response(id, &*result)).boxed();
+						}
+
+						let cache = cache.clone();
+						let (sink, slot) = sink.keeping_response();
+						let fut = method(id, params, sink, claimed);
+						async move {
+							let sent = fut.await;
+							cache_kept_result(&cache, name, cache_params.as_deref(), &slot);
+							sent
+						}
+						.boxed()
+					}))
+				}
+			};
+			callback.cache = Some(cache.clone());
 		}
 
 		Ok(())
 	}
 
+	/// Returns the callbacks of the method and of its aliases.
+	fn method_and_aliases<'a>(&'a mut self, method_name: &'a str) -> impl Iterator<Item = &'a mut MethodCallback> {
+		self.mut_callbacks().iter_mut().filter_map(move |(&name, callback)| {
+			let is_alias = callback.description.alias_of.as_deref() == Some(method_name);
+			(name == method_name || is_alias).then_some(callback)
+		})
+	}
+
+	/// Returns the cache of the responses of a method, if they are cached.
+	pub fn response_cache(&self, method_name: &str) -> Option<&ResponseCache> {
+		self.callbacks.get(method_name).and_then(|callback| callback.cache.as_ref())
+	}
+
+	/// Make the concurrent identical calls to a method, with the same params in canonical form, share one execution
+	/// of the method whose response is sent to all of them. The calls joining the call in flight don't claim
+	/// resources.
+	///
+	/// The aliases of the method share its calls in flight, whether they are registered before or after.
+	pub fn single_flight(&mut self, method_name: &str) -> Result<(), Error> {
+		let flights = match self.callbacks.get(method_name) {
+			Some(callback) if matches!(callback.description.ty, MethodType::Subscription | MethodType::Unsubscribe) => {
+				return Err(Error::Custom(format!("Subscription method {} can't be single-flight", method_name)));
+			}
+			Some(callback) => callback.single_flight.clone().unwrap_or_default(),
+			None => return Err(Error::MethodNotFound(method_name.into())),
+		};
+
+		for callback in self.method_and_aliases(method_name) {
+			callback.single_flight = Some(flights.clone());
+		}
+		Ok(())
+	}
+
+	/// Returns the method a request is routed to along with its name, which for versioned methods may depend on
+	/// the params. Calls to deprecated methods are logged.
+	pub fn route(&self, req: &Request) -> Option<(&'static str, &MethodCallback)> {
+		let (name, callback) = self.resolve(req)?;
+		if callback.is_deprecated() {
+			tracing::warn!("Call to deprecated method: {}", name);
+		}
+		Some((name, callback))
+	}
+
+	fn resolve(&self, req: &Request) -> Option<(&'static str, &MethodCallback)> {
+		let (&name, callback) = self.callbacks.get_key_value(&*req.method)?;
+		match req.params.filter(|_| !callback.versions.is_empty()).and_then(requested_version) {
+			Some(version) => {
+				let versioned_method = version.and_then(|version| callback.versions.get(&version))?;
+				self.callbacks.get_key_value(versioned_method).map(|(&name, callback)| (name, callback))
+			}
+			None => Some((name, callback)),
+		}
+	}
+
 	/// Returns the method callback.
 	pub fn method(&self, method_name: &str) -> Option<&MethodCallback> {
 		self.callbacks.get(method_name)
@@ -313,8 +669,11 @@ impl Methods {
 	/// Attempt to execute a callback, sending the resulting JSON (success or error) to the specified sink.
 	pub fn execute(&self, sink: &MethodSink, req: Request, conn_id: ConnectionId) -> MethodResult<bool> {
 		tracing::trace!("[Methods::execute] Executing request: {:?}", req);
-		match self.callbacks.get(&*req.method) {
-			Some(callback) => callback.execute(sink, req, conn_id, None),
+		match self.route(&req) {
+			Some((_, callback)) => match callback.join_flight(sink, &req) {
+				Flight::Lead(leader) => callback.execute_in_flight(sink, req, conn_id, None, leader),
+				Flight::Joined(joined) => joined,
+			},
 			None => {
 				sink.send_error(req.id, ErrorCode::MethodNotFound.into());
 				MethodResult::Sync(false)
@@ -322,8 +681,8 @@ impl Methods {
 		}
 	}
 
-	/// Attempt to execute a callback while checking that the call does not exhaust the available resources,
-	// sending the resulting JSON (success or error) to the specified sink.
+	/// Attempt to execute the callback the request is routed to, see [`Methods::route`], while checking that the call
+	/// does not exhaust the available resources, sending the resulting JSON (success or error) to the specified sink.
 	pub fn execute_with_resources<'r>(
 		&self,
 		sink: &MethodSink,
@@ -331,16 +690,44 @@ impl Methods {
 		conn_id: ConnectionId,
 		resources: &Resources,
 	) -> Result<(&'static str, MethodResult<bool>), Cow<'r, str>> {
-		tracing::trace!("[Methods::execute_with_resources] Executing request: {:?}", req);
-		match self.callbacks.get_key_value(&*req.method) {
-			Some((&name, callback)) => match callback.claim(&req.method, resources) {
-				Ok(guard) => Ok((name, callback.execute(sink, req, conn_id, Some(guard)))),
-				Err(err) => {
-					tracing::error!("[Methods::execute_with_resources] failed to lock resources: {:?}", err);
-					sink.send_error(req.id, ErrorCode::ServerIsBusy.into());
-					Ok((name, MethodResult::Sync(false)))
+		let route = self.route(&req);
+		self.execute_routed(sink, req, route, conn_id, resources)
+	}
+
+	/// Same as [`Methods::execute_with_resources`], for a request already routed with [`Methods::route`].
+	pub fn execute_routed<'r>(
+		&self,
+		sink: &MethodSink,
+		req: Request<'r>,
+		route: Option<(&'static str, &MethodCallback)>,
+		conn_id: ConnectionId,
+		resources: &Resources,
+	) -> Result<(&'static str, MethodResult<bool>), Cow<'r, str>> {
+		debug_assert_eq!(
+			route.map(|(name, _)| name),
+			self.resolve(&req).map(|(name, _)| name),
+			"The route doesn't match the request"
+		);
+		tracing::trace!("[Methods::execute_routed] Executing request: {:?}", req);
+		match route {
+			Some((name, callback)) => {
+				// The calls joining the call in flight don't claim resources.
+				let leader = match callback.join_flight(sink, &req) {
+					Flight::Lead(leader) => leader,
+					Flight::Joined(joined) => return Ok((name, joined)),
+				};
+				match callback.claim(name, resources) {
+					Ok(guard) => Ok((name, callback.execute_in_flight(sink, req, conn_id, Some(guard), leader))),
+					Err(err) => {
+						tracing::error!("[Methods::execute_routed] failed to lock resources: {:?}", err);
+						if let Some(leader) = leader {
+							leader.abort(ErrorCode::ServerIsBusy);
+						}
+						sink.send_error(req.id, ErrorCode::ServerIsBusy.into());
+						Ok((name, MethodResult::Sync(false)))
+					}
 				}
-			},
+			}
 			None => {
 				sink.send_error(req.id, ErrorCode::MethodNotFound.into());
 				Err(req.method)
@@ -470,6 +857,56 @@ impl Methods {
 	pub fn method_names(&self) -> impl Iterator<Item = &'static str> + '_ {
 		self.callbacks.keys().copied()
 	}
+
+	/// Describe the params of a method and whether it's deprecated, to be reported by the introspection method.
+	/// Aliases registered afterwards inherit the description.
+	pub fn describe(
+		&mut self,
+		method_name: &str,
+		deprecated: bool,
+		param_kind: ParamKind,
+		params: Vec<ParamDescription>,
+	) -> Result<(), Error> {
+		let callback = match self.mut_callbacks().get_mut(method_name) {
+			Some(callback) => callback,
+			None => return Err(Error::MethodNotFound(method_name.into())),
+		};
+
+		callback.description.deprecated = deprecated;
+		callback.description.param_kind = Some(param_kind);
+		callback.description.params = Some(params);
+
+		Ok(())
+	}
+
+	/// Register the [`RPC_METHODS`] introspection method, which returns a [`RpcMethods`] describing every method,
+	/// alias and subscription registered so far, itself included. Call it once all the other methods are in place,
+	/// i.e. after merging the modules served together.
+	pub fn register_introspection(&mut self) -> Result<(), Error> {
+		self.verify_method_name(RPC_METHODS)?;
+
+		let introspection =
+			MethodDescription { param_kind: Some(ParamKind::Array), params: Some(Vec::new()), ..Default::default() };
+
+		let mut methods: Vec<_> = self
+			.callbacks
+			.iter()
+			.map(|(&name, callback)| (name, &callback.description))
+			.chain(std::iter::once((RPC_METHODS, &introspection)))
+			.map(|(name, description)| MethodDescription { name: name.to_owned(), ..description.clone() })
+			.collect();
+		methods.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let response = to_json_raw_value(&RpcMethods { methods })?;
+
+		self.mut_callbacks().insert(
+			RPC_METHODS,
+			MethodCallback::new_sync(Arc::new(move |id, _, sink, _| sink.send_response(id, &response)))
+				.with_description(introspection),
+		);
+
+		Ok(())
+	}
 }
 
 impl<Context> Deref for RpcModule<Context> {
@@ -566,6 +1003,44 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 		Ok(MethodResourcesBuilder { build: ResourceVec::new(), callback })
 	}
 
+	/// Register a new asynchronous RPC method whose result is serialized while the server writes the response,
+	/// instead of being buffered in memory first. Use it for methods returning large results.
+	///
+	/// The HTTP server sends such responses with chunked transfer encoding and the WebSocket server as fragmented
+	/// messages, and the resources claimed by the call are held until the response is written. Because part of the
+	/// response has already been sent by then, a response that turns out to exceed the maximum response size is
+	/// aborted rather than replaced by an error: the HTTP body is cut short and the WebSocket connection is closed.
+	/// In batches and on WebSocket connections using compression or a binary encoding, the response is buffered as
+	/// usual.
+	pub fn register_streaming_method<R, Fun, Fut>(
+		&mut self,
+		method_name: &'static str,
+		callback: Fun,
+	) -> Result<MethodResourcesBuilder, Error>
+	where
+		R: Serialize + Send + 'static,
+		Fut: Future<Output = Result<R, Error>> + Send,
+		Fun: (Fn(Params<'static>, Arc<Context>) -> Fut) + Copy + Send + Sync + 'static,
+	{
+		let ctx = self.ctx.clone();
+		let callback = self.methods.verify_and_insert(
+			method_name,
+			MethodCallback::new_async(Arc::new(move |id, params, sink, claimed| {
+				let ctx = ctx.clone();
+				let future = async move {
+					match callback(params, ctx).await {
+						// Claimed resources are released once the response is written.
+						Ok(res) => sink.send_streaming_response(id, res, claimed),
+						Err(err) => sink.send_call_error(id, err),
+					}
+				};
+				future.boxed()
+			})),
+		)?;
+
+		Ok(MethodResourcesBuilder { build: ResourceVec::new(), callback })
+	}
+
 	/// Register a new **blocking** synchronous RPC method, which computes the response with the given callback.
 	/// Unlike the regular [`register_method`](RpcModule::register_method), this method can block its thread and perform expensive computations.
 	pub fn register_blocking_method<R, F>(
@@ -665,6 +1140,11 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 
 		{
 			let subscribers = subscribers.clone();
+			let description = MethodDescription {
+				ty: MethodType::Subscription,
+				unsubscribe: Some(unsubscribe_method_name.to_owned()),
+				..Default::default()
+			};
 			self.methods.mut_callbacks().insert(
 				subscribe_method_name,
 				MethodCallback::new_sync(Arc::new(move |id, params, method_sink, conn_id| {
@@ -699,11 +1179,19 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 					} else {
 						true
 					}
-				})),
+				}))
+				.with_description(description),
 			);
 		}
 
 		{
+			let description = MethodDescription {
+				ty: MethodType::Unsubscribe,
+				subscribe: Some(subscribe_method_name.to_owned()),
+				param_kind: Some(ParamKind::Array),
+				params: Some(vec![ParamDescription::new("subscription", "SubscriptionId", false)]),
+				..Default::default()
+			};
 			self.methods.mut_callbacks().insert(
 				unsubscribe_method_name,
 				MethodCallback::new_sync(Arc::new(move |id, params, sink, conn_id| {
@@ -727,21 +1215,69 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 						let err = to_json_raw_value(&format!("Invalid subscription ID={}", sub_id)).ok();
 						sink.send_error(id, invalid_subscription_err(err.as_deref()))
 					}
-				})),
+				}))
+				.with_description(description),
 			);
 		}
 
 		Ok(())
 	}
 
+	/// Register a new RPC subscription with an `async` callback, see [`RpcModule::register_subscription`].
+	///
+	/// The future returned by the callback is spawned on the tokio runtime once the subscription is accepted, so it
+	/// may run for as long as the subscription is alive. If it fails, the error is logged and the subscription is
+	/// closed.
+	///
+	/// # Examples
+	///
+	/// ```no_run
+	///
+	/// use jsonrpsee_utils::server::rpc_module::RpcModule;
+	///
+	/// let mut ctx = RpcModule::new(99_usize);
+	/// ctx.register_async_subscription("sub", "notif_name", "unsub", |params, mut sink, ctx| async move {
+	///     let x: usize = params.one()?;
+	///     sink.send(&(x + *ctx))
+	/// });
+	/// ```
+	pub fn register_async_subscription<R, F>(
+		&mut self,
+		subscribe_method_name: &'static str,
+		notif_method_name: &'static str,
+		unsubscribe_method_name: &'static str,
+		callback: F,
+	) -> Result<(), Error>
+	where
+		Context: Send + Sync + 'static,
+		R: Future<Output = Result<(), Error>> + Send + 'static,
+		F: Fn(Params<'static>, SubscriptionSink, Arc<Context>) -> R + Send + Sync + 'static,
+	{
+		self.register_subscription(
+			subscribe_method_name,
+			notif_method_name,
+			unsubscribe_method_name,
+			move |params, sink, ctx| {
+				let fut = callback(params.into_owned(), sink, ctx);
+				tokio::spawn(async move {
+					if let Err(err) = fut.await {
+						tracing::error!("subscription '{}' failed: {:?}", subscribe_method_name, err);
+					}
+				});
+				Ok(())
+			},
+		)
+	}
+
 	/// Register an alias for an existing_method. Alias uniqueness is enforced.
 	pub fn register_alias(&mut self, alias: &'static str, existing_method: &'static str) -> Result<(), Error> {
 		self.methods.verify_method_name(alias)?;
 
-		let callback = match self.methods.callbacks.get(existing_method) {
+		let mut callback = match self.methods.callbacks.get(existing_method) {
 			Some(callback) => callback.clone(),
 			None => return Err(Error::MethodNotFound(existing_method.into())),
 		};
+		callback.description.alias_of = Some(existing_method.to_owned());
 
 		self.methods.mut_callbacks().insert(alias, callback);
 
@@ -749,6 +1285,25 @@ impl<Context: Send + Sync + 'static> RpcModule<Context> {
 	}
 }
 
+/// Context of the connection a subscription was made on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionContext {
+	conn_id: ConnectionId,
+	sub_id: SubscriptionId,
+}
+
+impl ConnectionContext {
+	/// Get the ID of the connection.
+	pub fn connection_id(&self) -> ConnectionId {
+		self.conn_id
+	}
+
+	/// Get the ID of the subscription, unique for the connection.
+	pub fn subscription_id(&self) -> SubscriptionId {
+		self.sub_id
+	}
+}
+
 /// Represents a single subscription.
 #[derive(Debug)]
 pub struct SubscriptionSink {
@@ -781,19 +1336,32 @@ impl SubscriptionSink {
 		self.inner.is_closed()
 	}
 
-	fn build_message<T: Serialize>(&self, result: &T) -> Result<String, Error> {
-		serde_json::to_string(&SubscriptionResponse::new(
+	/// Wait until the subscription is closed, either by the subscriber unsubscribing or by the server.
+	///
+	/// A subscriber whose connection was closed is only noticed when a message is sent to it.
+	pub async fn closed(&mut self) {
+		if let Some(conn) = self.is_connected.as_mut() {
+			conn.cancellation().await;
+		}
+	}
+
+	/// Get the context of the connection the subscription was made on.
+	pub fn connection_context(&self) -> ConnectionContext {
+		ConnectionContext { conn_id: self.uniq_sub.conn_id, sub_id: self.uniq_sub.sub_id }
+	}
+
+	fn build_message<T: Serialize>(&self, result: &T) -> Result<SinkMessage, Error> {
+		self.inner.serialize_message(&SubscriptionResponse::new(
 			self.method.into(),
 			SubscriptionPayload { subscription: RpcSubscriptionId::Num(self.uniq_sub.sub_id), result },
 		))
-		.map_err(Into::into)
 	}
 
-	fn inner_send(&mut self, msg: String) -> Result<(), Error> {
+	fn inner_send(&mut self, msg: SinkMessage) -> Result<(), Error> {
 		let res = match self.is_connected.as_ref() {
 			Some(conn) if !conn.is_canceled() => {
 				// unbounded send only fails if the receiver has been dropped.
-				self.inner.send_raw(msg).map_err(|_| Some(SubscriptionClosedReason::ConnectionReset))
+				self.inner.send_message(msg).map_err(|_| Some(SubscriptionClosedReason::ConnectionReset))
 			}
 			Some(_) => Err(Some(SubscriptionClosedReason::Unsubscribed)),
 			// NOTE(niklasad1): this should be unreachble, after the first error is detected the subscription is closed.
@@ -824,7 +1392,7 @@ impl SubscriptionSink {
 			tracing::debug!("Closing subscription: {:?} reason: {:?}", self.uniq_sub.sub_id, close_reason);
 			if let Some(close_reason) = close_reason {
 				let msg = self.build_message(close_reason).expect("valid json infallible; qed");
-				let _ = sink.send_raw(msg);
+				let _ = sink.send_message(msg);
 			}
 		}
 	}
@@ -883,6 +1451,52 @@ impl Drop for Subscription {
 	}
 }
 
+/// Splits a versioned method name, e.g. `v2_foo`, into its version and the unversioned name.
+fn split_version(versioned_method: &'static str) -> Option<(u32, &'static str)> {
+	let (version, method_name) = versioned_method.strip_prefix('v')?.split_once('_')?;
+	if version.is_empty() || !version.bytes().all(|b| b.is_ascii_digit()) || method_name.is_empty() {
+		return None;
+	}
+	Some((version.parse().ok()?, method_name))
+}
+
+/// Version selected by the [`VERSION_PARAM`] field of the params, `Some(None)` if it's not a valid version.
+fn requested_version(params: &RawValue) -> Option<Option<u32>> {
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Version<'a> {
+		Number(u32),
+		Name(&'a str),
+	}
+
+	#[derive(Deserialize)]
+	struct VersionParams<'a> {
+		#[serde(borrow, default)]
+		version: Option<Version<'a>>,
+	}
+
+	// Only params passed by name can select a version.
+	if !params.get().trim_start().starts_with('{') {
+		return None;
+	}
+
+	match serde_json::from_str::<VersionParams>(params.get()) {
+		Ok(VersionParams { version: Some(Version::Number(version)) }) => Some(Some(version)),
+		Ok(VersionParams { version: Some(Version::Name(name)) }) => {
+			Some(name.strip_prefix('v').unwrap_or(name).parse().ok())
+		}
+		Ok(VersionParams { version: None }) => None,
+		Err(_) => Some(None),
+	}
+}
+
+/// Cache the result the method kept in the `slot`, if the call succeeded.
+fn cache_kept_result(cache: &ResponseCache, method_name: &str, params: Option<&RawValue>, slot: &ResponseSlot) {
+	if let Some(KeptResponse::Result(result)) = slot.get() {
+		cache.insert(method_name, params.map(ParamsSer::Raw), &result);
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -1069,6 +1683,75 @@ mod tests {
 		assert!(matches!(sub_err, Error::SubscriptionClosed(_)));
 	}
 
+	#[tokio::test]
+	async fn versioned_methods_are_routed() {
+		let mut v1 = RpcModule::new(());
+		v1.register_method("v1_greet", |_, _| Ok("v1")).unwrap();
+		v1.register_version("v1_greet").unwrap();
+		v1.deprecate("v1_greet").unwrap();
+
+		let mut v2 = RpcModule::new(());
+		v2.register_method("v2_greet", |_, _| Ok("v2")).unwrap();
+		v2.register_version("v2_greet").unwrap();
+
+		let mut module = RpcModule::new(());
+		module.merge(v2).unwrap();
+		module.merge(v1).unwrap();
+
+		// The unversioned name is an alias of the latest version, unless the params select one.
+		assert_eq!(module.call::<_, String>("greet", EmptyParams::new()).await.unwrap(), "v2");
+		assert_eq!(module.call::<_, String>("v1_greet", EmptyParams::new()).await.unwrap(), "v1");
+		for version in [r#"{"version":1}"#, r#"{"version":"v1"}"#] {
+			let call = format!(r#"{{"jsonrpc":"2.0","method":"greet","params":{},"id":0}}"#, version);
+			let (resp, _) = module.raw_json_request(&call).await.unwrap();
+			assert_eq!(resp, r#"{"jsonrpc":"2.0","result":"v1","id":0}"#);
+		}
+		let (resp, _) = module
+			.raw_json_request(r#"{"jsonrpc":"2.0","method":"greet","params":{"version":3},"id":0}"#)
+			.await
+			.unwrap();
+		assert_eq!(resp, r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":0}"#);
+
+		let req = Request::new("greet".into(), None, Id::Number(0));
+		assert!(matches!(module.route(&req), Some(("greet", callback)) if !callback.is_deprecated()));
+		let req = Request::new("v1_greet".into(), None, Id::Number(0));
+		assert!(matches!(module.route(&req), Some(("v1_greet", callback)) if callback.is_deprecated()));
+
+		let mut other_v2 = RpcModule::new(());
+		other_v2.register_method("v2_greet", |_, _| Ok("other v2")).unwrap();
+		other_v2.register_version("v2_greet").unwrap();
+		assert!(matches!(module.merge(other_v2), Err(Error::MethodAlreadyRegistered(name)) if name == "v2_greet"));
+		assert_eq!(module.call::<_, String>("greet", EmptyParams::new()).await.unwrap(), "v2");
+
+		assert!(matches!(module.register_version("greet"), Err(Error::MissingVersionPrefix(_))));
+		assert!(matches!(module.register_version("v3_greet"), Err(Error::MethodNotFound(_))));
+		assert!(matches!(module.register_method("greet", |_, _| Ok("greet")), Err(Error::MethodAlreadyRegistered(_))));
+	}
+
+	#[tokio::test]
+	async fn async_subscription_with_connection_context() {
+		let mut module = RpcModule::new(());
+		module
+			.register_async_subscription("my_sub", "my_sub", "my_unsub", |params, mut sink, _| async move {
+				let count: u64 = params.one()?;
+				let context = sink.connection_context();
+				for _ in 0..count {
+					sink.send(&context.subscription_id())?;
+				}
+				Ok(())
+			})
+			.unwrap();
+
+		let mut my_sub = module.subscribe("my_sub", [2]).await.unwrap();
+		for _ in 0..2 {
+			let (val, _) = my_sub.next::<u64>().await.unwrap().unwrap();
+			assert_eq!(val, my_sub.subscription_id());
+		}
+
+		let sub_err = my_sub.next::<u64>().await.unwrap().unwrap_err();
+		assert!(matches!(sub_err, Error::SubscriptionClosed(_)));
+	}
+
 	#[tokio::test]
 	async fn close_test_subscribing_without_server() {
 		let mut module = RpcModule::new(());
@@ -1093,4 +1776,183 @@ mod tests {
 		my_sub.close();
 		assert!(matches!(my_sub.next::<String>().await, None));
 	}
+
+	#[tokio::test]
+	async fn introspection_describes_methods() {
+		let mut module = RpcModule::new(());
+		module.register_method("add", |_: Params, _| Ok(0)).unwrap().resource("cpu", 3).unwrap();
+		module.describe("add", true, ParamKind::Map, vec![ParamDescription::new("a", "u32", false)]).unwrap();
+		module.register_alias("sum", "add").unwrap();
+		module.register_subscription("sub", "sub", "unsub", |_, _, _| Ok(())).unwrap();
+		assert!(matches!(module.describe("nope", false, ParamKind::Array, Vec::new()), Err(Error::MethodNotFound(_))));
+		module.register_introspection().unwrap();
+
+		let res: RpcMethods = module.call(RPC_METHODS, EmptyParams::new()).await.unwrap();
+		let names: Vec<_> = res.methods.iter().map(|m| m.name.as_str()).collect();
+		assert_eq!(names, ["add", "rpc_methods", "sub", "sum", "unsub"]);
+
+		let add = res.get("add").unwrap();
+		assert!(add.deprecated);
+		assert_eq!(add.resources.get("cpu"), Some(&3));
+		assert_eq!(add.param_kind, Some(ParamKind::Map));
+		assert_eq!(add.params, Some(vec![ParamDescription::new("a", "u32", false)]));
+
+		let sum = res.get("sum").unwrap();
+		assert_eq!(sum.alias_of.as_deref(), Some("add"));
+		assert_eq!(sum.params, add.params);
+
+		assert_eq!(res.get("sub").unwrap().ty, MethodType::Subscription);
+		assert_eq!(res.get("sub").unwrap().unsubscribe.as_deref(), Some("unsub"));
+		assert_eq!(res.get("unsub").unwrap().ty, MethodType::Unsubscribe);
+		assert_eq!(res.get("unsub").unwrap().subscribe.as_deref(), Some("sub"));
+		assert_eq!(res.get("rpc_methods").unwrap().params, Some(Vec::new()));
+
+		assert!(matches!(module.register_introspection(), Err(Error::MethodAlreadyRegistered(_))));
+	}
+
+	#[tokio::test]
+	async fn cached_methods_are_called_once() {
+		use jsonrpsee_types::v2::ParamsSer;
+		use std::sync::atomic::{AtomicUsize, Ordering};
+		use std::time::Duration;
+
+		let mut module = RpcModule::new(AtomicUsize::new(0));
+		module.register_method("get", |_, calls| Ok(calls.fetch_add(1, Ordering::SeqCst))).unwrap();
+		module
+			.register_async_method("get_async", |_, calls| async move { Ok(calls.fetch_add(1, Ordering::SeqCst)) })
+			.unwrap();
+		module
+			.register_method("failing", |_, calls| -> Result<(), Error> {
+				calls.fetch_add(1, Ordering::SeqCst);
+				Err(Error::Custom("failed".into()))
+			})
+			.unwrap();
+		module.register_subscription("sub", "sub", "unsub", |_, _, _| Ok(())).unwrap();
+		module.register_alias("get_early_alias", "get").unwrap();
+
+		let cache = ResponseCache::new();
+		let policy = CachePolicy::new(Duration::from_secs(60));
+		for method in ["get", "get_async", "failing"] {
+			module.cache_method(method, &cache, policy).unwrap();
+		}
+		module.register_alias("get_alias", "get").unwrap();
+		assert!(module.cache_method("sub", &cache, policy).is_err());
+		assert!(matches!(module.cache_method("nope", &cache, policy), Err(Error::MethodNotFound(_))));
+		assert!(module.response_cache("get_alias").is_some());
+		assert!(module.response_cache("get_early_alias").is_some());
+		assert!(module.response_cache("sub").is_none());
+
+		let call = |method: &str, params: &str| {
+			format!(r#"{{"jsonrpc":"2.0","method":"{}","params":{},"id":1}}"#, method, params)
+		};
+		let result = |resp: String| serde_json::from_str::<Response<u64>>(&resp).unwrap().result;
+		let (resp, _) = module.raw_json_request(&call("get", r#"{"a":1,"b":2}"#)).await.unwrap();
+		assert_eq!(result(resp), 0);
+		let (resp, _) = module.raw_json_request(&call("get", r#"{ "b": 2, "a": 1 }"#)).await.unwrap();
+		assert_eq!(result(resp), 0);
+		let (resp, _) = module.raw_json_request(&call("get_alias", r#"{"b":2,"a":1}"#)).await.unwrap();
+		assert_eq!(result(resp), 0);
+		let (resp, _) = module.raw_json_request(&call("get_early_alias", r#"{"b":2,"a":1}"#)).await.unwrap();
+		assert_eq!(result(resp), 0);
+		let (resp, _) = module.raw_json_request(&call("get", r#"{"a":2}"#)).await.unwrap();
+		assert_eq!(result(resp), 1);
+		assert_eq!(module.call::<_, u64>("get_async", EmptyParams::new()).await.unwrap(), 2);
+		assert_eq!(module.call::<_, u64>("get_async", EmptyParams::new()).await.unwrap(), 2);
+
+		// Errors aren't cached.
+		for calls in 4..6 {
+			assert!(module.call::<_, ()>("failing", EmptyParams::new()).await.is_err());
+			assert_eq!(module.ctx.load(Ordering::SeqCst), calls);
+		}
+
+		let params = serde_json::value::RawValue::from_string(r#"{"b":2,"a":1}"#.into()).unwrap();
+		module.response_cache("get").unwrap().invalidate("get", Some(ParamsSer::Raw(&params)));
+		let (resp, _) = module.raw_json_request(&call("get", r#"{"a":1,"b":2}"#)).await.unwrap();
+		assert_eq!(result(resp), 5);
+	}
+
+	#[tokio::test]
+	async fn single_flight_calls_share_one_execution() {
+		use futures_util::future::join_all;
+		use jsonrpsee_types::v2::{error::OVERSIZED_RESPONSE_CODE, RpcError};
+		use std::sync::atomic::{AtomicUsize, Ordering};
+
+		let mut module = RpcModule::new(AtomicUsize::new(0));
+		module
+			.register_async_method("get", |params, calls| async move {
+				let n: u64 = params.one()?;
+				// Let the identical calls join the call in flight.
+				tokio::task::yield_now().await;
+				Ok(n + calls.fetch_add(1, Ordering::SeqCst) as u64 * 10)
+			})
+			.unwrap();
+		module.register_subscription("sub", "sub", "unsub", |_, _, _| Ok(())).unwrap();
+		module.register_alias("get_early_alias", "get").unwrap();
+		module.single_flight("get").unwrap();
+		module.register_alias("get_alias", "get").unwrap();
+		assert!(module.single_flight("sub").is_err());
+		assert!(matches!(module.single_flight("nope"), Err(Error::MethodNotFound(_))));
+
+		let results = join_all([
+			module.call::<_, u64>("get", [1]),
+			module.call::<_, u64>("get_alias", [1]),
+			module.call::<_, u64>("get_early_alias", [1]),
+			module.call::<_, u64>("get", [2]),
+		])
+		.await;
+		let results: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+		assert_eq!(results, [1, 1, 1, 12]);
+		assert_eq!(module.ctx.load(Ordering::SeqCst), 2);
+
+		// The flight ended, the next call executes the method.
+		assert_eq!(module.call::<_, u64>("get", [1]).await.unwrap(), 21);
+
+		// Only the call executing the method claims resources.
+		let mut resources = Resources::default();
+		resources.register("cpu", 1, 1).unwrap();
+		let module = Methods::from(module).initialize_resources(&resources).unwrap();
+		let (tx, mut rx) = mpsc::unbounded();
+		let sink = MethodSink::new(tx);
+		let futs: Vec<_> = (1..4)
+			.map(|id| {
+				let call = format!(r#"{{"jsonrpc":"2.0","method":"get","params":[1],"id":{}}}"#, id);
+				let req: Request = serde_json::from_str(&call).unwrap();
+				match module.execute_with_resources(&sink, req, 0, &resources).unwrap().1 {
+					MethodResult::Async(fut) => fut,
+					MethodResult::Sync(_) => panic!("The call must be in flight"),
+				}
+			})
+			.collect();
+		assert!(join_all(futs).await.into_iter().all(|sent| sent));
+
+		let mut ids = Vec::new();
+		while let Some(resp) = rx.next().now_or_never().flatten() {
+			let resp: Response<u64> = serde_json::from_str(&resp).unwrap();
+			assert_eq!(resp.result, 31);
+			ids.push(resp.id.as_number().copied().unwrap());
+		}
+		ids.sort_unstable();
+		assert_eq!(ids, [1, 2, 3]);
+
+		// The response is checked against the size limit of the sink of each call.
+		let (small_tx, mut small_rx) = mpsc::unbounded();
+		let small_sink = MethodSink::new_with_limit(small_tx, 30);
+		let futs: Vec<_> = [&sink, &small_sink]
+			.iter()
+			.map(|sink| {
+				let req: Request =
+					serde_json::from_str(r#"{"jsonrpc":"2.0","method":"get","params":[1],"id":1}"#).unwrap();
+				match module.execute(sink, req, 0) {
+					MethodResult::Async(fut) => fut,
+					MethodResult::Sync(_) => panic!("The call must be in flight"),
+				}
+			})
+			.collect();
+		assert_eq!(join_all(futs).await, [true, false]);
+		let resp = rx.next().await.unwrap();
+		assert_eq!(serde_json::from_str::<Response<u64>>(&resp).unwrap().result, 41);
+		let err = small_rx.next().await.unwrap();
+		let err: RpcError = serde_json::from_str(&err).unwrap();
+		assert_eq!(err.error.code, ErrorCode::ServerError(OVERSIZED_RESPONSE_CODE));
+	}
 }
@@ -27,26 +27,27 @@
 use crate::manager::{RequestManager, RequestStatus};
 use crate::transport::Sender as WsSender;
 use crate::types::v2::{
-	Id, Notification, ParamsSer, RequestSer, Response, RpcError, SubscriptionId, SubscriptionResponse,
+	BatchResponseEntry, Id, Notification, ParamsSer, RequestSer, Response, RpcError, SubscriptionId,
+	SubscriptionResponse,
 };
-use crate::types::{Error, RequestMessage};
+use crate::types::{Error, RequestMessage, UnsubscribeMessage};
 use futures::channel::{mpsc, oneshot};
 use serde_json::Value as JsonValue;
 use std::convert::TryInto;
 use std::time::Duration;
 
-/// Attempts to process a batch response.
+/// Attempts to process a batch response, whose calls may have failed.
 ///
-/// On success the result is sent to the frontend.
-pub fn process_batch_response(manager: &mut RequestManager, rps: Vec<Response<JsonValue>>) -> Result<(), Error> {
+/// On success the result of each call is sent to the frontend.
+pub fn process_batch_response(manager: &mut RequestManager, rps: Vec<BatchResponseEntry>) -> Result<(), Error> {
 	let mut digest = Vec::with_capacity(rps.len());
-	let mut ordered_responses = vec![JsonValue::Null; rps.len()];
+	let mut ordered_responses: Vec<_> = rps.iter().map(|_| Err(Error::InvalidRequestId)).collect();
 	let mut rps_unordered: Vec<_> = Vec::with_capacity(rps.len());
 
-	for rp in rps {
-		let id = rp.id.as_number().copied().ok_or(Error::InvalidRequestId)?;
+	for (id, rp) in rps {
+		let id = id.as_number().copied().ok_or(Error::InvalidRequestId)?;
 		digest.push(id);
-		rps_unordered.push((id, rp.result));
+		rps_unordered.push((id, rp));
 	}
 
 	digest.sort_unstable();
@@ -183,6 +184,48 @@ pub async fn stop_subscription(sender: &mut WsSender, manager: &mut RequestManag
 	}
 }
 
+/// Sends the unsubscribe request of [`Subscription::unsubscribe`](crate::types::Subscription::unsubscribe) to the
+/// server. The response of the server is sent back over the channel of the message.
+pub async fn unsubscribe(
+	sender: &mut WsSender,
+	manager: &mut RequestManager,
+	unsub: UnsubscribeMessage,
+	request_timeout: Duration,
+) {
+	let UnsubscribeMessage { sub_id, send_back } = unsub;
+
+	// NOTE: The subscription may have been closed earlier if the channel was full or disconnected.
+	let msg = match manager
+		.get_request_id_by_subscription_id(&sub_id)
+		.and_then(|req_id| build_unsubscribe_message(manager, req_id, sub_id))
+	{
+		Some(msg) => msg,
+		None => {
+			let _ = send_back.send(Err(Error::InvalidSubscriptionId));
+			return;
+		}
+	};
+
+	// The front-end waits for the response without a timeout, the request timeout of the client is enforced here.
+	let (timeout_tx, timeout_rx) = oneshot::channel();
+	tokio::spawn(async move {
+		if let Ok(res) = call_with_timeout(request_timeout, timeout_rx).await {
+			let _ = send_back.send(res);
+		}
+	});
+
+	// The unsubscribe call was reserved without a channel when subscribing.
+	let _ = manager.complete_pending_call(msg.id);
+	manager.insert_pending_call(msg.id, Some(timeout_tx)).expect("Request ID removed above; qed");
+
+	if let Err(e) = sender.send(msg.raw).await {
+		tracing::error!("Send unsubscribe request failed: {:?}", e);
+		if let Some(Some(send_back)) = manager.complete_pending_call(msg.id) {
+			let _ = send_back.send(Err(Error::Transport(e.into())));
+		}
+	}
+}
+
 /// Builds an unsubscription message.
 pub fn build_unsubscribe_message(
 	manager: &mut RequestManager,
@@ -50,3 +50,4 @@ mod tests;
 
 pub use client::{WsClient, WsClientBuilder};
 pub use jsonrpsee_types as types;
+pub use jsonrpsee_types::encoding::WsEncoding;
@@ -127,6 +127,32 @@ async fn subscription_works() {
 	}
 }
 
+#[tokio::test]
+async fn unsubscribe_times_out() {
+	// The server only answers to the subscribe call.
+	let server = WebSocketTestServer::with_hardcoded_subscription(
+		"127.0.0.1:0".parse().unwrap(),
+		server_subscription_id_response(Id::Num(0)),
+		server_subscription_response(JsonValue::String("hello my friend".to_owned())),
+	)
+	.with_default_timeout()
+	.await
+	.unwrap();
+	let uri = to_ws_uri_string(server.local_addr());
+	let client = WsClientBuilder::default()
+		.request_timeout(std::time::Duration::from_millis(100))
+		.build(&uri)
+		.with_default_timeout()
+		.await
+		.unwrap()
+		.unwrap();
+	let sub: Subscription<String> =
+		client.subscribe("subscribe_hello", None, "unsubscribe_hello").with_default_timeout().await.unwrap().unwrap();
+
+	let err = sub.unsubscribe().with_default_timeout().await.unwrap().unwrap_err();
+	assert!(matches!(err, Error::RequestTimeout), "{:?}", err);
+}
+
 #[tokio::test]
 async fn notification_handler_works() {
 	let server = WebSocketTestServer::with_hardcoded_notification(
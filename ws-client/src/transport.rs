@@ -28,8 +28,13 @@ use crate::{stream::EitherStream, types::CertificateStore};
 use beef::Cow;
 use futures::io::{BufReader, BufWriter};
 use http::Uri;
+use jsonrpsee_types::encoding::{EncodingError, WsEncoding};
+use jsonrpsee_utils::deflate::BoundedDeflate;
+use jsonrpsee_utils::proxy::{Proxy, ProxyConfig};
 use soketto::connection;
+use soketto::connection::Mode as DeflateMode;
 use soketto::handshake::client::{Client as WsHandshakeClient, Header, ServerResponse};
+#[cfg(feature = "tls")]
 use std::convert::TryInto;
 use std::{
 	convert::TryFrom,
@@ -44,12 +49,15 @@ use tokio::net::TcpStream;
 #[derive(Debug)]
 pub struct Sender {
 	inner: connection::Sender<BufReader<BufWriter<EitherStream>>>,
+	encoding: WsEncoding,
 }
 
 /// Receiving end of WebSocket transport.
 #[derive(Debug)]
 pub struct Receiver {
 	inner: connection::Receiver<BufReader<BufWriter<EitherStream>>>,
+	max_message_size: u32,
+	encoding: WsEncoding,
 }
 
 /// Builder for a WebSocket transport [`Sender`] and ['Receiver`] pair.
@@ -68,6 +76,12 @@ pub struct WsTransportClientBuilder<'a> {
 	pub max_request_body_size: u32,
 	/// Max number of redirections.
 	pub max_redirections: usize,
+	/// Whether to request the `permessage-deflate` extension.
+	pub permessage_deflate: bool,
+	/// Encoding to request through the WebSocket subprotocol, JSON is used if the server does not support it.
+	pub encoding: WsEncoding,
+	/// HTTP proxy to tunnel the connections through, the target of a redirection is checked against it again.
+	pub proxy: ProxyConfig,
 }
 
 /// Stream mode, either plain TCP or TLS.
@@ -136,6 +150,10 @@ pub enum WsError {
 	/// Failed to parse the message in JSON.
 	#[error("Failed to parse message in JSON: {}", 0)]
 	ParseError(#[source] serde_json::error::Error),
+
+	/// Failed to transcode a message from or to the binary encoding of the connection.
+	#[error("Failed to transcode message: {0}")]
+	Encoding(#[source] EncodingError),
 }
 
 impl Sender {
@@ -143,7 +161,12 @@ impl Sender {
 	/// successfully sent.
 	pub async fn send(&mut self, body: String) -> Result<(), WsError> {
 		tracing::debug!("send: {}", body);
-		self.inner.send_text(body).await?;
+		if self.encoding.is_binary() {
+			let encoded = self.encoding.encode(&body).map_err(WsError::Encoding)?;
+			self.inner.send_binary_mut(encoded).await?;
+		} else {
+			self.inner.send_text(body).await?;
+		}
 		self.inner.flush().await?;
 		Ok(())
 	}
@@ -158,7 +181,21 @@ impl Receiver {
 	/// Returns a `Future` resolving when the server sent us something back.
 	pub async fn next_response(&mut self) -> Result<Vec<u8>, WsError> {
 		let mut message = Vec::new();
-		self.inner.receive_data(&mut message).await?;
+		let data = self.inner.receive_data(&mut message).await?;
+
+		// Binary frames carry the negotiated encoding, text frames are always JSON.
+		if data.is_binary() && self.encoding.is_binary() {
+			message = self.encoding.decode(&message).map_err(WsError::Encoding)?;
+		}
+
+		// The size limit of the connection applies to the payload as received, check it against the decoded message
+		// when a binary encoding is used. `BoundedDeflate` already enforces it while inflating.
+		if message.len() > self.max_message_size as usize {
+			let err =
+				connection::Error::MessageTooLarge { current: message.len(), maximum: self.max_message_size as usize };
+			return Err(err.into());
+		}
+
 		Ok(message)
 	}
 }
@@ -185,22 +222,28 @@ impl<'a> WsTransportClientBuilder<'a> {
 
 			// The sockaddrs might get reused if the server replies with a relative URI.
 			let sockaddrs = std::mem::take(&mut target.sockaddrs);
-			for sockaddr in &sockaddrs {
+			// The proxy resolves the host itself, so there is a single attempt through it.
+			let proxy = target.proxy.clone();
+			let dests: Vec<_> = match &proxy {
+				Some(proxy) => vec![Dest::Proxy(proxy)],
+				None => sockaddrs.iter().copied().map(Dest::Direct).collect(),
+			};
+			for dest in dests {
 				#[cfg(feature = "tls")]
-				let tcp_stream = match connect(*sockaddr, self.timeout, &target.host, connector.as_ref()).await {
+				let tcp_stream = match connect(dest, &target, self.timeout, connector.as_ref()).await {
 					Ok(stream) => stream,
 					Err(e) => {
-						tracing::debug!("Failed to connect to sockaddr: {:?}", sockaddr);
+						tracing::debug!("Failed to connect to {:?}", dest);
 						err = Some(Err(e));
 						continue;
 					}
 				};
 
 				#[cfg(not(feature = "tls"))]
-				let tcp_stream = match connect(*sockaddr, self.timeout).await {
+				let tcp_stream = match connect(dest, &target, self.timeout).await {
 					Ok(stream) => stream,
 					Err(e) => {
-						tracing::debug!("Failed to connect to sockaddr: {:?}", sockaddr);
+						tracing::debug!("Failed to connect to {:?}", dest);
 						err = Some(Err(e));
 						continue;
 					}
@@ -214,14 +257,30 @@ impl<'a> WsTransportClientBuilder<'a> {
 
 				client.set_headers(&self.headers);
 
+				if self.permessage_deflate {
+					let max_message_size = self.max_request_body_size as usize;
+					client.add_extension(Box::new(BoundedDeflate::new(DeflateMode::Client, max_message_size)));
+				}
+
+				if let Some(protocol) = self.encoding.subprotocol() {
+					client.add_protocol(protocol);
+				}
+
 				// Perform the initial handshake.
 				match client.handshake().await {
-					Ok(ServerResponse::Accepted { .. }) => {
+					Ok(ServerResponse::Accepted { protocol }) => {
 						tracing::info!("Connection established to target: {:?}", target);
+						let encoding =
+							protocol.as_deref().and_then(WsEncoding::from_subprotocol).unwrap_or(WsEncoding::Json);
+						if encoding != self.encoding {
+							tracing::debug!("Encoding {:?} not supported by the server, using JSON", self.encoding);
+						}
 						let mut builder = client.into_builder();
 						builder.set_max_message_size(self.max_request_body_size as usize);
 						let (sender, receiver) = builder.finish();
-						return Ok((Sender { inner: sender }, Receiver { inner: receiver }));
+						let receiver =
+							Receiver { inner: receiver, max_message_size: self.max_request_body_size, encoding };
+						return Ok((Sender { inner: sender, encoding }, receiver));
 					}
 
 					Ok(ServerResponse::Rejected { status_code }) => {
@@ -235,7 +294,7 @@ impl<'a> WsTransportClientBuilder<'a> {
 							Ok(uri) => {
 								// Absolute URI.
 								if uri.scheme().is_some() {
-									target = uri.try_into()?;
+									target = Target::parse(uri, &self.proxy)?;
 
 									// Only build TLS connector if `wss` in redirection URL.
 									#[cfg(feature = "tls")]
@@ -291,25 +350,47 @@ impl<'a> WsTransportClientBuilder<'a> {
 	}
 }
 
+/// Where to open the TCP connection to.
+#[derive(Debug, Clone, Copy)]
+enum Dest<'a> {
+	/// Directly to one of the resolved addresses of the target.
+	Direct(SocketAddr),
+	/// To the proxy, which opens a tunnel to the target.
+	Proxy(&'a Proxy),
+}
+
+async fn open_socket(dest: Dest<'_>, target: &Target) -> Result<TcpStream, WsHandshakeError> {
+	let socket = match dest {
+		Dest::Direct(sockaddr) => TcpStream::connect(sockaddr).await?,
+		Dest::Proxy(proxy) => {
+			let mut socket = TcpStream::connect(proxy.authority()).await?;
+			proxy.tunnel(&mut socket, &target.host_header).await?;
+			socket
+		}
+	};
+	if let Err(err) = socket.set_nodelay(true) {
+		tracing::warn!("set nodelay failed: {:?}", err);
+	}
+	Ok(socket)
+}
+
 #[cfg(feature = "tls")]
 async fn connect(
-	sockaddr: SocketAddr,
+	dest: Dest<'_>,
+	target: &Target,
 	timeout_dur: Duration,
-	host: &str,
 	tls_connector: Option<&tokio_rustls::TlsConnector>,
 ) -> Result<EitherStream, WsHandshakeError> {
-	let socket = TcpStream::connect(sockaddr);
+	let socket = open_socket(dest, target);
 	let timeout = tokio::time::sleep(timeout_dur);
 	tokio::select! {
 		socket = socket => {
 			let socket = socket?;
-			if let Err(err) = socket.set_nodelay(true) {
-				tracing::warn!("set nodelay failed: {:?}", err);
-			}
 			match tls_connector {
 				None => Ok(EitherStream::Plain(socket)),
 				Some(connector) => {
-					let server_name: tokio_rustls::rustls::ServerName = host.try_into().map_err(|e| WsHandshakeError::Url(format!("Invalid host: {} {:?}", host, e).into()))?;
+					let host = &target.host;
+					let server_name: tokio_rustls::rustls::ServerName = host.as_str().try_into().map_err(|e| WsHandshakeError::Url(format!("Invalid host: {} {:?}", host, e).into()))?;
 					let tls_stream = connector.connect(server_name, socket).await?;
 					Ok(EitherStream::Tls(tls_stream))
 				}
@@ -320,17 +401,11 @@ async fn connect(
 }
 
 #[cfg(not(feature = "tls"))]
-async fn connect(sockaddr: SocketAddr, timeout_dur: Duration) -> Result<EitherStream, WsHandshakeError> {
-	let socket = TcpStream::connect(sockaddr);
+async fn connect(dest: Dest<'_>, target: &Target, timeout_dur: Duration) -> Result<EitherStream, WsHandshakeError> {
+	let socket = open_socket(dest, target);
 	let timeout = tokio::time::sleep(timeout_dur);
 	tokio::select! {
-		socket = socket => {
-			let socket = socket?;
-			if let Err(err) = socket.set_nodelay(true) {
-				tracing::warn!("set nodelay failed: {:?}", err);
-			}
-			Ok(EitherStream::Plain(socket))
-		}
+		socket = socket => Ok(EitherStream::Plain(socket?)),
 		_ = timeout => Err(WsHandshakeError::Timeout(timeout_dur))
 	}
 }
@@ -373,12 +448,23 @@ pub struct Target {
 	mode: Mode,
 	/// The path and query parts from an URL.
 	path_and_query: String,
+	/// HTTP proxy to tunnel the connection through, the host is then not resolved.
+	proxy: Option<Proxy>,
 }
 
 impl TryFrom<Uri> for Target {
 	type Error = WsHandshakeError;
 
 	fn try_from(uri: Uri) -> Result<Self, Self::Error> {
+		Self::parse(uri, &ProxyConfig::None)
+	}
+}
+
+impl Target {
+	/// Parse the target from `uri`, and pick the proxy to reach it through according to `proxy`.
+	///
+	/// The IP addresses of the host are resolved unless the connection goes through a proxy.
+	pub fn parse(uri: Uri, proxy: &ProxyConfig) -> Result<Self, WsHandshakeError> {
 		let mode = match uri.scheme_str() {
 			Some("ws") => Mode::Plain,
 			#[cfg(feature = "tls")]
@@ -396,10 +482,14 @@ impl TryFrom<Uri> for Target {
 			.port_u16()
 			.ok_or_else(|| WsHandshakeError::Url("No port number in URL (default port is not supported)".into()))?;
 		let host_header = format!("{}:{}", host, port);
+		let proxy = proxy.resolve(mode == Mode::Tls, &host).map_err(|e| WsHandshakeError::Url(e.into()))?;
 		let parts = uri.into_parts();
 		let path_and_query = parts.path_and_query.ok_or_else(|| WsHandshakeError::Url("No path in URL".into()))?;
-		let sockaddrs = host_header.to_socket_addrs().map_err(WsHandshakeError::ResolutionFailed)?;
-		Ok(Self { sockaddrs: sockaddrs.collect(), host, host_header, mode, path_and_query: path_and_query.to_string() })
+		let sockaddrs = match proxy {
+			Some(_) => Vec::new(),
+			None => host_header.to_socket_addrs().map_err(WsHandshakeError::ResolutionFailed)?.collect(),
+		};
+		Ok(Self { sockaddrs, host, host_header, mode, path_and_query: path_and_query.to_string(), proxy })
 	}
 }
 
@@ -431,6 +521,10 @@ fn build_tls_config(cert_store: &CertificateStore) -> Result<tokio_rustls::TlsCo
 				rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
 			}));
 		}
+		CertificateStore::Custom(custom) => {
+			let config = custom.client_config().map_err(WsHandshakeError::CertificateStore)?;
+			return Ok(config.into());
+		}
 		_ => {
 			let err = io::Error::new(io::ErrorKind::NotFound, "Invalid certificate store");
 			return Err(WsHandshakeError::CertificateStore(err));
@@ -445,7 +539,7 @@ fn build_tls_config(cert_store: &CertificateStore) -> Result<tokio_rustls::TlsCo
 
 #[cfg(test)]
 mod tests {
-	use super::{Mode, Target, Uri, WsHandshakeError};
+	use super::{Mode, Proxy, ProxyConfig, Target, Uri, WsHandshakeError};
 	use http::uri::InvalidUri;
 	use std::convert::TryInto;
 
@@ -511,4 +605,15 @@ mod tests {
 		let target = parse_target("ws://127.0.0.1:443/my.htm#ignore").unwrap();
 		assert_ws_target(target, "127.0.0.1", "127.0.0.1:443", Mode::Plain, "/my.htm");
 	}
+
+	#[test]
+	fn proxied_target_is_not_resolved() {
+		let proxy = ProxyConfig::Url("http://proxy.example:3128".into());
+		let target = Target::parse("ws://unresolvable.invalid:9944".parse().unwrap(), &proxy).unwrap();
+		assert!(target.sockaddrs.is_empty());
+		assert_eq!(target.proxy.as_ref().map(Proxy::authority), Some("proxy.example:3128"));
+
+		let err = Target::parse("ws://127.0.0.1:9944".parse().unwrap(), &ProxyConfig::Url("socks5://proxy".into()));
+		assert!(matches!(err, Err(WsHandshakeError::Url(_))));
+	}
 }
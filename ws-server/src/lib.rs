@@ -34,12 +34,15 @@ extern crate alloc;
 
 mod future;
 mod server;
+mod streaming;
 
 #[cfg(test)]
 mod tests;
 
 pub use future::{ServerHandle as WsServerHandle, ShutdownWaiter as WsShutdownWaiter};
 pub use jsonrpsee_types as types;
-pub use jsonrpsee_utils::server::rpc_module::{RpcModule, SubscriptionSink};
+pub use jsonrpsee_types::encoding::WsEncoding;
+pub use jsonrpsee_utils::server::access_log::{AccessLog, AccessLogWriter};
+pub use jsonrpsee_utils::server::rpc_module::{ConnectionContext, RpcModule, SubscriptionSink};
 pub use server::{Builder as WsServerBuilder, Server as WsServer};
 pub use tracing;
@@ -30,7 +30,9 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use crate::future::{FutureDriver, ServerHandle, StopMonitor};
+use crate::streaming::{write_queued, write_streaming_response, QueuedSocket};
 use crate::types::{
+	encoding::{EncodedRequest, WsEncoding},
 	error::Error,
 	middleware::Middleware,
 	v2::{ErrorCode, Id, Request},
@@ -39,16 +41,20 @@ use crate::types::{
 use futures_channel::mpsc;
 use futures_util::future::join_all;
 use futures_util::future::FutureExt;
-use futures_util::io::{BufReader, BufWriter};
+use futures_util::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter, ReadHalf};
 use futures_util::stream::StreamExt;
-use soketto::connection::Error as SokettoError;
+use soketto::base::OpCode;
+use soketto::connection::Mode as DeflateMode;
+use soketto::connection::{Builder as ConnectionBuilder, Error as SokettoError};
 use soketto::handshake::{server::Response, Server as SokettoServer};
 use soketto::Sender;
 use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
 use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
 
+use jsonrpsee_utils::deflate::{BoundedDeflate, InflatedTooLarge};
 use jsonrpsee_utils::server::{
-	helpers::{collect_batch_response, prepare_error, MethodSink},
+	access_log::AccessLog,
+	helpers::{collect_batch_response, MethodSink, StreamingResponse},
 	resource_limiting::Resources,
 	rpc_module::{ConnectionId, MethodResult, Methods},
 };
@@ -101,7 +107,7 @@ impl<M: Middleware> Server<M> {
 
 		loop {
 			match connections.select_with(&mut incoming).await {
-				Ok((socket, _addr)) => {
+				Ok((socket, remote_addr)) => {
 					if let Err(e) = socket.set_nodelay(true) {
 						tracing::error!("Could not set NODELAY on socket: {:?}", e);
 						continue;
@@ -120,6 +126,7 @@ impl<M: Middleware> Server<M> {
 						socket,
 						HandshakeResponse::Accept {
 							conn_id: id,
+							remote_addr,
 							methods,
 							resources: &resources,
 							cfg,
@@ -197,6 +204,7 @@ enum HandshakeResponse<'a, M> {
 	},
 	Accept {
 		conn_id: ConnectionId,
+		remote_addr: SocketAddr,
 		methods: &'a Methods,
 		resources: &'a Resources,
 		cfg: &'a Settings,
@@ -225,20 +233,34 @@ where
 
 			Ok(())
 		}
-		HandshakeResponse::Accept { conn_id, methods, resources, cfg, stop_monitor, middleware } => {
+		HandshakeResponse::Accept { conn_id, remote_addr, methods, resources, cfg, stop_monitor, middleware } => {
 			tracing::debug!("Accepting new connection: {}", conn_id);
-			let key = {
+
+			if cfg.permessage_deflate {
+				let max_message_size = cfg.max_request_body_size as usize;
+				server.add_extension(Box::new(BoundedDeflate::new(DeflateMode::Server, max_message_size)));
+			}
+
+			for protocol in cfg.encodings.iter().filter_map(WsEncoding::subprotocol) {
+				server.add_protocol(protocol);
+			}
+
+			let key_and_encoding = {
 				let req = server.receive_request().await?;
 				let host_check = cfg.allowed_hosts.verify("Host", Some(req.headers().host));
 				let origin_check = cfg.allowed_origins.verify("Origin", req.headers().origin);
+				// The client lists the subprotocols in order of preference, only the supported ones are kept.
+				let encoding =
+					req.protocols().next().and_then(WsEncoding::from_subprotocol).unwrap_or(WsEncoding::Json);
 
-				host_check.and(origin_check).map(|()| req.key())
+				host_check.and(origin_check).map(|()| (req.key(), encoding))
 			};
 
-			match key {
-				Ok(key) => {
-					let accept = Response::Accept { key, protocol: None };
+			let encoding = match key_and_encoding {
+				Ok((key, encoding)) => {
+					let accept = Response::Accept { key, protocol: encoding.subprotocol() };
 					server.send_response(&accept).await?;
+					encoding
 				}
 				Err(error) => {
 					let reject = Response::Reject { status_code: 403 };
@@ -246,14 +268,17 @@ where
 
 					return Err(error);
 				}
-			}
+			};
 
 			let join_result = tokio::spawn(background_task(
 				server,
 				conn_id,
+				remote_addr,
 				methods.clone(),
 				resources.clone(),
 				cfg.max_request_body_size,
+				encoding,
+				cfg.access_log.clone(),
 				stop_monitor.clone(),
 				middleware,
 			))
@@ -267,41 +292,87 @@ where
 	}
 }
 
+type Socket = BufReader<BufWriter<Compat<TcpStream>>>;
+
+#[allow(clippy::too_many_arguments)]
 async fn background_task(
-	server: SokettoServer<'_, BufReader<BufWriter<Compat<tokio::net::TcpStream>>>>,
+	server: SokettoServer<'_, Socket>,
 	conn_id: ConnectionId,
+	remote_addr: SocketAddr,
 	methods: Methods,
 	resources: Resources,
 	max_request_body_size: u32,
+	encoding: WsEncoding,
+	access_log: Option<AccessLog>,
 	stop_server: StopMonitor,
 	middleware: impl Middleware,
 ) -> Result<(), Error> {
-	// And we can finally transition to a websocket background_task.
-	let mut builder = server.into_builder();
+	// And we can finally transition to a websocket background_task. The connection is built on top of a
+	// `QueuedSocket` so that the task sending the responses can write streaming responses as fragmented messages.
+	let mut server = server;
+	let buffer = server.take_buffer();
+	let extensions: Vec<_> = server.drain_extensions().filter(|extension| extension.is_enabled()).collect();
+	let (reader, mut writer) = server.into_inner().split();
+	let (socket, mut queued) = QueuedSocket::new(reader);
+
+	// Fragments are written as they are, streaming responses are buffered if they have to be compressed or encoded.
+	let streaming = extensions.is_empty() && !encoding.is_binary();
+
+	let mut builder = ConnectionBuilder::new(socket, DeflateMode::Server);
+	builder.set_buffer(buffer);
+	builder.add_extensions(extensions);
 	builder.set_max_message_size(max_request_body_size as usize);
 	let (mut sender, mut receiver) = builder.finish();
-	let (tx, mut rx) = mpsc::unbounded::<String>();
+	let (streams_tx, mut streams_rx) = mpsc::unbounded::<StreamingResponse>();
 	let stop_server2 = stop_server.clone();
-	let sink = MethodSink::new_with_limit(tx, max_request_body_size);
+	// Messages are serialized in the encoding of the connection.
+	let (mut sink, mut rx) = if encoding.is_binary() {
+		let (tx, rx) = mpsc::unbounded();
+		(MethodSink::new_encoded(encoding, tx, max_request_body_size), rx.map(Outgoing::Binary).left_stream())
+	} else {
+		let (tx, rx) = mpsc::unbounded();
+		(MethodSink::new_with_limit(tx, max_request_body_size), rx.map(Outgoing::Text).right_stream())
+	};
+	if streaming {
+		sink = sink.with_streaming(streams_tx);
+	}
 
 	middleware.on_connect();
 
 	// Send results back to the client.
 	tokio::spawn(async move {
 		while !stop_server2.shutdown_requested() {
-			match rx.next().await {
-				Some(response) => {
-					// If websocket message send fail then terminate the connection.
-					if let Err(err) = send_ws_message(&mut sender, response).await {
-						tracing::error!("WS transport error: {:?}; terminate connection", err);
-						break;
-					}
+			let result = tokio::select! {
+				response = rx.next() => match response {
+					Some(response) => match send_ws_message(&mut sender, response).await {
+						Ok(()) => write_queued(&mut writer, &mut queued).await.map_err(Into::into),
+						Err(err) => Err(err),
+					},
+					None => break,
+				},
+				Some(response) = streams_rx.next() => {
+					write_streaming_response(&mut writer, &mut queued, response, OpCode::Text).await.map_err(Into::into)
 				}
-				None => break,
+				// Control frames sent by the receiver, e.g. the answers to pings.
+				frames = queued.next() => match frames {
+					Some(frames) => match writer.write_all(&frames).await {
+						Ok(()) => write_queued(&mut writer, &mut queued).await.map_err(Into::into),
+						Err(err) => Err(err.into()),
+					},
+					None => break,
+				},
 			};
+			// If websocket message send fail then terminate the connection.
+			if let Err(err) = result {
+				let err: Error = err;
+				tracing::error!("WS transport error: {:?}; terminate connection", err);
+				break;
+			}
 		}
 		// terminate connection.
 		let _ = sender.close().await;
+		let _ = write_queued(&mut writer, &mut queued).await;
+		let _ = writer.close().await;
 		// NOTE(niklasad1): when the receiver is dropped no further requests or subscriptions
 		// will be possible.
 	});
@@ -310,18 +381,22 @@ async fn background_task(
 	let mut data = Vec::with_capacity(100);
 	let mut method_executors = FutureDriver::default();
 	let middleware = &middleware;
+	let access_log = &access_log;
+	let log_invalid =
+		move |data: &[u8]| access_log.as_ref().and_then(|log| log.start_invalid("ws", Some(remote_addr), data));
 
 	let result = loop {
 		data.clear();
 
-		{
+		let is_binary = {
 			// Need the extra scope to drop this pinned future and reclaim access to `data`
 			let receive = receiver.receive_data(&mut data);
 
 			tokio::pin!(receive);
 
-			if let Err(err) = method_executors.select_with(Monitored::new(receive, &stop_server)).await {
-				match err {
+			match method_executors.select_with(Monitored::new(receive, &stop_server)).await {
+				Ok(received) => received.is_binary(),
+				Err(err) => match err {
 					MonitoredError::Selector(SokettoError::Closed) => {
 						tracing::debug!("WS transport error: remote peer terminated the connection: {}", conn_id);
 						sink.close();
@@ -336,6 +411,11 @@ async fn background_task(
 						sink.send_error(Id::Null, ErrorCode::OversizedRequest.into());
 						continue;
 					}
+					MonitoredError::Selector(SokettoError::Extension(err)) if err.is::<InflatedTooLarge>() => {
+						tracing::warn!("WS transport error: {}", err);
+						sink.send_error(Id::Null, ErrorCode::OversizedRequest.into());
+						continue;
+					}
 					// These errors can not be gracefully handled, so just log them and terminate the connection.
 					MonitoredError::Selector(err) => {
 						tracing::error!("WS transport error: {:?} => terminating connection {}", err, conn_id);
@@ -343,107 +423,137 @@ async fn background_task(
 						break Err(err.into());
 					}
 					MonitoredError::Shutdown => break Ok(()),
-				};
-			};
+				},
+			}
 		};
 
 		tracing::debug!("recv {} bytes", data.len());
 
+		// Binary frames carry the negotiated encoding, text frames are always JSON.
+		let frame_encoding = if is_binary { encoding } else { WsEncoding::Json };
 		let request_start = middleware.on_request();
 
-		match data.get(0) {
-			Some(b'{') => {
-				if let Ok(req) = serde_json::from_slice::<Request>(&data) {
-					middleware.on_call(req.method.as_ref());
+		// JSON messages must start with an object or an array.
+		if !frame_encoding.is_binary() && !matches!(data.first(), Some(b'{') | Some(b'[')) {
+			sink.with_access_log(log_invalid(&data)).send_error(Id::Null, ErrorCode::ParseError.into());
+			continue;
+		}
 
-					tracing::debug!("recv method call={}", req.method);
-					tracing::trace!("recv: req={:?}", req);
-					match methods.execute_with_resources(&sink, req, conn_id, &resources) {
-						Ok((name, MethodResult::Sync(success))) => {
-							middleware.on_result(name, success, request_start);
-							middleware.on_response(request_start);
-						}
-						Ok((name, MethodResult::Async(fut))) => {
-							let request_start = request_start;
+		if !frame_encoding.is_batch(&data) {
+			let encoded;
+			let request = if frame_encoding.is_binary() {
+				encoded = frame_encoding.from_slice::<EncodedRequest>(&data).ok();
+				encoded.as_ref().map(EncodedRequest::as_request)
+			} else {
+				serde_json::from_slice::<Request>(&data).ok()
+			};
 
-							let fut = async move {
-								let success = fut.await;
-								middleware.on_result(name, success, request_start);
-								middleware.on_response(request_start);
-							};
+			if let Some(req) = request {
+				middleware.on_call(req.method.as_ref());
+				let route = methods.route(&req);
+				if let Some((name, _)) = route.filter(|(_, callback)| callback.is_deprecated()) {
+					middleware.on_deprecated_call(name);
+				}
 
-							method_executors.add(fut.boxed());
-						}
-						Err(name) => {
-							middleware.on_result(name.as_ref(), false, request_start);
+				tracing::debug!("recv method call={}", req.method);
+				tracing::trace!("recv: req={:?}", req);
+
+				let entry = access_log.as_ref().and_then(|log| log.start("ws", Some(remote_addr), &req));
+				let sink = sink.with_access_log(entry);
+
+				match methods.execute_routed(&sink, req, route, conn_id, &resources) {
+					Ok((name, MethodResult::Sync(success))) => {
+						middleware.on_result(name, success, request_start);
+						middleware.on_response(request_start);
+					}
+					Ok((name, MethodResult::Async(fut))) => {
+						let request_start = request_start;
+
+						let fut = async move {
+							let success = fut.await;
+							middleware.on_result(name, success, request_start);
 							middleware.on_response(request_start);
-						}
+						};
+
+						method_executors.add(fut.boxed());
+					}
+					Err(name) => {
+						middleware.on_result(name.as_ref(), false, request_start);
+						middleware.on_response(request_start);
 					}
-				} else {
-					let (id, code) = prepare_error(&data);
-					sink.send_error(id, code.into());
-					middleware.on_response(request_start);
 				}
+			} else {
+				let (id, code) = frame_encoding.prepare_error(&data);
+				sink.with_access_log(log_invalid(&data)).send_error(id, code.into());
+				middleware.on_response(request_start);
 			}
-			Some(b'[') => {
-				// Make sure the following variables are not moved into async closure below.
-				let d = std::mem::take(&mut data);
-				let resources = &resources;
-				let methods = &methods;
-				let sink = sink.clone();
-
-				let fut = async move {
-					// Batch responses must be sent back as a single message so we read the results from each
-					// request in the batch and read the results off of a new channel, `rx_batch`, and then send the
-					// complete batch response back to the client over `tx`.
-					let (tx_batch, mut rx_batch) = mpsc::unbounded();
-					let sink_batch = MethodSink::new_with_limit(tx_batch, max_request_body_size);
-					if let Ok(batch) = serde_json::from_slice::<Vec<Request>>(&d) {
-						tracing::debug!("recv batch len={}", batch.len());
-						tracing::trace!("recv: batch={:?}", batch);
-						if !batch.is_empty() {
-							join_all(batch.into_iter().filter_map(move |req| {
-								match methods.execute_with_resources(&sink_batch, req, conn_id, resources) {
-									Ok((name, MethodResult::Sync(success))) => {
-										middleware.on_result(name, success, request_start);
-										None
-									}
-									Ok((name, MethodResult::Async(fut))) => Some(async move {
-										let success = fut.await;
-										middleware.on_result(name, success, request_start);
-									}),
-									Err(name) => {
-										middleware.on_result(name.as_ref(), false, request_start);
-										None
-									}
-								}
-							}))
-							.await;
+		} else {
+			// Make sure the following variables are not moved into async closure below.
+			let d = std::mem::take(&mut data);
+			let resources = &resources;
+			let methods = &methods;
+			let sink = sink.clone();
+
+			let fut = async move {
+				// Batch responses must be sent back as a single message so we read the results from each
+				// request in the batch and read the results off of a new channel, and then send the
+				// complete batch response back to the client over `sink`.
+				let (sink_batch, responses) = BatchResponses::sink(encoding, max_request_body_size);
+				let encoded;
+				let batch = if frame_encoding.is_binary() {
+					encoded = frame_encoding.from_slice::<Vec<EncodedRequest>>(&d).ok();
+					encoded.as_ref().map(|batch| batch.iter().map(EncodedRequest::as_request).collect::<Vec<_>>())
+				} else {
+					serde_json::from_slice::<Vec<Request>>(&d).ok()
+				};
 
-							rx_batch.close();
-							let results = collect_batch_response(rx_batch).await;
+				if let Some(batch) = batch {
+					tracing::debug!("recv batch len={}", batch.len());
+					tracing::trace!("recv: batch={:?}", batch);
+					if !batch.is_empty() {
+						join_all(batch.into_iter().filter_map(move |req| {
+							let entry = access_log.as_ref().and_then(|log| log.start("ws", Some(remote_addr), &req));
+							let sink_batch = sink_batch.with_access_log(entry);
+
+							let route = methods.route(&req);
+							if let Some((name, _)) = route.filter(|(_, callback)| callback.is_deprecated()) {
+								middleware.on_deprecated_call(name);
+							}
 
-							if let Err(err) = sink.send_raw(results) {
-								tracing::error!("Error sending batch response to the client: {:?}", err)
-							} else {
-								middleware.on_response(request_start);
+							match methods.execute_routed(&sink_batch, req, route, conn_id, resources) {
+								Ok((name, MethodResult::Sync(success))) => {
+									middleware.on_result(name, success, request_start);
+									None
+								}
+								Ok((name, MethodResult::Async(fut))) => Some(async move {
+									let success = fut.await;
+									middleware.on_result(name, success, request_start);
+								}),
+								Err(name) => {
+									middleware.on_result(name.as_ref(), false, request_start);
+									None
+								}
 							}
+						}))
+						.await;
+
+						if let Err(err) = responses.send(&sink).await {
+							tracing::error!("Error sending batch response to the client: {:?}", err)
 						} else {
-							sink.send_error(Id::Null, ErrorCode::InvalidRequest.into());
 							middleware.on_response(request_start);
 						}
 					} else {
-						let (id, code) = prepare_error(&d);
-						sink.send_error(id, code.into());
+						sink.with_access_log(log_invalid(&d)).send_error(Id::Null, ErrorCode::InvalidRequest.into());
 						middleware.on_response(request_start);
 					}
-				};
+				} else {
+					let (id, code) = frame_encoding.prepare_error(&d);
+					sink.with_access_log(log_invalid(&d)).send_error(id, code.into());
+					middleware.on_response(request_start);
+				}
+			};
 
-				method_executors.add(Box::pin(fut));
-			}
-			_ => {
-				sink.send_error(Id::Null, ErrorCode::ParseError.into());
-			}
+			method_executors.add(Box::pin(fut));
 		}
 	};
 
@@ -490,6 +600,12 @@ struct Settings {
 	allowed_hosts: AllowedValue,
 	/// Custom tokio runtime to run the server on.
 	tokio_runtime: Option<tokio::runtime::Handle>,
+	/// Access log configuration.
+	access_log: Option<AccessLog>,
+	/// Whether the `permessage-deflate` extension is offered to clients.
+	permessage_deflate: bool,
+	/// Binary encodings clients may select through the WebSocket subprotocol.
+	encodings: Vec<WsEncoding>,
 }
 
 impl Default for Settings {
@@ -500,6 +616,9 @@ impl Default for Settings {
 			allowed_origins: AllowedValue::Any,
 			allowed_hosts: AllowedValue::Any,
 			tokio_runtime: None,
+			access_log: None,
+			permessage_deflate: false,
+			encodings: Vec::new(),
 		}
 	}
 }
@@ -532,6 +651,25 @@ impl<M> Builder<M> {
 		self
 	}
 
+	/// Enable the `permessage-deflate` extension (RFC 7692) for clients that request it during the handshake.
+	/// Default is disabled.
+	///
+	/// The [`max_request_body_size`](Builder::max_request_body_size) limit applies to decompressed messages.
+	pub fn permessage_deflate(mut self, enabled: bool) -> Self {
+		self.settings.permessage_deflate = enabled;
+		self
+	}
+
+	/// Allow clients to select the binary `encoding` during the handshake, see [`WsEncoding`] for details.
+	///
+	/// By default only JSON is supported. Enabling [`WsEncoding::Json`] has no effect.
+	pub fn enable_encoding(mut self, encoding: WsEncoding) -> Self {
+		if encoding.is_binary() && !self.settings.encodings.contains(&encoding) {
+			self.settings.encodings.push(encoding);
+		}
+		self
+	}
+
 	/// Set the maximum number of connections allowed. Default is 100.
 	pub fn max_connections(mut self, max: u64) -> Self {
 		self.settings.max_connections = max;
@@ -566,7 +704,7 @@ impl<M> Builder<M> {
 		List: IntoIterator<Item = Origin>,
 		Origin: Into<String>,
 	{
-		let list: Box<_> = list.into_iter().map(Into::into).collect();
+		let list: Box<[_]> = list.into_iter().map(Into::into).collect();
 
 		if list.len() == 0 {
 			return Err(Error::EmptyAllowList("Origin"));
@@ -630,7 +768,7 @@ impl<M> Builder<M> {
 		List: IntoIterator<Item = Host>,
 		Host: Into<String>,
 	{
-		let list: Box<_> = list.into_iter().map(Into::into).collect();
+		let list: Box<[_]> = list.into_iter().map(Into::into).collect();
 
 		if list.len() == 0 {
 			return Err(Error::EmptyAllowList("Host"));
@@ -656,6 +794,20 @@ impl<M> Builder<M> {
 		self
 	}
 
+	/// Enables structured access logging, one record per JSON-RPC call.
+	///
+	/// See [`AccessLog`](../jsonrpsee_utils/server/access_log/struct.AccessLog.html) for the available options.
+	///
+	/// ```
+	/// use jsonrpsee_ws_server::{AccessLog, WsServerBuilder};
+	///
+	/// let builder = WsServerBuilder::new().set_access_log(AccessLog::new().sample_rate(0.5));
+	/// ```
+	pub fn set_access_log(mut self, access_log: AccessLog) -> Self {
+		self.settings.access_log = Some(access_log);
+		self
+	}
+
 	/// Finalize the configuration of the server. Consumes the [`Builder`].
 	///
 	/// ```rust
@@ -680,12 +832,57 @@ impl<M> Builder<M> {
 	}
 }
 
-async fn send_ws_message(
-	sender: &mut Sender<BufReader<BufWriter<Compat<TcpStream>>>>,
-	response: String,
-) -> Result<(), Error> {
-	tracing::debug!("send {} bytes", response.len());
-	tracing::trace!("send: {}", response);
-	sender.send_text_owned(response).await?;
+/// Message sent to the client, in a text or a binary frame.
+enum Outgoing {
+	Text(String),
+	Binary(Vec<u8>),
+}
+
+/// Receiver of the responses to the calls of a batch.
+enum BatchResponses {
+	Json(mpsc::UnboundedReceiver<String>),
+	Encoded(WsEncoding, mpsc::UnboundedReceiver<Vec<u8>>),
+}
+
+impl BatchResponses {
+	/// Create a sink for the calls of a batch, which serializes their responses in `encoding`.
+	fn sink(encoding: WsEncoding, max_response_size: u32) -> (MethodSink, Self) {
+		if encoding.is_binary() {
+			let (tx, rx) = mpsc::unbounded();
+			(MethodSink::new_encoded(encoding, tx, max_response_size), BatchResponses::Encoded(encoding, rx))
+		} else {
+			let (tx, rx) = mpsc::unbounded();
+			(MethodSink::new_with_limit(tx, max_response_size), BatchResponses::Json(rx))
+		}
+	}
+
+	/// Send the responses received so far to `sink` as a single message.
+	async fn send(self, sink: &MethodSink) -> Result<(), Error> {
+		match self {
+			BatchResponses::Json(mut rx) => {
+				rx.close();
+				sink.send_raw(collect_batch_response(rx).await)
+			}
+			BatchResponses::Encoded(encoding, mut rx) => {
+				rx.close();
+				let responses: Vec<_> = rx.collect().await;
+				sink.send_encoded(encoding.encode_batch(&responses))
+			}
+		}
+	}
+}
+
+async fn send_ws_message(sender: &mut Sender<QueuedSocket<ReadHalf<Socket>>>, message: Outgoing) -> Result<(), Error> {
+	match message {
+		Outgoing::Text(response) => {
+			tracing::debug!("send {} bytes", response.len());
+			tracing::trace!("send: {}", response);
+			sender.send_text_owned(response).await?;
+		}
+		Outgoing::Binary(response) => {
+			tracing::debug!("send {} bytes", response.len());
+			sender.send_binary_mut(response).await?;
+		}
+	}
 	sender.flush().await.map_err(Into::into)
 }
@@ -0,0 +1,255 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+//
+// Permission is hereby granted, free of charge, to any
+// person obtaining a copy of this software and associated
+// documentation files (the "Software"), to deal in the
+// Software without restriction, including without
+// limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of
+// the Software, and to permit persons to whom the Software
+// is furnished to do so, subject to the following
+// conditions:
+//
+// The above copyright notice and this permission notice
+// shall be included in all copies or substantial portions
+// of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF
+// ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED
+// TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT
+// SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+// CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR
+// IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+//! Streaming responses sent as fragmented WebSocket messages.
+//!
+//! `soketto` can only send complete messages, so the task writing to the connection owns the socket: `soketto`
+//! writes to a [`QueuedSocket`] whose frames are handed to that task once flushed, and the fragments of the
+//! streaming responses are written to the socket directly, with only the control frames `soketto` answers with
+//! in between.
+
+use futures_channel::mpsc;
+use futures_util::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use jsonrpsee_utils::server::helpers::StreamingResponse;
+use soketto::base::{Codec, Header, OpCode};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Maximum size of the fragments of a streaming response.
+pub(crate) const FRAGMENT_SIZE: usize = 64 * 1024;
+
+/// Socket reading from the connection, which queues the frames written to it until they are flushed.
+#[derive(Debug)]
+pub(crate) struct QueuedSocket<R> {
+	reader: R,
+	buf: Vec<u8>,
+	frames: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl<R> QueuedSocket<R> {
+	/// Create a socket reading from `reader`, returns the receiver of the flushed frames. The receiver ends once the
+	/// socket is closed.
+	pub(crate) fn new(reader: R) -> (Self, mpsc::UnboundedReceiver<Vec<u8>>) {
+		let (frames, rx) = mpsc::unbounded();
+		(Self { reader, buf: Vec::new(), frames }, rx)
+	}
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for QueuedSocket<R> {
+	fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+	}
+}
+
+impl<R: Unpin> AsyncWrite for QueuedSocket<R> {
+	fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+		self.get_mut().buf.extend_from_slice(data);
+		Poll::Ready(Ok(data.len()))
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+		let this = self.get_mut();
+		if !this.buf.is_empty() {
+			let frames = std::mem::take(&mut this.buf);
+			this.frames.unbounded_send(frames).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+		}
+		Poll::Ready(Ok(()))
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		let this = self.get_mut();
+		let flushed = Pin::new(&mut *this).poll_flush(cx);
+		this.frames.close_channel();
+		flushed
+	}
+}
+
+/// Write the frames queued so far to the `writer`.
+pub(crate) async fn write_queued<W: AsyncWrite + Unpin>(
+	writer: &mut W,
+	queued: &mut mpsc::UnboundedReceiver<Vec<u8>>,
+) -> io::Result<()> {
+	while let Some(Some(frames)) = queued.next().now_or_never() {
+		writer.write_all(&frames).await?;
+	}
+	writer.flush().await
+}
+
+/// Serialize the `response` on a blocking thread and write it to the `writer` as a message of `opcode` fragmented
+/// in frames of [`FRAGMENT_SIZE`] bytes at most, writing the `queued` frames in between.
+///
+/// No message must be queued meanwhile, only control frames can be sent within a fragmented message. The message
+/// is left incomplete if the serialization fails or exceeds the maximum response size, the connection must then be
+/// closed.
+pub(crate) async fn write_streaming_response<W: AsyncWrite + Unpin>(
+	writer: &mut W,
+	queued: &mut mpsc::UnboundedReceiver<Vec<u8>>,
+	response: StreamingResponse,
+	opcode: OpCode,
+) -> io::Result<()> {
+	let (tx, mut fragments) = mpsc::channel(0);
+	let mut fragment_writer =
+		FragmentWriter { runtime: tokio::runtime::Handle::current(), fragments: tx, buf: Vec::new() };
+	let serialized = tokio::task::spawn_blocking(move || {
+		response.write_to(&mut fragment_writer)?;
+		fragment_writer.send_fragment()
+	});
+
+	let mut codec = Codec::new();
+	let mut opcode = opcode;
+	// The last fragment is written once the serialization is over, with the FIN bit set.
+	let mut pending: Option<Vec<u8>> = None;
+	loop {
+		tokio::select! {
+			fragment = fragments.next() => match fragment {
+				Some(fragment) => {
+					if let Some(previous) = pending.replace(fragment) {
+						write_frame(writer, &mut codec, opcode, false, &previous).await?;
+						opcode = OpCode::Continue;
+					}
+				}
+				None => break,
+			},
+			frames = queued.next() => match frames {
+				Some(frames) => {
+					writer.write_all(&frames).await?;
+					writer.flush().await?;
+				}
+				None => return Err(io::ErrorKind::ConnectionAborted.into()),
+			},
+		}
+	}
+
+	serialized.await.map_err(io::Error::other)??;
+	write_frame(writer, &mut codec, opcode, true, &pending.unwrap_or_default()).await
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(
+	writer: &mut W,
+	codec: &mut Codec,
+	opcode: OpCode,
+	fin: bool,
+	data: &[u8],
+) -> io::Result<()> {
+	let mut header = Header::new(opcode);
+	header.set_fin(fin);
+	header.set_payload_len(data.len());
+	writer.write_all(codec.encode_header(&header)).await?;
+	writer.write_all(data).await?;
+	writer.flush().await
+}
+
+/// Writer that sends the data written to it to the task writing to the connection in fragments of
+/// [`FRAGMENT_SIZE`] bytes, waiting for each fragment to be taken before serializing further.
+struct FragmentWriter {
+	runtime: tokio::runtime::Handle,
+	fragments: mpsc::Sender<Vec<u8>>,
+	buf: Vec<u8>,
+}
+
+impl FragmentWriter {
+	fn send_fragment(&mut self) -> io::Result<()> {
+		if self.buf.is_empty() {
+			return Ok(());
+		}
+		let fragment = std::mem::replace(&mut self.buf, Vec::with_capacity(FRAGMENT_SIZE));
+		self.runtime
+			.block_on(self.fragments.send(fragment))
+			.map_err(|err| io::Error::new(io::ErrorKind::BrokenPipe, err))
+	}
+}
+
+impl io::Write for FragmentWriter {
+	fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+		let len = data.len().min(FRAGMENT_SIZE - self.buf.len());
+		self.buf.extend_from_slice(&data[..len]);
+		if self.buf.len() == FRAGMENT_SIZE {
+			self.send_fragment()?;
+		}
+		Ok(len)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		// The last fragment is sent once the response is complete.
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{write_streaming_response, QueuedSocket, FRAGMENT_SIZE};
+	use crate::types::v2::Id;
+	use futures_util::io::{AsyncWriteExt, Cursor};
+	use futures_util::StreamExt;
+	use jsonrpsee_utils::server::helpers::MethodSink;
+	use soketto::base::{Codec, OpCode};
+	use soketto::Parsing;
+
+	#[tokio::test]
+	async fn streaming_response_is_fragmented() {
+		let (tx, _rx) = futures_channel::mpsc::unbounded();
+		let (streams_tx, mut streams_rx) = futures_channel::mpsc::unbounded();
+		let sink = MethodSink::new(tx).with_streaming(streams_tx);
+		assert!(sink.send_streaming_response(Id::Number(1), "x".repeat(2 * FRAGMENT_SIZE), None));
+		let response = streams_rx.next().await.unwrap();
+
+		// A control frame queued while the response is written is sent between its fragments.
+		let (mut socket, mut queued) = QueuedSocket::new(Cursor::new(Vec::<u8>::new()));
+		socket.write_all(b"pong").await.unwrap();
+		socket.flush().await.unwrap();
+
+		let mut written = Vec::new();
+		write_streaming_response(&mut written, &mut queued, response, OpCode::Text).await.unwrap();
+
+		let codec = Codec::new();
+		let mut frames = Vec::new();
+		let mut rest = &written[..];
+		while !rest.is_empty() {
+			if rest.starts_with(b"pong") {
+				rest = &rest[4..];
+				continue;
+			}
+			let (header, len) = match codec.decode_header(rest).unwrap() {
+				Parsing::Done { value, offset } => (value, offset),
+				Parsing::NeedMore(_) => panic!("Incomplete frame"),
+			};
+			frames.push((header.opcode(), header.is_fin(), header.payload_len()));
+			rest = &rest[len + header.payload_len()..];
+		}
+
+		let len = r#"{"jsonrpc":"2.0","result":"","id":1}"#.len() + 2 * FRAGMENT_SIZE;
+		assert_eq!(
+			frames,
+			[
+				(OpCode::Text, false, FRAGMENT_SIZE),
+				(OpCode::Continue, false, FRAGMENT_SIZE),
+				(OpCode::Continue, true, len - 2 * FRAGMENT_SIZE)
+			]
+		);
+	}
+}
@@ -629,3 +629,61 @@ async fn unsubscribe_wrong_sub_id_type() {
 	let err = Some(to_json_raw_value(&"Invalid subscription ID type, must be integer").unwrap());
 	assert_eq!(unsub_2_err, RpcError::new(invalid_subscription_err(err.as_deref()), v2::Id::Number(0)));
 }
+
+#[tokio::test]
+async fn access_log_records_each_call() {
+	init_logger();
+	let records = std::sync::Arc::new(AccessLogRecords::default());
+	let access_log = crate::AccessLog::new().log_params(true).redact_params(["secret"]).writer(records.clone());
+	let server = WsServerBuilder::default().set_access_log(access_log).build("127.0.0.1:0").await.unwrap();
+	let mut module = RpcModule::new(());
+	module.register_async_method("login", |_, _| async move { Ok(true) }).unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module).unwrap();
+	let mut client = WebSocketTestClient::new(addr).with_default_timeout().await.unwrap().unwrap();
+
+	let req = r#"{"jsonrpc":"2.0","method":"login","params":{"user":"bob","secret":"1234"},"id":1}"#;
+	let response = client.send_request_text(req).with_default_timeout().await.unwrap().unwrap();
+	client.send_request_text(call("nope", Vec::<()>::new(), Id::Num(2))).with_default_timeout().await.unwrap().unwrap();
+	let req = r#"{"jsonrpc":"2.0","method":"login","id":{}}"#;
+	client.send_request_text(req).with_default_timeout().await.unwrap().unwrap();
+
+	let records = records.0.lock().unwrap();
+	assert_eq!(records.len(), 3);
+	assert_eq!(records[0]["transport"], "ws");
+	assert_eq!(records[0]["method"], "login");
+	assert_eq!(records[0]["params"], serde_json::json!({"user": "bob", "secret": "<redacted>"}));
+	assert_eq!(records[0]["response_size"], response.len());
+	assert_eq!(records[1]["method"], "nope");
+	assert_eq!(records[1]["success"], false);
+	assert_eq!(records[1]["error_code"], -32601);
+	assert_eq!(records[2]["method"], "login");
+	assert_eq!(records[2]["error_code"], -32700);
+}
+
+#[tokio::test]
+async fn streaming_responses_are_fragmented() {
+	init_logger();
+	let server = WsServerBuilder::default().max_request_body_size(200 * 1024).build("127.0.0.1:0").await.unwrap();
+	let mut module = RpcModule::new(());
+	module
+		.register_streaming_method("blob", |params, _| async move {
+			let len: usize = params.one()?;
+			Ok(vec![7_u8; len])
+		})
+		.unwrap();
+	let addr = server.local_addr().unwrap();
+	let _handle = server.start(module).unwrap();
+
+	// The client rejects frames larger than the fragments sent by the server.
+	let mut client =
+		WebSocketTestClient::with_max_frame_size(addr, 64 * 1024).with_default_timeout().await.unwrap().unwrap();
+	let response =
+		client.send_request_text(call("blob", vec![50_000], Id::Num(1))).with_default_timeout().await.unwrap();
+	assert_eq!(response.unwrap(), ok_response(vec![7_u8; 50_000].into(), Id::Num(1)));
+
+	// The limit is enforced while the message is sent, which closes the connection.
+	let response =
+		client.send_request_text(call("blob", vec![200_000], Id::Num(2))).with_default_timeout().await.unwrap();
+	assert!(response.is_err());
+}